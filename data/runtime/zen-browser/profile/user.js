@@ -17,4 +17,7 @@ user_pref("browser.translations.automaticallyPopup", false);
 user_pref("browser.link.open_newwindow", 2);
 user_pref("datareporting.policy.firstRunURL","");
 user_pref("zen.welcomeScreen.enabled", false);
-user_pref("zen.view.compact",true);
\ No newline at end of file
+user_pref("zen.view.compact",true);
+user_pref("zen.workspaces.enabled", false);
+user_pref("zen.view.compact.hide-tabbar", true);
+user_pref("zen.view.sidebar-expanded", false);
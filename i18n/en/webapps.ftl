@@ -17,8 +17,40 @@ settings=Settings
 import-theme=Import theme
 imported-themes=Imported themes
 run-app=Run app
+migrate-icons=Convert icons made by older versions
+migrate=Convert
+migrated-icons={ $count ->
+    [one] Converted 1 icon
+   *[other] Converted { $count } icons
+}
+clean-profiles=Remove profiles of deleted web apps
+clean=Remove
+cleaned-profiles={ $count ->
+    [one] Removed 1 profile
+   *[other] Removed { $count } profiles
+}
 reset-settings=Reset settings
 reset=Reset
+icon-format=Icon format
+icon-format-svg=SVG
+icon-format-png=PNG
+icon-format-hicolor=PNG icon theme set
+launch-method=Start web apps from this app
+launch-desktop-entry=Through the desktop (gtk-launch)
+launch-exec=Run the browser directly
+hashed-icon-names=Unique icon file names (avoids outdated cached icons)
+keep-original-icons=Keep original icon files
+compact-icons=Store icons without transparency as WebP (smaller, not supported by every desktop)
+isolate-profiles=Isolated profiles for new web apps
+menu-folder=Group web apps in a menu folder
+web-apps=Web Apps
+strip-tracking-params=Remove tracking parameters (like utm_source) from web app addresses
+offline-mode=Offline mode (don't download favicons)
+accept-invalid-icon-certs=Insecure: download icons from servers with invalid certificates
+favicon-service-fallback=Ask DuckDuckGo for icons no other source has (shares the site address)
+icon-search-depth=Icon search depth
+max-download-size=Maximum icon download size
+shown-browsers=Browsers shown in the editor
 
 # header
 main-window={ $app }
@@ -57,9 +89,44 @@ browser=Browser
 
 new-webapp-title=New Quick Web App
 title=Title
+profile-source=Browser profile
+profile-new=New profile
+profile-copy-default=Copy of default profile
+profile-reuse-default=Default profile
+profile-dir=Profile location (optional), e.g. /mnt/ssd/profiles
+invalid-profile-dir=Profile location has to be an absolute path or start with ~/
+profile-extensions-copied=Extensions and their settings are copied from your default profile once, cookies, logins and history are not. Extension data, like a password manager's session, is then available to this site.
+profile-default-shared=The web app uses your default profile with all its extensions, logins and history, it is not isolated from your browser.
+verify-url=Verify
+url-reachable=The address is reachable
+url-redirected=The address redirects to { $url }
+use-redirect=Use it
+url-auth-required=The site requires signing in, sign in once in the web app and let the browser remember the password
+url-credentials=The user name and password are removed from the address, so they aren't stored in plain text
+url-not-found=The page doesn't exist
+url-network-error=Can't reach the address: { $error }
+url-check-skipped=Not verified in offline mode
+duplicate-webapp={ $name } already opens this page in the same browser
+packaging-conflict={ $name } opens this page in { $browser }, which keeps its own profile, so logins aren't shared between them
+foreign-webapps=Web apps created by other tools already open this page, you may want to remove them: { $apps }
+edit-existing=Edit it
+create-anyway=Create anyway
+install-pwa=Start the app installed from the browser's menu when there is one
+single-window=Single main window
+badges=Unread count badges (experimental)
+window-title=Window title
+window-size=Window size, e.g. 1280x800
+invalid-window-size=Window size has to be two positive numbers, like 1280x800
+window-position=Window position on the desktop, e.g. 1920,0
+invalid-window-position=Window position has to be two numbers, like 1920,0
+start-maximized=Start maximized
 url=URL
 download-favicon=Download favicon
 non-standard-arguments=Non-standard arguments
+extra-urls=Additional URLs opened as tabs, separated by spaces
+url-schemes=Open links of these schemes, e.g. slack zoommtg
+invalid-url-schemes=Some schemes are invalid or too broad (like http or mailto) and will be ignored
+new-windows-as-tabs=Open links to new windows as tabs of the web app
 # keep navbar, isolated profile nad private mode small count of characters
 navbar=Nav Bar
 isolated-profile=Isolated Profile
@@ -70,6 +137,19 @@ icon-name-to-find=Icon name to find
 my-icons=My icons
 download=Download
 search=Search
+refresh=Refresh
+use-icon-name=Use by name
+icons-from-website=Website
+icons-from-user=Your icons
+icons-from-system=System
+icons-symbolic=Symbolic
+favicon-lookup-skipped=Favicons aren't downloaded in offline mode
+favicon-lookup-failed=Favicon download failed: { $error }
+icon-save-failed=Can't save the icon: { $error }
+create-failed=Can't create the web app: { $error }
+icons-dir-unreadable=Can't read icons in { $path }: { $error }
+named-icon-missing=There is no icon named { $name } in your icon themes
+generate-icon=Generate
 
 # icons_installator.rs
 icons-installer-header=Please wait. Downloading icons...
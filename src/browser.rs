@@ -1,25 +1,253 @@
-use crate::{common::fd_entries, LOCALES};
+use crate::{
+    common::{
+        brew_prefixes, exec_arg, fd_entries, home_dir, is_sandboxed, quote_exec_arg, webapp_id,
+        DataPaths,
+    },
+    config::AppConfig,
+    error::Error,
+    fl, LOCALES,
+};
 use freedesktop_desktop_entry::{matching::find_entry_from_appid, DesktopEntry, PathSource};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{
-    fs::{create_dir_all, remove_file, File},
+    collections::HashMap,
+    fs::{self, create_dir_all, remove_file, File},
     io::Write,
-    path::PathBuf,
-    str::FromStr,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
 };
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
+use walkdir::WalkDir;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumIter, Deserialize, Serialize)]
+pub enum ProfileSource {
+    #[default]
+    New,
+    CopyDefault,
+    ReuseDefault,
+}
+
+impl AsRef<str> for ProfileSource {
+    fn as_ref(&self) -> &str {
+        match self {
+            ProfileSource::New => "new",
+            ProfileSource::CopyDefault => "copy-default",
+            ProfileSource::ReuseDefault => "reuse-default",
+        }
+    }
+}
+
+impl ProfileSource {
+    pub fn from(value: &str) -> Self {
+        ProfileSource::iter()
+            .find(|source| source.as_ref() == value)
+            .unwrap_or_default()
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            ProfileSource::New => fl!("profile-new"),
+            ProfileSource::CopyDefault => fl!("profile-copy-default"),
+            ProfileSource::ReuseDefault => fl!("profile-reuse-default"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirefoxProfile {
+    pub name: String,
+    pub path: PathBuf,
+    pub default: bool,
+}
+
+// Parses `profiles.ini`, resolving relative profile paths against `base`.
+// The profile locked by an `[Install...]` section is the one Firefox
+// really starts with, so it's marked as default before the legacy `Default=1`.
+pub fn parse_profiles_ini(content: &str, base: &Path) -> Vec<FirefoxProfile> {
+    let mut profiles = Vec::new();
+    let mut install_defaults = Vec::new();
+
+    let mut section = String::new();
+    let mut name = String::new();
+    let mut path = String::new();
+    let mut relative = true;
+    let mut default = false;
+
+    let mut flush = |section: &str, name: &str, path: &str, relative: bool, default: bool| {
+        if section.starts_with("Profile") && !path.is_empty() {
+            profiles.push(FirefoxProfile {
+                name: name.to_string(),
+                path: if relative {
+                    base.join(path)
+                } else {
+                    PathBuf::from(path)
+                },
+                default,
+            });
+        }
+    };
+
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') && line.ends_with(']') {
+            flush(&section, &name, &path, relative, default);
+
+            section = line[1..line.len() - 1].to_string();
+            name.clear();
+            path.clear();
+            relative = true;
+            default = false;
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+
+        match (key.trim(), value.trim()) {
+            ("Name", value) => name = value.to_string(),
+            ("Path", value) => path = value.to_string(),
+            ("IsRelative", value) => relative = value != "0",
+            ("Default", value) if section.starts_with("Install") => {
+                install_defaults.push(value.to_string())
+            }
+            ("Default", value) => default = value == "1",
+            _ => (),
+        }
+    }
+
+    flush(&section, &name, &path, relative, default);
+
+    if let Some(install_default) = install_defaults.first() {
+        let install_path = base.join(install_default);
+
+        if profiles.iter().any(|profile| profile.path == install_path) {
+            for profile in profiles.iter_mut() {
+                profile.default = profile.path == install_path;
+            }
+        }
+    }
+
+    profiles
+}
+
+pub fn default_firefox_profile() -> Option<FirefoxProfile> {
+    let home = home_dir()?;
+
+    [
+        home.join(".mozilla/firefox"),
+        home.join(".var/app/org.mozilla.firefox/.mozilla/firefox"),
+    ]
+    .into_iter()
+    .find_map(|base| {
+        let content = fs::read_to_string(base.join("profiles.ini")).ok()?;
+        let profiles = parse_profiles_ini(&content, &base);
+
+        profiles
+            .iter()
+            .find(|profile| profile.default)
+            .or(profiles.first())
+            .cloned()
+    })
+}
+
+// Profile data of Chromium based browsers making up the installed extensions,
+// copied into new isolated profiles. Cookies, logins and history are left out.
+const CHROMIUM_EXTENSION_DATA: [&str; 6] = [
+    "Extensions",
+    "Extension Rules",
+    "Extension State",
+    "Local Extension Settings",
+    "Preferences",
+    "Secure Preferences",
+];
+
+// Default profile of a Chromium based browser, `Default` inside its user data dir.
+pub fn default_chromium_profile(model: &BrowserModel, appid: &str) -> Option<PathBuf> {
+    let config_dir = model.chromium_config_dir()?;
+    let home = home_dir()?;
+
+    [
+        home.join(".config").join(config_dir),
+        home.join(".var/app")
+            .join(appid)
+            .join("config")
+            .join(config_dir),
+    ]
+    .into_iter()
+    .map(|base| base.join("Default"))
+    .find(|profile| profile.join("Preferences").exists())
+}
+
+// Seeds the `Default` profile of a new user data dir with the extensions of `from`.
+fn seed_chromium_extensions(from: &Path, user_data_dir: &Path) {
+    let to = user_data_dir.join("Default");
+
+    if let Err(err) = create_dir_all(&to) {
+        tracing::warn!("can't create {:?}: {}", to, err);
+        return;
+    }
+
+    for name in CHROMIUM_EXTENSION_DATA {
+        let source = from.join(name);
+
+        if source.is_dir() {
+            copy_profile(&source, &to.join(name));
+        } else if source.is_file() {
+            if let Err(err) = fs::copy(&source, to.join(name)) {
+                tracing::warn!("failed to copy {:?}: {}", source, err);
+            }
+        }
+    }
+}
+
+// Copies the profile files, leaving out the locks of a running Firefox.
+pub fn copy_profile(from: &Path, to: &Path) {
+    for entry in WalkDir::new(from).into_iter().filter_map(|e| e.ok()) {
+        let Ok(relative) = entry.path().strip_prefix(from) else {
+            continue;
+        };
+
+        if matches!(
+            relative.to_str(),
+            Some("lock") | Some(".parentlock") | Some("parent.lock")
+        ) {
+            continue;
+        }
+
+        let target = to.join(relative);
+
+        let result = if entry.file_type().is_dir() {
+            create_dir_all(&target)
+        } else {
+            fs::copy(entry.path(), &target).map(|_| ())
+        };
+
+        if let Err(err) = result {
+            tracing::warn!("failed to copy {:?}: {}", entry.path(), err);
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Firefox {
     pub exec: String,
     pub codename: String,
+    pub window_title: String,
     pub url: String,
+    pub extra_urls: Vec<String>,
     pub user_dir: String,
     pub navbar: bool,
+    pub new_windows_as_tabs: bool,
     pub isolated: bool,
     pub zen_browser: bool,
     pub private: bool,
+    pub base_profile: Option<PathBuf>,
+    pub reuse_profile: bool,
+    pub window_size: Option<(u32, u32)>,
     pub custom_args: String,
 }
 
@@ -28,21 +256,31 @@ impl Firefox {
         Self {
             exec: browser_exec,
             codename: String::new(),
+            window_title: String::new(),
             url: String::new(),
+            extra_urls: Vec::new(),
             user_dir: String::new(),
             navbar: false,
+            new_windows_as_tabs: false,
             isolated: true,
             zen_browser: false,
             private: false,
+            base_profile: None,
+            reuse_profile: false,
+            window_size: None,
             custom_args: String::new(),
         }
     }
 
     pub fn codename(&mut self, codename: String) -> &mut Self {
-        self.codename = format!(
-            "--class dev.heppen.webapps.{} --name dev.heppen.webapps.{}",
-            codename, codename
-        );
+        self.codename = webapp_id(&codename);
+        self
+    }
+
+    // Firefox uses `--name` as the window name, while `--class`
+    // still matches the window with its desktop file.
+    pub fn window_title(&mut self, title: String) -> &mut Self {
+        self.window_title = title;
         self
     }
 
@@ -51,36 +289,61 @@ impl Firefox {
         self
     }
 
+    // opened as tabs next to the main url
+    pub fn extra_urls(&mut self, urls: Vec<String>) -> &mut Self {
+        self.extra_urls = urls;
+        self
+    }
+
     pub fn navbar(&mut self, flag: bool) -> &mut Self {
         self.navbar = flag;
         self
     }
 
+    // Links a site opens in a new window become tabs of the web app's
+    // window instead of new windows looking like the web app.
+    pub fn new_windows_as_tabs(&mut self, flag: bool) -> &mut Self {
+        self.new_windows_as_tabs = flag;
+        self
+    }
+
     pub fn isolated(&mut self, flag: bool) -> &mut Self {
         self.isolated = flag;
         self
     }
 
-    fn create_firefox_userjs(&self, path: PathBuf) {
-        let file = File::create(path.join("user.js"));
+    fn user_prefs(&self) -> String {
+        let mut prefs = format!(
+            "user_pref(\"browser.tabs.inTitlebar\", {});\n",
+            if self.navbar { 2 } else { 0 }
+        );
 
-        if let Ok(mut file) = file {
-            let navbar_pref = if self.navbar {
-                b"user_pref(\"browser.tabs.inTitlebar\", 2);\n"
-            } else {
-                b"user_pref(\"browser.tabs.inTitlebar\", 0);\n"
-            };
-            file.write_all(navbar_pref).unwrap();
+        if self.zen_browser {
+            // compact mode keeps the toolbar only when the navbar is wanted
+            prefs.push_str(&format!(
+                "user_pref(\"zen.view.compact.hide-toolbar\", {});\n",
+                !self.navbar
+            ));
+            prefs.push_str(include_str!("../data/runtime/zen-browser/profile/user.js"));
+        } else {
+            prefs.push_str(include_str!("../data/runtime/firefox/profile/user.js"));
+        }
 
-            if self.zen_browser {
-                file.write_all(include_bytes!(
-                    "../data/runtime/zen-browser/profile/user.js"
-                ))
-                .unwrap();
-            } else {
-                file.write_all(include_bytes!("../data/runtime/firefox/profile/user.js"))
-                    .unwrap();
-            };
+        // later prefs win over the `open_newwindow` of the bundled user.js
+        if self.new_windows_as_tabs {
+            if !prefs.ends_with('\n') {
+                prefs.push('\n');
+            }
+            prefs.push_str("user_pref(\"browser.link.open_newwindow\", 3);\n");
+            prefs.push_str("user_pref(\"browser.link.open_newwindow.restriction\", 0);\n");
+        }
+
+        prefs
+    }
+
+    fn create_firefox_userjs(&self, path: PathBuf) {
+        if let Ok(mut file) = File::create(path.join("user.js")) {
+            file.write_all(self.user_prefs().as_bytes()).unwrap();
         }
     }
 
@@ -110,17 +373,57 @@ impl Firefox {
         }
     }
 
+    // Existing profile used as a starting point for the new one,
+    // or directly when `reuse_profile` is set.
+    pub fn base_profile(&mut self, path: Option<PathBuf>) -> &mut Self {
+        self.base_profile = path;
+        self
+    }
+
+    pub fn reuse_profile(&mut self, flag: bool) -> &mut Self {
+        self.reuse_profile = flag;
+        self
+    }
+
     pub fn profile_path(&mut self, path: PathBuf) -> &mut Self {
+        if self.reuse_profile {
+            if let Some(base) = &self.base_profile {
+                match base.to_str() {
+                    // the user's own profile is left untouched
+                    Some(base) => {
+                        self.isolated = false;
+                        self.user_dir = format!(" --profile {}", quote_exec_arg(base));
+                        return self;
+                    }
+                    // the desktop file can only reference UTF-8 paths
+                    None => {
+                        tracing::warn!(
+                            "can't reuse profile {:?}, creating a separate one from it",
+                            base
+                        );
+                        self.isolated = true;
+                    }
+                }
+            }
+        }
+
         if self.isolated {
             tracing::info!("Creating profile directory in: {:?}", &path);
 
+            if let Some(base) = &self.base_profile {
+                if !path.exists() {
+                    tracing::info!("Copying profile from: {:?}", base);
+                    copy_profile(base, &path);
+                }
+            }
+
             let _ = create_dir_all(&path);
 
             self.create_firefox_userjs(path.clone());
             self.create_user_chrome_css(path.clone());
         }
 
-        self.user_dir = format!(" --profile \"{}\"", path.to_str().unwrap());
+        self.user_dir = format!(" --profile {}", quote_exec_arg(path.to_str().unwrap()));
         self
     }
 
@@ -134,27 +437,50 @@ impl Firefox {
         self
     }
 
+    pub fn window_size(&mut self, size: Option<(u32, u32)>) -> &mut Self {
+        self.window_size = size;
+        self
+    }
+
     pub fn custom_args(&mut self, args: String) -> &mut Self {
         self.custom_args = args;
         self
     }
 
     pub fn build(&mut self) -> String {
-        let mut exec = format!("{} --no-remote {}", self.exec, self.codename);
+        let name = if self.window_title.is_empty() {
+            self.codename.clone()
+        } else {
+            exec_arg(&self.window_title)
+        };
+        let mut exec = format!("{} --class {} --name {}", self.exec, self.codename, name);
 
         if self.private {
             exec.push_str(" --private-window");
         }
 
+        // a shared profile is opened by the running instance,
+        // `--no-remote` would refuse to start with it in use
         if self.isolated {
+            exec.push_str(" --no-remote");
             exec.push_str(&self.user_dir);
+        } else if self.reuse_profile && self.base_profile.is_some() {
+            exec.push_str(&self.user_dir);
+        }
+
+        if let Some((width, height)) = self.window_size {
+            exec.push_str(&format!(" --width {} --height {}", width, height));
         }
 
         if !self.custom_args.is_empty() {
             exec.push_str(&self.custom_args);
         }
 
-        exec.push_str(&format!(" --new-window {}", self.url));
+        exec.push_str(&format!(" --new-window {}", exec_arg(&self.url)));
+
+        for url in &self.extra_urls {
+            exec.push_str(&format!(" --new-tab {}", exec_arg(url)));
+        }
 
         exec
     }
@@ -185,7 +511,7 @@ impl Falkon {
     }
 
     pub fn codename(&mut self, codename: String) -> &mut Self {
-        self.codename = format!("--wmclass dev.heppen.webapps.{}", codename);
+        self.codename = format!("--wmclass {}", webapp_id(&codename));
         self
     }
 
@@ -205,7 +531,7 @@ impl Falkon {
             let _ = create_dir_all(&path);
         }
 
-        self.user_dir = format!(" --profile \"{}\"", path.to_str().unwrap());
+        self.user_dir = format!(" --profile {}", quote_exec_arg(path.to_str().unwrap()));
         self
     }
 
@@ -234,48 +560,36 @@ impl Falkon {
             exec.push_str(&format!(" {}", self.custom_args));
         }
 
-        exec.push_str(&format!(" {}", self.url));
+        exec.push_str(&format!(" {}", exec_arg(&self.url)));
 
         exec
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct Chromium {
+pub struct Otter {
     pub exec: String,
-    pub codename: String,
     pub url: String,
     pub user_dir: String,
     pub isolated: bool,
-    pub ms_edge: bool,
     pub private: bool,
     pub custom_args: String,
 }
 
-impl Chromium {
+impl Otter {
     pub fn builder(browser_exec: String) -> Self {
         Self {
             exec: browser_exec,
-            codename: String::new(),
             url: String::new(),
             user_dir: String::new(),
             isolated: true,
-            ms_edge: false,
             private: false,
             custom_args: String::new(),
         }
     }
 
-    pub fn codename(&mut self, codename: String) -> &mut Self {
-        self.codename = format!(
-            "--class=dev.heppen.webapps.{} --name=dev.heppen.webapps.{}",
-            codename, codename
-        );
-        self
-    }
-
     pub fn url(&mut self, app_url: String) -> &mut Self {
-        self.url = format!("--app={}", app_url);
+        self.url = app_url;
         self
     }
 
@@ -290,12 +604,7 @@ impl Chromium {
             let _ = create_dir_all(&path);
         }
 
-        self.user_dir = format!(" --user-data-dir=\"{}\"", path.to_str().unwrap());
-        self
-    }
-
-    pub fn ms_edge(&mut self, flag: bool) -> &mut Self {
-        self.ms_edge = flag;
+        self.user_dir = format!(" --profile {}", quote_exec_arg(path.to_str().unwrap()));
         self
     }
 
@@ -310,14 +619,10 @@ impl Chromium {
     }
 
     pub fn build(&mut self) -> String {
-        let mut exec = format!("{} {} {}", self.exec, self.url, self.codename);
+        let mut exec = self.exec.clone();
 
         if self.private {
-            if self.ms_edge {
-                exec.push_str(" --inprivate");
-            } else {
-                exec.push_str(" --incognito");
-            }
+            exec.push_str(" --private-session");
         }
 
         if self.isolated {
@@ -325,144 +630,830 @@ impl Chromium {
         }
 
         if !self.custom_args.is_empty() {
-            exec.push_str(&self.custom_args);
+            exec.push_str(&format!(" {}", self.custom_args));
         }
 
+        exec.push_str(&format!(" --new-window {}", exec_arg(&self.url)));
+
         exec
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, EnumIter, Deserialize, Serialize)]
-pub enum BrowserModel {
-    Brave,
-    Chrome,
-    Chromium,
-    Cromite,
-    Falkon,
-    Floorp,
-    Firefox,
-    Librewolf,
-    MicrosoftEdge,
-    Vivaldi,
-    Waterfox,
-    Zen,
+// Min has no single site mode nor profile switches,
+// the page is opened in a new tab of its own window.
+#[derive(Debug, Clone)]
+pub struct Min {
+    pub exec: String,
+    pub url: String,
+    pub custom_args: String,
 }
 
-impl AsRef<str> for BrowserModel {
-    fn as_ref(&self) -> &str {
-        match &self {
-            BrowserModel::Brave => "brave",
-            BrowserModel::Chrome => "google-chrome",
-            BrowserModel::Chromium => "chromium",
-            BrowserModel::Cromite => "cromite",
-            BrowserModel::Falkon => "falkon",
-            BrowserModel::Floorp => "floorp",
-            BrowserModel::Firefox => "firefox",
-            BrowserModel::Librewolf => "librewolf",
-            BrowserModel::MicrosoftEdge => "microsoft-edge",
-            BrowserModel::Vivaldi => "vivaldi",
-            BrowserModel::Waterfox => "waterfox",
-            BrowserModel::Zen => "zen_browser",
+impl Min {
+    pub fn builder(browser_exec: String) -> Self {
+        Self {
+            exec: browser_exec,
+            url: String::new(),
+            custom_args: String::new(),
         }
     }
-}
 
-impl BrowserModel {
-    pub fn from(app_id: &str) -> Option<BrowserModel> {
-        let normalize_appid = app_id.to_lowercase().replace("-", ".");
-        BrowserModel::iter().find(|b| {
-            let compare_b = b.as_ref().to_lowercase().replace("-", ".");
-            normalize_appid.contains(&compare_b)
-        })
+    pub fn url(&mut self, app_url: String) -> &mut Self {
+        self.url = app_url;
+        self
     }
-}
 
-#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
-pub enum BrowserSource {
-    Flatpak,
-    #[default]
-    Native,
-    NativeLocal,
-    Nix,
-    Snap,
-    SystemFlatpak,
+    pub fn custom_args(&mut self, args: String) -> &mut Self {
+        self.custom_args = args;
+        self
+    }
+
+    pub fn build(&mut self) -> String {
+        let mut exec = self.exec.clone();
+
+        if !self.custom_args.is_empty() {
+            exec.push_str(&format!(" {}", self.custom_args));
+        }
+
+        exec.push_str(&format!(" {}", exec_arg(&self.url)));
+
+        exec
+    }
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
-pub struct Browser {
-    pub model: Option<BrowserModel>,
-    pub source: BrowserSource,
-    #[serde(skip_serializing)]
-    #[serde(skip_deserializing)]
-    pub entry: Option<DesktopEntry>,
-    pub name: String,
-    pub exec: String,
-    pub profile_path: PathBuf,
+// First Chromium release able to start an installed web app with `--app-id`.
+pub const PWA_MIN_VERSION: u32 = 122;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum LaunchStrategy {
+    #[default]
+    App,
+    Pwa,
 }
 
-impl AsRef<str> for Browser {
-    fn as_ref(&self) -> &str {
-        &self.name
+impl LaunchStrategy {
+    // The installed app is started only when it was asked for and the browser
+    // is a Chromium release new enough to support it, `--app` otherwise.
+    pub fn select(pwa: bool, model: Option<&BrowserModel>, version: Option<&str>) -> Self {
+        let supported = model.is_some_and(|model| model.info().supports_pwa)
+            && version
+                .and_then(major_version)
+                .is_some_and(|major| major >= PWA_MIN_VERSION);
+
+        if pwa && supported {
+            LaunchStrategy::Pwa
+        } else {
+            LaunchStrategy::App
+        }
     }
 }
 
-impl Browser {
-    fn create(entry: DesktopEntry) -> Self {
-        let mut name = entry.name(&LOCALES).unwrap_or_default().to_string();
-        let xdg_data = dirs::data_dir().unwrap_or_default();
-        let profile_path = xdg_data.join("quick-webapps/profiles").join(&entry.appid);
+// Major version from `--version` output, like "Chromium 124.0.6367.78 built on Debian"
+// or "Brave Browser 124.1.65.114".
+pub fn major_version(version: &str) -> Option<u32> {
+    version
+        .split_whitespace()
+        .find(|word| word.contains('.') && word.starts_with(|c: char| c.is_ascii_digit()))
+        .and_then(|word| word.split('.').next())
+        .and_then(|major| major.parse().ok())
+}
 
-        if let Some(model) = BrowserModel::from(&entry.appid) {
-            let source = match PathSource::guess_from(&entry.path) {
-                PathSource::Local | PathSource::LocalDesktop | PathSource::SystemLocal => {
-                    BrowserSource::NativeLocal
-                }
-                PathSource::System => BrowserSource::Native,
-                PathSource::LocalFlatpak => BrowserSource::Flatpak,
-                PathSource::LocalNix | PathSource::Nix => BrowserSource::Nix,
-                PathSource::SystemFlatpak => BrowserSource::SystemFlatpak,
-                PathSource::SystemSnap => BrowserSource::Snap,
-                PathSource::Other(_) => {
-                    let str_path = entry.path.to_string_lossy();
+// Id Chromium gives a web app installed from `url` when its manifest has no
+// `id`: the first 16 bytes of the SHA-256 of the url, a letter from `a` to `p`
+// for each half byte.
+pub fn chromium_app_id(url: &str) -> String {
+    let spec = match url::Url::parse(url) {
+        Ok(mut parsed) => {
+            parsed.set_fragment(None);
+            parsed.to_string()
+        }
+        Err(_) => url.to_string(),
+    };
+
+    Sha256::digest(spec.as_bytes())[..16]
+        .iter()
+        .flat_map(|byte| [byte >> 4, byte & 0xf])
+        .map(|nibble| (b'a' + nibble) as char)
+        .collect()
+}
 
-                    if str_path.contains(".local/share/flatpak/") {
-                        BrowserSource::Flatpak
-                    } else if str_path.contains("/var/lib/flatpak/") {
-                        BrowserSource::SystemFlatpak
-                    } else {
-                        BrowserSource::Native
-                    }
-                }
-            };
+// Whether the web app was installed from the browser's menu in `profile`.
+pub fn chromium_app_installed(profile: &Path, app_id: &str) -> bool {
+    profile
+        .join("Web Applications/Manifest Resources")
+        .join(app_id)
+        .is_dir()
+}
 
-            match source {
-                BrowserSource::Flatpak => name.push_str(" (User Flatpak)"),
-                BrowserSource::Snap => name.push_str(" (Snap)"),
-                BrowserSource::SystemFlatpak => name.push_str(" (System Flatpak)"),
-                BrowserSource::NativeLocal => name.push_str(" (User)"),
-                _ => (),
+#[derive(Debug, Clone)]
+pub struct Chromium {
+    pub exec: String,
+    pub codename: String,
+    pub url: String,
+    pub extra_urls: Vec<String>,
+    pub user_dir: String,
+    pub isolated: bool,
+    pub ms_edge: bool,
+    pub private: bool,
+    pub badges: bool,
+    pub window_size: Option<(u32, u32)>,
+    pub maximized: bool,
+    pub window_position: Option<(i32, i32)>,
+    pub strategy: LaunchStrategy,
+    pub base_profile: Option<PathBuf>,
+    pub custom_args: String,
+}
+
+impl Chromium {
+    pub fn builder(browser_exec: String) -> Self {
+        Self {
+            exec: browser_exec,
+            codename: String::new(),
+            url: String::new(),
+            extra_urls: Vec::new(),
+            user_dir: String::new(),
+            isolated: true,
+            ms_edge: false,
+            private: false,
+            badges: false,
+            window_size: None,
+            maximized: false,
+            window_position: None,
+            strategy: LaunchStrategy::App,
+            base_profile: None,
+            custom_args: String::new(),
+        }
+    }
+
+    // Without `--class` app windows get a `crx_<hash>` class which matches no launcher.
+    // It's the same id as `StartupWMClass` of the desktop file, but it only applies
+    // to a new browser process, so windows opened by a running instance sharing
+    // the profile keep the class of that instance.
+    pub fn codename(&mut self, codename: String) -> &mut Self {
+        let app_id = webapp_id(&codename);
+        self.codename = format!("--class={} --name={}", app_id, app_id);
+        self
+    }
+
+    pub fn url(&mut self, app_url: String) -> &mut Self {
+        self.url = app_url;
+        self
+    }
+
+    // positional urls open as tabs of a regular browser window
+    pub fn extra_urls(&mut self, urls: Vec<String>) -> &mut Self {
+        self.extra_urls = urls;
+        self
+    }
+
+    pub fn strategy(&mut self, strategy: LaunchStrategy) -> &mut Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn isolated(&mut self, flag: bool) -> &mut Self {
+        self.isolated = flag;
+        self
+    }
+
+    // Profile whose extensions are copied into a new isolated profile.
+    pub fn base_profile(&mut self, path: Option<PathBuf>) -> &mut Self {
+        self.base_profile = path;
+        self
+    }
+
+    pub fn profile_path(&mut self, path: PathBuf) -> &mut Self {
+        if self.isolated {
+            tracing::info!("Creating profile directory in: {:?}", &path);
+
+            if let Some(base) = &self.base_profile {
+                if !path.exists() {
+                    tracing::info!("Copying extensions from: {:?}", base);
+                    seed_chromium_extensions(base, &path);
+                }
+            }
+
+            let _ = create_dir_all(&path);
+        }
+
+        self.user_dir = format!(
+            " {}",
+            quote_exec_arg(&format!("--user-data-dir={}", path.to_str().unwrap()))
+        );
+        self
+    }
+
+    pub fn ms_edge(&mut self, flag: bool) -> &mut Self {
+        self.ms_edge = flag;
+        self
+    }
+
+    pub fn private_mode(&mut self, flag: bool) -> &mut Self {
+        self.private = flag;
+        self
+    }
+
+    // The Badging API (`navigator.setAppBadge`) is still experimental outside
+    // of installed apps on Linux, whether the count shows up on the launcher
+    // depends on the browser version and the panel.
+    pub fn badges(&mut self, flag: bool) -> &mut Self {
+        self.badges = flag;
+        self
+    }
+
+    pub fn window_size(&mut self, size: Option<(u32, u32)>) -> &mut Self {
+        self.window_size = size;
+        self
+    }
+
+    // Wayland compositors place windows themselves and ignore it
+    pub fn window_position(&mut self, position: Option<(i32, i32)>) -> &mut Self {
+        self.window_position = position;
+        self
+    }
+
+    pub fn maximized(&mut self, flag: bool) -> &mut Self {
+        self.maximized = flag;
+        self
+    }
+
+    pub fn custom_args(&mut self, args: String) -> &mut Self {
+        self.custom_args = args;
+        self
+    }
+
+    pub fn build(&mut self) -> String {
+        let url = match self.strategy {
+            LaunchStrategy::App => exec_arg(&format!("--app={}", self.url)),
+            LaunchStrategy::Pwa => format!("--app-id={}", chromium_app_id(&self.url)),
+        };
+        let mut exec = format!("{} {} {}", self.exec, url, self.codename);
+
+        if self.private {
+            if self.ms_edge {
+                exec.push_str(" --inprivate");
+            } else {
+                exec.push_str(" --incognito");
+            }
+        }
+
+        if self.isolated {
+            exec.push_str(&self.user_dir);
+        }
+
+        if self.badges {
+            exec.push_str(" --enable-experimental-web-platform-features");
+        }
+
+        if self.maximized {
+            exec.push_str(" --start-maximized");
+        } else if let Some((width, height)) = self.window_size {
+            exec.push_str(&format!(" --window-size={},{}", width, height));
+        }
+
+        if let Some((x, y)) = self.window_position {
+            exec.push_str(&format!(" --window-position={},{}", x, y));
+        }
+
+        if !self.custom_args.is_empty() {
+            exec.push_str(&self.custom_args);
+        }
+
+        for url in &self.extra_urls {
+            exec.push_str(&format!(" {}", exec_arg(url)));
+        }
+
+        exec
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, Deserialize, Serialize)]
+pub enum BrowserModel {
+    Brave,
+    Chrome,
+    Chromium,
+    Cromite,
+    Falkon,
+    Floorp,
+    Firefox,
+    Librewolf,
+    MicrosoftEdge,
+    Midori,
+    Min,
+    Otter,
+    Vivaldi,
+    Waterfox,
+    Zen,
+}
+
+impl AsRef<str> for BrowserModel {
+    fn as_ref(&self) -> &str {
+        match &self {
+            BrowserModel::Brave => "brave",
+            BrowserModel::Chrome => "google-chrome",
+            BrowserModel::Chromium => "chromium",
+            BrowserModel::Cromite => "cromite",
+            BrowserModel::Falkon => "falkon",
+            BrowserModel::Floorp => "floorp",
+            BrowserModel::Firefox => "firefox",
+            BrowserModel::Librewolf => "librewolf",
+            BrowserModel::MicrosoftEdge => "microsoft-edge",
+            BrowserModel::Midori => "midori",
+            BrowserModel::Min => "min",
+            BrowserModel::Otter => "otter-browser",
+            BrowserModel::Vivaldi => "vivaldi",
+            BrowserModel::Waterfox => "waterfox",
+            BrowserModel::Zen => "zen_browser",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    Chromium,
+    Electron,
+    Gecko,
+    QtWebEngine,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrowserInfo {
+    pub name: &'static str,
+    pub engine: Engine,
+    pub private_flag: &'static str,
+    pub profile_flag: &'static str,
+    pub supports_private: bool,
+    pub supports_pwa: bool,
+    pub supports_navbar: bool,
+    // links opening a new window can become tabs, see `Firefox::new_windows_as_tabs`
+    pub supports_new_windows_as_tabs: bool,
+    // a web app with its own profile runs as a single browser instance
+    pub single_instance: bool,
+    // sites can set an unread count on the launcher, see `Chromium::badges`
+    pub supports_badges: bool,
+}
+
+impl BrowserInfo {
+    const fn gecko(name: &'static str) -> Self {
+        Self {
+            name,
+            engine: Engine::Gecko,
+            private_flag: "--private-window",
+            profile_flag: "--profile",
+            supports_private: true,
+            supports_pwa: false,
+            supports_navbar: true,
+            supports_new_windows_as_tabs: true,
+            single_instance: true,
+            supports_badges: false,
+        }
+    }
+
+    const fn chromium(name: &'static str) -> Self {
+        Self {
+            name,
+            engine: Engine::Chromium,
+            private_flag: "--incognito",
+            profile_flag: "--user-data-dir",
+            supports_private: true,
+            supports_pwa: true,
+            supports_navbar: false,
+            supports_new_windows_as_tabs: false,
+            single_instance: true,
+            supports_badges: true,
+        }
+    }
+}
+
+// Every supported browser with what it can do.
+pub fn browser_models() -> Vec<(BrowserModel, BrowserInfo)> {
+    BrowserModel::iter()
+        .map(|model| {
+            let info = model.info();
+            (model, info)
+        })
+        .collect()
+}
+
+impl BrowserModel {
+    // Otter and Min have no switch for the window class, their windows always
+    // carry the class of the browser itself and match no web app launcher.
+    pub fn sets_wm_class(&self) -> bool {
+        !matches!(self, BrowserModel::Otter | BrowserModel::Min)
+    }
+
+    pub fn info(&self) -> BrowserInfo {
+        match self {
+            BrowserModel::Brave => BrowserInfo::chromium("Brave"),
+            BrowserModel::Chrome => BrowserInfo::chromium("Google Chrome"),
+            BrowserModel::Chromium => BrowserInfo::chromium("Chromium"),
+            BrowserModel::Cromite => BrowserInfo::chromium("Cromite"),
+            BrowserModel::Falkon => BrowserInfo {
+                name: "Falkon",
+                engine: Engine::QtWebEngine,
+                private_flag: "--private-browsing",
+                profile_flag: "--profile",
+                supports_private: true,
+                supports_pwa: false,
+                supports_navbar: false,
+                supports_new_windows_as_tabs: false,
+                single_instance: false,
+                supports_badges: false,
+            },
+            // Floorp manages its own toolbar layout
+            BrowserModel::Floorp => BrowserInfo {
+                supports_navbar: false,
+                ..BrowserInfo::gecko("Floorp")
+            },
+            BrowserModel::Firefox => BrowserInfo::gecko("Firefox"),
+            BrowserModel::Librewolf => BrowserInfo::gecko("LibreWolf"),
+            BrowserModel::MicrosoftEdge => BrowserInfo {
+                private_flag: "--inprivate",
+                ..BrowserInfo::chromium("Microsoft Edge")
+            },
+            // Midori is based on Floorp since version 11
+            BrowserModel::Midori => BrowserInfo::gecko("Midori"),
+            BrowserModel::Min => BrowserInfo {
+                name: "Min",
+                engine: Engine::Electron,
+                private_flag: "",
+                profile_flag: "",
+                supports_private: false,
+                supports_pwa: false,
+                supports_navbar: false,
+                supports_new_windows_as_tabs: false,
+                single_instance: false,
+                supports_badges: false,
+            },
+            BrowserModel::Otter => BrowserInfo {
+                name: "Otter Browser",
+                engine: Engine::QtWebEngine,
+                private_flag: "--private-session",
+                profile_flag: "--profile",
+                supports_private: true,
+                supports_pwa: false,
+                supports_navbar: false,
+                supports_new_windows_as_tabs: false,
+                single_instance: false,
+                supports_badges: false,
+            },
+            BrowserModel::Vivaldi => BrowserInfo::chromium("Vivaldi"),
+            BrowserModel::Waterfox => BrowserInfo::gecko("Waterfox"),
+            BrowserModel::Zen => BrowserInfo::gecko("Zen Browser"),
+        }
+    }
+
+    pub fn from(app_id: &str) -> Option<BrowserModel> {
+        let normalize_appid = app_id.to_lowercase().replace("-", ".");
+        BrowserModel::iter().find(|b| {
+            let compare_b = b.as_ref().to_lowercase().replace("-", ".");
+
+            // "min" is a part of too many other app ids, like "terminal"
+            if *b == BrowserModel::Min {
+                return normalize_appid.split('.').any(|part| part == compare_b);
+            }
+
+            normalize_appid.contains(&compare_b)
+        })
+    }
+
+    // Where the flatpak of a Gecko browser keeps its profiles, relative to the
+    // sandbox home `~/.var/app/<app id>`. Profiles outside of it can't be opened.
+    fn flatpak_profiles_dir(&self) -> Option<&'static str> {
+        match self {
+            BrowserModel::Firefox => Some(".mozilla/firefox"),
+            BrowserModel::Floorp => Some(".floorp"),
+            BrowserModel::Librewolf => Some(".librewolf"),
+            BrowserModel::Waterfox => Some(".waterfox"),
+            BrowserModel::Zen => Some(".zen"),
+            _ => None,
+        }
+    }
+
+    // User data dir of a Chromium based browser, relative to `~/.config`,
+    // or to `~/.var/app/<app id>/config` for flatpaks.
+    pub fn chromium_config_dir(&self) -> Option<&'static str> {
+        match self {
+            BrowserModel::Brave => Some("BraveSoftware/Brave-Browser"),
+            BrowserModel::Chrome => Some("google-chrome"),
+            BrowserModel::Chromium => Some("chromium"),
+            BrowserModel::MicrosoftEdge => Some("microsoft-edge"),
+            BrowserModel::Vivaldi => Some("vivaldi"),
+            _ => None,
+        }
+    }
+
+    // Vendor packages which install the real binary into `/opt`
+    // and only add a symlink to `/usr/bin`.
+    fn opt_executables(&self) -> &'static [&'static str] {
+        match self {
+            BrowserModel::Brave => &["opt/brave.com/brave/brave-browser"],
+            BrowserModel::Chrome => &[
+                "opt/google/chrome/google-chrome",
+                "opt/google/chrome-beta/google-chrome-beta",
+                "opt/google/chrome-unstable/google-chrome-unstable",
+            ],
+            BrowserModel::MicrosoftEdge => &[
+                "opt/microsoft/msedge/microsoft-edge",
+                "opt/microsoft/msedge-beta/microsoft-edge-beta",
+                "opt/microsoft/msedge-dev/microsoft-edge-dev",
+            ],
+            BrowserModel::Vivaldi => &[
+                "opt/vivaldi/vivaldi",
+                "opt/vivaldi-snapshot/vivaldi-snapshot",
+            ],
+            _ => &[],
+        }
+    }
+}
+
+// Profiles root of a flatpak Gecko browser, kept apart from the browser's own profiles.
+fn flatpak_profile_root(model: &BrowserModel, appid: &str, home: &Path) -> Option<PathBuf> {
+    let dir = model.flatpak_profiles_dir()?;
+
+    Some(
+        home.join(".var/app")
+            .join(appid)
+            .join(dir)
+            .join("quick-webapps"),
+    )
+}
+
+fn executable_exists(binary: &str) -> bool {
+    let path = Path::new(binary);
+
+    if path.is_absolute() {
+        return path.exists();
+    }
+
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(binary).exists()))
+        .unwrap_or_default()
+}
+
+// Falls back to the vendor layout in `/opt` when the binary
+// referenced by the desktop entry can't be found.
+fn resolve_native_exec(model: &BrowserModel, exec: &str, root: &Path) -> String {
+    let mut parts = exec.splitn(2, ' ');
+    let binary = parts.next().unwrap_or_default();
+
+    if executable_exists(binary) {
+        return exec.to_string();
+    }
+
+    let found = model
+        .opt_executables()
+        .iter()
+        .map(|path| root.join(path))
+        .find(|path| path.exists());
+
+    match found {
+        Some(path) => {
+            tracing::debug!("{} not found, using {:?} instead", binary, path);
+
+            match parts.next() {
+                Some(args) => format!("{} {}", path.display(), args),
+                None => path.display().to_string(),
+            }
+        }
+        None => exec.to_string(),
+    }
+}
+
+// Brew's `bin` isn't always in the `$PATH` of the session starting
+// the desktop file, so the binary is referenced by its full path.
+fn resolve_brew_exec(exec: &str, prefixes: &[PathBuf]) -> String {
+    let mut parts = exec.splitn(2, ' ');
+    let binary = parts.next().unwrap_or_default();
+
+    if Path::new(binary).is_absolute() {
+        return exec.to_string();
+    }
+
+    match prefixes
+        .iter()
+        .map(|prefix| prefix.join("bin").join(binary))
+        .find(|path| path.exists())
+    {
+        Some(path) => match parts.next() {
+            Some(args) => format!("{} {}", path.display(), args),
+            None => path.display().to_string(),
+        },
+        None => exec.to_string(),
+    }
+}
+
+// Picks the flatpak export which actually exists, preferring the installation
+// the desktop entry came from. A browser installed only once shouldn't point
+// at an export of the other installation.
+fn flatpak_export(
+    appid: &str,
+    source: &BrowserSource,
+    home: &Path,
+    root: &Path,
+) -> Option<(BrowserSource, PathBuf)> {
+    let user = (
+        BrowserSource::Flatpak,
+        home.join(".local/share/flatpak/exports/bin").join(appid),
+    );
+    let system = (
+        BrowserSource::SystemFlatpak,
+        root.join("var/lib/flatpak/exports/bin").join(appid),
+    );
+
+    let candidates = match source {
+        BrowserSource::SystemFlatpak => [system, user],
+        _ => [user, system],
+    };
+
+    candidates.into_iter().find(|(_, path)| path.exists())
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BrowserSource {
+    Flatpak,
+    Homebrew,
+    #[default]
+    Native,
+    NativeLocal,
+    Nix,
+    Snap,
+    SystemFlatpak,
+}
+
+impl AsRef<str> for BrowserSource {
+    fn as_ref(&self) -> &str {
+        match self {
+            BrowserSource::Flatpak => "flatpak",
+            BrowserSource::Homebrew => "homebrew",
+            BrowserSource::Native => "native",
+            BrowserSource::NativeLocal => "native-local",
+            BrowserSource::Nix => "nix",
+            BrowserSource::Snap => "snap",
+            BrowserSource::SystemFlatpak => "system-flatpak",
+        }
+    }
+}
+
+// Stable reference to an installed browser made of its model and where it's
+// installed from, like `firefox/flatpak`. Unlike the desktop entry id or the
+// exec line, it still matches after the browser renames its desktop file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrowserId(String);
+
+impl BrowserId {
+    pub fn new(model: &BrowserModel, source: &BrowserSource) -> Self {
+        Self(format!("{}/{}", model.as_ref(), source.as_ref()))
+    }
+
+    pub fn parse(id: &str) -> Option<Self> {
+        let (model, source) = id.trim().split_once('/')?;
+
+        if model.is_empty() || source.is_empty() {
+            return None;
+        }
+
+        Some(Self(id.trim().to_string()))
+    }
+}
+
+impl AsRef<str> for BrowserId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+pub fn browser_by_id(id: &BrowserId) -> Option<Browser> {
+    detected_browsers()
+        .into_iter()
+        .find(|browser| browser.id().as_ref() == Some(id))
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Browser {
+    pub model: Option<BrowserModel>,
+    pub source: BrowserSource,
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
+    pub entry: Option<DesktopEntry>,
+    pub name: String,
+    pub exec: String,
+    pub profile_path: PathBuf,
+}
+
+impl AsRef<str> for Browser {
+    fn as_ref(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Browser {
+    pub fn id(&self) -> Option<BrowserId> {
+        self.model
+            .as_ref()
+            .map(|model| BrowserId::new(model, &self.source))
+    }
+
+    pub fn is_hidden(&self, hidden: &[String]) -> bool {
+        self.id()
+            .is_some_and(|id| hidden.iter().any(|hidden| hidden == id.as_ref()))
+    }
+
+    fn create(entry: DesktopEntry) -> Self {
+        let mut name = entry.name(&LOCALES).unwrap_or_default().to_string();
+        // left empty when there's no data dir, `profile_path()` reports it then
+        let mut profile_path = DataPaths::current()
+            .profiles()
+            .map(|root| root.join(&entry.appid))
+            .unwrap_or_default();
+
+        if let Some(model) = BrowserModel::from(&entry.appid) {
+            let mut source = match PathSource::guess_from(&entry.path) {
+                PathSource::Local | PathSource::LocalDesktop | PathSource::SystemLocal => {
+                    BrowserSource::NativeLocal
+                }
+                PathSource::System => BrowserSource::Native,
+                PathSource::LocalFlatpak => BrowserSource::Flatpak,
+                PathSource::LocalNix | PathSource::Nix => BrowserSource::Nix,
+                PathSource::SystemFlatpak => BrowserSource::SystemFlatpak,
+                PathSource::SystemSnap => BrowserSource::Snap,
+                PathSource::Other(_) => {
+                    let str_path = entry.path.to_string_lossy();
+
+                    if str_path.contains(".local/share/flatpak/") {
+                        BrowserSource::Flatpak
+                    } else if str_path.contains("/var/lib/flatpak/") {
+                        BrowserSource::SystemFlatpak
+                    } else if brew_prefixes()
+                        .iter()
+                        .any(|prefix| entry.path.starts_with(prefix))
+                    {
+                        BrowserSource::Homebrew
+                    } else {
+                        BrowserSource::Native
+                    }
+                }
+            };
+
+            let mut flatpak_exec = None;
+
+            if matches!(
+                source,
+                BrowserSource::Flatpak | BrowserSource::SystemFlatpak
+            ) {
+                let Some(home) = home_dir() else {
+                    tracing::warn!("no home directory, skipping flatpak {}", entry.appid);
+                    return Browser::default();
+                };
+
+                match flatpak_export(&entry.appid, &source, &home, Path::new("/")) {
+                    Some((export_source, exec)) => {
+                        source = export_source;
+                        flatpak_exec = Some(exec);
+                    }
+                    None => {
+                        tracing::debug!("no flatpak export found for {}, skipping", entry.appid);
+                        return Browser::default();
+                    }
+                }
+
+                if let Some(root) = flatpak_profile_root(&model, &entry.appid, &home) {
+                    profile_path = root;
+                }
+            }
+
+            match source {
+                BrowserSource::Flatpak => name.push_str(" (User Flatpak)"),
+                BrowserSource::Homebrew => name.push_str(" (Homebrew)"),
+                BrowserSource::Snap => name.push_str(" (Snap)"),
+                BrowserSource::SystemFlatpak => name.push_str(" (System Flatpak)"),
+                BrowserSource::NativeLocal => name.push_str(" (User)"),
+                _ => (),
             }
 
             let executable = match source {
-                BrowserSource::Native
-                | BrowserSource::NativeLocal
-                | BrowserSource::Nix
-                | BrowserSource::Snap => entry.exec().unwrap_or_default().to_string(),
-                BrowserSource::Flatpak => dirs::home_dir()
-                    .unwrap_or_default()
-                    .join(".local/share/flatpak/exports/bin")
-                    .join(&entry.appid)
-                    .to_str()
-                    .unwrap()
-                    .to_string(),
-                BrowserSource::SystemFlatpak => PathBuf::from_str("/var/lib/flatpak/exports/bin")
+                BrowserSource::Native => {
+                    resolve_native_exec(&model, entry.exec().unwrap_or_default(), Path::new("/"))
+                }
+                BrowserSource::Homebrew => {
+                    resolve_brew_exec(entry.exec().unwrap_or_default(), &brew_prefixes())
+                }
+                BrowserSource::NativeLocal | BrowserSource::Nix | BrowserSource::Snap => {
+                    entry.exec().unwrap_or_default().to_string()
+                }
+                BrowserSource::Flatpak | BrowserSource::SystemFlatpak => flatpak_exec
                     .unwrap_or_default()
-                    .join(&entry.appid)
-                    .to_str()
-                    .unwrap()
+                    .to_string_lossy()
                     .to_string(),
             };
 
+            tracing::debug!(
+                "resolved browser {:?} from {:?} as {:?}: {}",
+                model,
+                entry.path,
+                source,
+                executable
+            );
+
             return Self {
                 model: Some(model),
                 source,
@@ -496,24 +1487,828 @@ impl Browser {
 
         Browser::default()
     }
+
+    // Absolute profiles root. Records of older versions keep it relative
+    // to the home directory, like `.var/app/<appid>/...`.
+    pub fn profile_path(&self) -> Result<PathBuf, Error> {
+        if self.profile_path.is_absolute() {
+            return Ok(self.profile_path.clone());
+        }
+
+        if self.profile_path.as_os_str().is_empty() {
+            return Err(Error::NoHomeDir);
+        }
+
+        Ok(home_dir().ok_or(Error::NoHomeDir)?.join(&self.profile_path))
+    }
+
+    // A flatpak browser that was never started has no data directory yet,
+    // so make sure profiles can be created there before writing the launcher.
+    pub fn check_profile_root(&self) -> anyhow::Result<()> {
+        let profile_path = self.profile_path()?;
+        let probe = profile_path.join(".qwa-write-test");
+
+        create_dir_all(&profile_path)
+            .and_then(|_| File::create(&probe).map(drop))
+            .and_then(|_| remove_file(&probe))
+            .map_err(|err| {
+                anyhow::anyhow!(
+                    "profile directory {:?} for {} is not writable: {}",
+                    profile_path,
+                    self.name,
+                    err
+                )
+            })
+    }
+
+    // Program the browser is started with, a flatpak export for flatpaks.
+    // An `env VAR=value` prefix, as used by some snap and nix entries, is skipped.
+    pub fn executable(&self) -> Option<&str> {
+        let mut words = self
+            .exec
+            .split_whitespace()
+            .map(|word| word.trim_matches('"'))
+            .peekable();
+
+        if words.peek() == Some(&"env") {
+            words.next();
+        }
+
+        words.find(|word| !word.is_empty() && (word.starts_with('/') || !word.contains('=')))
+    }
+
+    // Binary for `TryExec=`, which hides the web app once the browser is gone.
+    // Wrappers like `flatpak run` stay installed without the browser, so
+    // there's none for them.
+    pub fn try_exec(&self) -> Option<&str> {
+        self.executable().filter(|binary| {
+            let name = Path::new(binary)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default();
+
+            !matches!(
+                name,
+                "flatpak" | "flatpak-spawn" | "snap" | "nix" | "nix-shell"
+            )
+        })
+    }
+
+    // Version of this installation of the browser, so the same model installed
+    // from different sources reports its own version. Flatpaks are looked up in
+    // their metainfo, other browsers are asked once per session.
+    pub fn version(&self) -> Option<String> {
+        static VERSIONS: Lazy<Mutex<HashMap<String, Option<String>>>> = Lazy::new(Default::default);
+
+        if let Ok(versions) = VERSIONS.lock() {
+            if let Some(version) = versions.get(&self.exec) {
+                return version.clone();
+            }
+        }
+
+        let version = match self.source {
+            BrowserSource::Flatpak | BrowserSource::SystemFlatpak => self.flatpak_version(),
+            _ => self.binary_version(),
+        };
+
+        if let Ok(mut versions) = VERSIONS.lock() {
+            versions.insert(self.exec.clone(), version.clone());
+        }
+
+        version
+    }
+
+    // Latest release listed by the metainfo of the installed flatpak.
+    fn flatpak_version(&self) -> Option<String> {
+        let appid = match &self.entry {
+            Some(entry) => entry.appid.clone(),
+            None => Path::new(self.executable()?)
+                .file_name()?
+                .to_string_lossy()
+                .to_string(),
+        };
+        let home = home_dir()?;
+        let root = if is_sandboxed() {
+            PathBuf::from("/run/host")
+        } else {
+            PathBuf::from("/")
+        };
+
+        let metainfo = [
+            home.join(".local/share/flatpak/app"),
+            root.join("var/lib/flatpak/app"),
+        ]
+        .into_iter()
+        .map(|apps| apps.join(&appid).join("current/active/files/share"))
+        .flat_map(|share| {
+            [
+                share.join(format!("metainfo/{}.metainfo.xml", appid)),
+                share.join(format!("appdata/{}.appdata.xml", appid)),
+            ]
+        })
+        .find_map(|path| fs::read_to_string(path).ok())?;
+
+        let release = &metainfo[metainfo.find("<release ")?..];
+        let release = &release[..release.find('>')?];
+        let version = &release[release.find("version=\"")? + "version=\"".len()..];
+
+        Some(version[..version.find('"')?].to_string())
+    }
+
+    fn binary_version(&self) -> Option<String> {
+        let binary = self.executable()?;
+        // the browser lives on the host, out of reach of the sandbox
+        let output = if is_sandboxed() {
+            Command::new("flatpak-spawn")
+                .args(["--host", binary, "--version"])
+                .output()
+        } else {
+            Command::new(binary).arg("--version").output()
+        }
+        .ok()?;
+
+        if !output.status.success() {
+            tracing::debug!("{} --version exited with {}", binary, output.status);
+            return None;
+        }
+
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
 }
 
+// Desktop entry id of the system default browser, without `.desktop`.
+fn default_browser_id() -> Option<String> {
+    let queries: [&[&str]; 2] = [
+        &["xdg-settings", "get", "default-web-browser"],
+        &["xdg-mime", "query", "default", "x-scheme-handler/https"],
+    ];
+
+    queries.iter().find_map(|query| {
+        let output = Command::new(query[0]).args(&query[1..]).output().ok()?;
+        let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+        match output.status.success() && !id.is_empty() {
+            true => Some(id.trim_end_matches(".desktop").to_string()),
+            false => None,
+        }
+    })
+}
+
+// The system default browser among `browsers`, none when it isn't supported.
+pub fn default_browser(browsers: &[Browser]) -> Option<usize> {
+    browser_with_entry_id(browsers, &default_browser_id()?)
+}
+
+fn browser_with_entry_id(browsers: &[Browser], id: &str) -> Option<usize> {
+    let position = browsers.iter().position(|browser| {
+        browser
+            .entry
+            .as_ref()
+            .is_some_and(|entry| entry.appid == id)
+    });
+
+    if position.is_none() {
+        tracing::debug!("default browser {} isn't supported", id);
+    }
+
+    position
+}
+
+// Browsers offered in the picker, without the ones hidden in the settings.
 pub fn installed_browsers() -> Vec<Browser> {
+    let hidden = AppConfig::config().hidden_browsers;
+
+    detected_browsers()
+        .into_iter()
+        .filter(|browser| !browser.is_hidden(&hidden))
+        .collect()
+}
+
+// Every supported browser found, hidden ones included, so web apps made
+// with a browser hidden later still resolve it.
+pub fn detected_browsers() -> Vec<Browser> {
+    let entries: Vec<DesktopEntry> = fd_entries()
+        .into_iter()
+        .filter(|entry| {
+            !entry
+                .comment(&LOCALES)
+                .is_some_and(|comments| comments.contains("Quick Web App"))
+        })
+        .collect();
+
+    let apps = resolve_browsers(&entries);
+    tracing::debug!("detected {} browsers", apps.len());
+
+    apps
+}
+
+// Supported browsers of the desktop entries, in the order of the entries,
+// leaving out entries starting a browser already listed.
+fn resolve_browsers(entries: &[DesktopEntry]) -> Vec<Browser> {
     let mut apps: Vec<Browser> = Vec::new();
 
-    for entry in fd_entries() {
-        if let Some(comments) = entry.comment(&LOCALES) {
-            if comments.contains("Quick Web App") {
-                continue;
+    // resolving a browser checks many candidate paths, which is slow on network
+    // filesystems, so entries are resolved in parallel chunks kept in their order
+    let threads = std::thread::available_parallelism()
+        .map(|threads| threads.get())
+        .unwrap_or(1);
+    let chunk_size = entries.len().div_ceil(threads).max(1);
+
+    let browsers: Vec<Browser> = std::thread::scope(|scope| {
+        let handles: Vec<_> = entries
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|entry| Browser::from_path(&entry.path))
+                        .collect::<Vec<Browser>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_default())
+            .collect()
+    });
+
+    for browser in browsers {
+        if browser.model.is_some() && !apps.iter().any(|app| app.exec == browser.exec) {
+            apps.push(browser);
+        }
+    }
+
+    apps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_ini_prefers_the_install_default() {
+        let content = "\
+[Profile1]
+Name=default
+IsRelative=1
+Path=abcd.default
+Default=1
+
+[Profile0]
+Name=default-release
+IsRelative=1
+Path=efgh.default-release
+
+[Profile2]
+Name=elsewhere
+IsRelative=0
+Path=/data/firefox/work
+
+[Install4F96D1932A9F858E]
+Default=efgh.default-release
+Locked=1
+";
+        let base = Path::new("/home/user/.mozilla/firefox");
+        let profiles = parse_profiles_ini(content, base);
+
+        assert_eq!(profiles.len(), 3);
+        assert_eq!(profiles[0].path, base.join("abcd.default"));
+        assert!(!profiles[0].default);
+        assert_eq!(profiles[1].name, "default-release");
+        assert!(profiles[1].default);
+        assert_eq!(profiles[2].path, PathBuf::from("/data/firefox/work"));
+    }
+
+    #[test]
+    fn profiles_ini_falls_back_to_default_key() {
+        let content =
+            "[Profile0]\nName=a\nPath=a.profile\n\n[Profile1]\nName=b\nPath=b.profile\nDefault=1\n";
+        let profiles = parse_profiles_ini(content, Path::new("/base"));
+
+        assert_eq!(
+            profiles
+                .iter()
+                .filter(|profile| profile.default)
+                .map(|profile| profile.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["b"]
+        );
+    }
+
+    #[test]
+    fn non_utf8_profile_is_copied_instead_of_reused() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir
+            .path()
+            .join(std::ffi::OsStr::from_bytes(b"profile-\xff"));
+        fs::create_dir_all(&base).unwrap();
+        fs::write(base.join("prefs.js"), "prefs").unwrap();
+        let path = dir.path().join("webapp");
+
+        let exec = Firefox::builder("firefox".to_string())
+            .base_profile(Some(base))
+            .reuse_profile(true)
+            .profile_path(path.clone())
+            .build();
+
+        assert!(exec.contains(&format!(
+            "--no-remote --profile {}",
+            quote_exec_arg(path.to_str().unwrap())
+        )));
+        assert!(path.join("prefs.js").is_file());
+    }
+
+    fn chromium_default_profile(root: &Path) -> PathBuf {
+        let base = root.join("chromium/Default");
+        let extension = base.join("Extensions/abcdefghijklmnop/1.0_0");
+        fs::create_dir_all(&extension).unwrap();
+        fs::create_dir_all(base.join("Local Extension Settings/abcdefghijklmnop")).unwrap();
+        fs::write(extension.join("manifest.json"), "{}").unwrap();
+        fs::write(base.join("Preferences"), "{}").unwrap();
+        fs::write(base.join("Cookies"), "cookies").unwrap();
+        fs::write(base.join("History"), "history").unwrap();
+        base
+    }
+
+    #[test]
+    fn only_extensions_are_seeded_into_chromium_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = chromium_default_profile(dir.path());
+        let user_data_dir = dir.path().join("webapp");
+
+        seed_chromium_extensions(&base, &user_data_dir);
+
+        let seeded = user_data_dir.join("Default");
+        assert!(seeded
+            .join("Extensions/abcdefghijklmnop/1.0_0/manifest.json")
+            .is_file());
+        assert!(seeded
+            .join("Local Extension Settings/abcdefghijklmnop")
+            .is_dir());
+        assert!(seeded.join("Preferences").is_file());
+        assert!(!seeded.join("Extension State").exists());
+        assert!(!seeded.join("Cookies").exists());
+        assert!(!seeded.join("History").exists());
+    }
+
+    #[test]
+    fn chromium_profiles_are_seeded_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = chromium_default_profile(dir.path());
+        let path = dir.path().join("webapp");
+        let build = || {
+            Chromium::builder("chromium".to_string())
+                .base_profile(Some(base.clone()))
+                .profile_path(path.clone())
+                .build()
+        };
+
+        let exec = build();
+        assert!(exec.contains(&format!("--user-data-dir={}", path.to_str().unwrap())));
+        assert!(path.join("Default/Preferences").is_file());
+
+        // an existing profile is left as the web app changed it
+        fs::write(path.join("Default/Preferences"), "changed").unwrap();
+        build();
+        assert_eq!(
+            fs::read_to_string(path.join("Default/Preferences")).unwrap(),
+            "changed"
+        );
+
+        // shared profiles aren't touched
+        let shared = dir.path().join("shared");
+        Chromium::builder("chromium".to_string())
+            .isolated(false)
+            .base_profile(Some(base.clone()))
+            .profile_path(shared.clone())
+            .build();
+        assert!(!shared.exists());
+    }
+
+    #[test]
+    fn profile_roots_are_absolute() {
+        let browser = |path: &str| Browser {
+            profile_path: PathBuf::from(path),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            browser("/data/quick-webapps/profiles/chromium")
+                .profile_path()
+                .unwrap(),
+            PathBuf::from("/data/quick-webapps/profiles/chromium")
+        );
+        if let Some(home) = home_dir() {
+            assert_eq!(
+                browser(".var/app/org.chromium.Chromium/data/quick-webapps")
+                    .profile_path()
+                    .unwrap(),
+                home.join(".var/app/org.chromium.Chromium/data/quick-webapps")
+            );
+        }
+        assert!(matches!(browser("").profile_path(), Err(Error::NoHomeDir)));
+    }
+
+    #[test]
+    fn homebrew_browsers_are_detected() {
+        let prefix = tempfile::tempdir().unwrap();
+        let bin = prefix.path().join("bin");
+        let applications = prefix.path().join("share/applications");
+        fs::create_dir_all(&bin).unwrap();
+        fs::create_dir_all(&applications).unwrap();
+        fs::write(bin.join("chromium"), "#!/bin/sh\n").unwrap();
+        let desktop_file = applications.join("chromium.desktop");
+        fs::write(
+            &desktop_file,
+            "[Desktop Entry]\nType=Application\nName=Chromium\nExec=chromium %U\n",
+        )
+        .unwrap();
+        crate::testing::set_brew_prefix(prefix.path().to_path_buf());
+
+        assert!(fd_entries().iter().any(|entry| entry.path == desktop_file));
+
+        let browser = Browser::from_path(&desktop_file);
+        assert_eq!(browser.model, Some(BrowserModel::Chromium));
+        assert_eq!(browser.source, BrowserSource::Homebrew);
+        assert!(browser.name.ends_with(" (Homebrew)"), "{}", browser.name);
+        // the session's `$PATH` may not have brew's bin
+        assert_eq!(
+            browser.exec,
+            format!("{} %U", bin.join("chromium").display())
+        );
+    }
+
+    #[test]
+    fn hidden_browsers_are_left_out_of_the_picker() {
+        let prefix = tempfile::tempdir().unwrap();
+        let applications = prefix.path().join("share/applications");
+        fs::create_dir_all(&applications).unwrap();
+        for (exec, name) in [("chromium", "Chromium"), ("firefox", "Firefox")] {
+            fs::write(
+                applications.join(format!("{}.desktop", exec)),
+                format!(
+                    "[Desktop Entry]\nType=Application\nName={}\nExec={} %U\n",
+                    name, exec
+                ),
+            )
+            .unwrap();
+        }
+        crate::testing::set_brew_prefix(prefix.path().to_path_buf());
+
+        let of_file = |browsers: &[Browser], file: &str| {
+            let path = applications.join(file);
+            browsers
+                .iter()
+                .find(|browser| {
+                    browser
+                        .entry
+                        .as_ref()
+                        .is_some_and(|entry| entry.path == path)
+                })
+                .cloned()
+        };
+
+        let chromium = of_file(&detected_browsers(), "chromium.desktop").unwrap();
+        let id = chromium.id().unwrap();
+        crate::testing::set_config(AppConfig {
+            hidden_browsers: vec![id.as_ref().to_string()],
+            ..AppConfig::default()
+        });
+
+        let installed = installed_browsers();
+        assert_eq!(of_file(&installed, "chromium.desktop"), None);
+        assert!(of_file(&installed, "firefox.desktop").is_some());
+
+        // web apps made with it before still find it
+        assert_eq!(
+            of_file(&detected_browsers(), "chromium.desktop"),
+            Some(chromium)
+        );
+        assert_eq!(
+            browser_by_id(&id).and_then(|browser| browser.model),
+            Some(BrowserModel::Chromium)
+        );
+    }
+
+    #[test]
+    fn brew_exec_keeps_absolute_and_unknown_binaries() {
+        let prefix = tempfile::tempdir().unwrap();
+        let prefixes = vec![prefix.path().to_path_buf()];
+
+        assert_eq!(
+            resolve_brew_exec("/usr/bin/chromium %U", &prefixes),
+            "/usr/bin/chromium %U"
+        );
+        assert_eq!(resolve_brew_exec("chromium %U", &prefixes), "chromium %U");
+    }
+
+    #[test]
+    fn default_profile_is_reused_when_asked() {
+        let exec = Firefox::builder("firefox".to_string())
+            .base_profile(Some(PathBuf::from(
+                "/home/user/.mozilla/firefox/abcd.default",
+            )))
+            .reuse_profile(true)
+            .profile_path(PathBuf::from("/nonexistent/webapp"))
+            .build();
+
+        assert!(exec.contains("--profile \"/home/user/.mozilla/firefox/abcd.default\""));
+        assert!(!exec.contains("--no-remote"));
+        assert!(!Path::new("/nonexistent/webapp").exists());
+    }
+
+    #[test]
+    fn major_version_of_version_output() {
+        assert_eq!(
+            major_version("Chromium 124.0.6367.78 built on Debian"),
+            Some(124)
+        );
+        assert_eq!(major_version("Brave Browser 124.1.65.114"), Some(124));
+        assert_eq!(major_version("Google Chrome"), None);
+    }
+
+    #[test]
+    fn pwa_needs_a_supporting_browser_and_version() {
+        let chromium = BrowserModel::Chromium;
+        let firefox = BrowserModel::Firefox;
+
+        assert_eq!(
+            LaunchStrategy::select(true, Some(&chromium), Some("Chromium 124.0.6367.78")),
+            LaunchStrategy::Pwa
+        );
+        assert_eq!(
+            LaunchStrategy::select(true, Some(&chromium), Some("Chromium 121.0.6167.85")),
+            LaunchStrategy::App
+        );
+        assert_eq!(
+            LaunchStrategy::select(false, Some(&chromium), Some("Chromium 124.0.6367.78")),
+            LaunchStrategy::App
+        );
+        assert_eq!(
+            LaunchStrategy::select(true, Some(&chromium), None),
+            LaunchStrategy::App
+        );
+        assert_eq!(
+            LaunchStrategy::select(true, Some(&firefox), Some("Mozilla Firefox 128.0")),
+            LaunchStrategy::App
+        );
+        assert_eq!(
+            LaunchStrategy::select(true, None, Some("124.0")),
+            LaunchStrategy::App
+        );
+    }
+
+    // Flathub ids of the Gecko browsers keeping profiles in their sandbox home
+    const GECKO_FLATPAKS: [(BrowserModel, &str); 5] = [
+        (BrowserModel::Firefox, "org.mozilla.firefox"),
+        (BrowserModel::Floorp, "one.ablaze.floorp"),
+        (BrowserModel::Librewolf, "io.gitlab.librewolf-community"),
+        (BrowserModel::Waterfox, "net.waterfox.waterfox"),
+        (BrowserModel::Zen, "app.zen_browser.zen"),
+    ];
+
+    #[test]
+    fn flatpak_profiles_are_where_the_browser_reads_them() {
+        let home = Path::new("/home/user");
+
+        assert_eq!(
+            flatpak_profile_root(&BrowserModel::Firefox, "org.mozilla.firefox", home),
+            Some(PathBuf::from(
+                "/home/user/.var/app/org.mozilla.firefox/.mozilla/firefox/quick-webapps"
+            ))
+        );
+        assert_eq!(
+            flatpak_profile_root(&BrowserModel::Zen, "app.zen_browser.zen", home),
+            Some(PathBuf::from(
+                "/home/user/.var/app/app.zen_browser.zen/.zen/quick-webapps"
+            ))
+        );
+        assert_eq!(
+            flatpak_profile_root(&BrowserModel::Chromium, "org.chromium.Chromium", home),
+            None
+        );
+    }
+
+    #[test]
+    fn flatpak_manifest_grants_the_gecko_profile_roots() {
+        let manifest: serde_json::Value =
+            serde_json::from_str(include_str!("../dev.heppen.webapps.json")).unwrap();
+        let finish_args = manifest["finish-args"].as_array().unwrap();
+
+        for model in BrowserModel::iter().filter(|model| model.flatpak_profiles_dir().is_some()) {
+            let (_, appid) = GECKO_FLATPAKS
+                .iter()
+                .find(|(gecko, _)| *gecko == model)
+                .unwrap_or_else(|| panic!("no flatpak id for {:?}", model));
+            let permission = format!("--filesystem=~/.var/app/{}:create", appid);
+
+            assert!(
+                finish_args
+                    .iter()
+                    .any(|arg| arg.as_str() == Some(&permission)),
+                "{}",
+                permission
+            );
+        }
+    }
+
+    #[test]
+    fn parallel_detection_matches_serial_detection() {
+        let root = crate::testing::data_root();
+        let appids = [
+            "firefox",
+            "gedit",
+            "chromium",
+            "brave-browser",
+            "org.mozilla.firefox",
+            "vivaldi-stable",
+            "falkon",
+        ];
+        let entries: Vec<DesktopEntry> = (0..6)
+            .flat_map(|copy| appids.map(|appid| (copy, appid)))
+            .map(|(copy, appid)| {
+                let dir = root.path().join(format!("applications-{}", copy));
+                let path = dir.join(format!("{}.desktop", appid));
+                fs::create_dir_all(&dir).unwrap();
+                // copies of the same browser start the same program
+                fs::write(
+                    &path,
+                    format!("[Desktop Entry]\nType=Application\nName={appid}\nExec={appid} %u\n"),
+                )
+                .unwrap();
+                DesktopEntry::from_path(&path, Some(&LOCALES)).unwrap()
+            })
+            .collect();
+
+        let mut serial: Vec<Browser> = Vec::new();
+        for browser in entries.iter().map(|entry| Browser::from_path(&entry.path)) {
+            if browser.model.is_some() && !serial.iter().any(|app| app.exec == browser.exec) {
+                serial.push(browser);
             }
         }
 
-        let browser = Browser::from_path(&entry.path);
+        let parallel = resolve_browsers(&entries);
+        assert_eq!(parallel, serial);
+        assert_eq!(
+            parallel
+                .iter()
+                .map(|browser| browser.model.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                Some(BrowserModel::Firefox),
+                Some(BrowserModel::Chromium),
+                Some(BrowserModel::Brave),
+                Some(BrowserModel::Firefox),
+                Some(BrowserModel::Vivaldi),
+                Some(BrowserModel::Falkon),
+            ]
+        );
+    }
 
-        if browser.model.is_some() && !apps.contains(&browser) {
-            apps.push(browser);
+    #[test]
+    fn default_browser_is_found_by_its_desktop_id() {
+        let root = tempfile::tempdir().unwrap();
+        let browsers: Vec<Browser> = ["chromium", "firefox", "gedit"]
+            .iter()
+            .map(|appid| {
+                let path = root.path().join(format!("{}.desktop", appid));
+                fs::write(
+                    &path,
+                    format!("[Desktop Entry]\nType=Application\nName={appid}\nExec={appid} %u\n"),
+                )
+                .unwrap();
+                Browser::from_path(&path)
+            })
+            .collect();
+
+        assert_eq!(browser_with_entry_id(&browsers, "firefox"), Some(1));
+        assert_eq!(browsers[1].model, Some(BrowserModel::Firefox));
+        assert_eq!(browser_with_entry_id(&browsers, "chromium"), Some(0));
+        // a default which isn't a supported browser isn't preselected
+        assert_eq!(browser_with_entry_id(&browsers, "org.gnome.Epiphany"), None);
+    }
+
+    #[test]
+    fn flatpak_browser_uses_the_existing_export() {
+        let home = tempfile::tempdir().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        let appid = "org.mozilla.firefox";
+
+        assert_eq!(
+            flatpak_export(
+                appid,
+                &BrowserSource::SystemFlatpak,
+                home.path(),
+                root.path()
+            ),
+            None
+        );
+
+        let user = home
+            .path()
+            .join(".local/share/flatpak/exports/bin")
+            .join(appid);
+        fs::create_dir_all(user.parent().unwrap()).unwrap();
+        fs::write(&user, "").unwrap();
+
+        // whichever installation the entry came from, only the user one exists
+        for source in [BrowserSource::Flatpak, BrowserSource::SystemFlatpak] {
+            assert_eq!(
+                flatpak_export(appid, &source, home.path(), root.path()),
+                Some((BrowserSource::Flatpak, user.clone()))
+            );
         }
     }
 
-    apps
+    #[test]
+    fn native_browsers_fall_back_to_opt() {
+        let root = tempfile::tempdir().unwrap();
+        let exec = "/usr/bin/google-chrome-stable --new-window";
+
+        // without the vendor layout the desktop entry is kept as it is
+        assert_eq!(
+            resolve_native_exec(&BrowserModel::Chrome, exec, root.path()),
+            exec
+        );
+
+        let binary = root.path().join("opt/google/chrome/google-chrome");
+        fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        fs::write(&binary, "").unwrap();
+
+        assert_eq!(
+            resolve_native_exec(&BrowserModel::Chrome, exec, root.path()),
+            format!("{} --new-window", binary.display())
+        );
+        // only the layouts of the same vendor are used
+        assert_eq!(
+            resolve_native_exec(
+                &BrowserModel::Vivaldi,
+                "/usr/bin/vivaldi-stable",
+                root.path()
+            ),
+            "/usr/bin/vivaldi-stable"
+        );
+    }
+
+    #[test]
+    fn new_windows_open_as_tabs_when_enabled() {
+        for zen_browser in [false, true] {
+            let prefs = Firefox::builder("firefox".to_string())
+                .zen_browser(zen_browser)
+                .new_windows_as_tabs(true)
+                .user_prefs();
+            let last_line = prefs.lines().rev().nth(1).unwrap();
+
+            assert_eq!(last_line, "user_pref(\"browser.link.open_newwindow\", 3);");
+            assert!(prefs.ends_with("user_pref(\"browser.link.open_newwindow.restriction\", 0);\n"));
+        }
+    }
+
+    #[test]
+    fn new_windows_stay_app_windows_by_default() {
+        for zen_browser in [false, true] {
+            let prefs = Firefox::builder("firefox".to_string())
+                .zen_browser(zen_browser)
+                .user_prefs();
+
+            assert!(prefs.contains("user_pref(\"browser.link.open_newwindow\", 2);"));
+            assert!(!prefs.contains("open_newwindow\", 3"));
+            assert!(!prefs.contains("open_newwindow.restriction"));
+        }
+    }
+
+    fn browser(exec: &str) -> Browser {
+        Browser {
+            exec: exec.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn try_exec_skips_env_assignments() {
+        let browser = browser("env MOZ_ENABLE_WAYLAND=1 GDK_BACKEND=wayland firefox --new-window");
+
+        assert_eq!(browser.executable(), Some("firefox"));
+        assert_eq!(browser.try_exec(), Some("firefox"));
+    }
+
+    #[test]
+    fn try_exec_is_left_out_for_wrappers() {
+        assert_eq!(browser("flatpak run org.mozilla.firefox").try_exec(), None);
+        assert_eq!(
+            browser("/usr/bin/flatpak run org.chromium.Chromium").try_exec(),
+            None
+        );
+        assert_eq!(
+            browser("/var/lib/flatpak/exports/bin/org.chromium.Chromium").try_exec(),
+            Some("/var/lib/flatpak/exports/bin/org.chromium.Chromium")
+        );
+        assert_eq!(
+            browser("\"/opt/brave.com/brave/brave\" --app").try_exec(),
+            Some("/opt/brave.com/brave/brave")
+        );
+    }
 }
@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+#[derive(Debug, Default, Clone)]
+pub struct Args {
+    pub audit_icons: bool,
+    // keeps every written file under this directory instead of `~/.local/share`
+    pub data_dir: Option<PathBuf>,
+    // codename of the web app written to `<codename>.qwa`
+    pub export: Option<String>,
+    pub import: Option<PathBuf>,
+    pub list_browsers: bool,
+    pub offline: bool,
+    // RON list of web apps to install, see `launcher::reconcile`
+    pub reconcile: Option<PathBuf>,
+    pub prune: bool,
+    pub refresh: bool,
+    // installs desktop files and icons for all users
+    pub system: bool,
+    pub upgrade_icons: bool,
+    // `--export` also bundles the isolated profile, without logins and cookies
+    pub with_profile: bool,
+    // prefills the editor, e.g. with the page open in a browser
+    pub url: Option<String>,
+    pub title: Option<String>,
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        let mut args = Args::default();
+
+        let mut env_args = std::env::args().skip(1);
+
+        while let Some(arg) = env_args.next() {
+            match arg.as_str() {
+                "--audit-icons" => args.audit_icons = true,
+                "--data-dir" => args.data_dir = env_args.next().map(PathBuf::from),
+                "--export" => args.export = env_args.next(),
+                "--import" => args.import = env_args.next().map(PathBuf::from),
+                "--list-browsers" => args.list_browsers = true,
+                "--offline" => args.offline = true,
+                "--prune" => args.prune = true,
+                "--reconcile" => args.reconcile = env_args.next().map(PathBuf::from),
+                "--refresh" => args.refresh = true,
+                "--system" => args.system = true,
+                "--title" => args.title = env_args.next(),
+                "--upgrade-icons" => args.upgrade_icons = true,
+                "--url" => args.url = env_args.next(),
+                "--with-profile" => args.with_profile = true,
+                _ => tracing::warn!("unknown argument: {}", arg),
+            }
+        }
+
+        args
+    }
+}
@@ -1,350 +1,3409 @@
 use std::{
+    collections::{HashMap, HashSet},
     ffi::OsStr,
-    fs::{self, copy, create_dir_all, File},
+    fs::{self, create_dir_all},
     io::{Cursor, Read},
-    path::PathBuf,
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
     str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
 };
 
+use anyhow::{anyhow, bail, Result};
 use base64::prelude::*;
 use bytes::Bytes;
 use cosmic::{iced_core, widget};
 use freedesktop_desktop_entry::{default_paths, DesktopEntry, Iter};
+use image::codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder};
+use image::imageops::FilterType;
 use image::ImageReader;
-use image::{load_from_memory, GenericImageView};
+use image::{load_from_memory, AnimationDecoder, DynamicImage, GenericImageView};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
 use svg::node::element::Image;
 use svg::Document;
-use url::Url;
+use url::{Host, Url};
 use walkdir::WalkDir;
 
-use crate::{favicon, LOCALES};
+use crate::{config::AppConfig, error::Error, favicon, fl, APP_ID, ARGS, LOCALES};
 
 const ICON_SIZE: u32 = 42;
+// 512 keeps icons sharp on HiDPI displays
+const HICOLOR_SIZES: [u32; 8] = [16, 24, 32, 48, 64, 128, 256, 512];
+pub const HICOLOR_MAX_SIZE: u32 = 512;
+const MENU_DIRECTORY_FILE: &str = "quick-webapps.directory";
+const MENU_FILE: &str = "quick-webapps.menu";
+// additional category of web apps shown in the menu folder
+pub const MENU_CATEGORY: &str = "X-QuickWebApps";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumIter, Deserialize, Serialize)]
+pub enum IconFormat {
+    #[default]
+    Svg,
+    Png,
+    HicolorPngSet,
+}
 
-pub fn url_valid(url: &str) -> bool {
-    Url::parse(url).is_ok()
+impl IconFormat {
+    pub fn name(&self) -> String {
+        match self {
+            IconFormat::Svg => fl!("icon-format-svg"),
+            IconFormat::Png => fl!("icon-format-png"),
+            IconFormat::HicolorPngSet => fl!("icon-format-hicolor"),
+        }
+    }
 }
 
-pub fn is_svg(path: &str) -> bool {
-    if !url_valid(path) {
-        let Ok(pb) = PathBuf::from_str(path);
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumIter, Deserialize, Serialize)]
+pub enum LaunchMethod {
+    // `gtk-launch` with the desktop file id, so the desktop tracks the app
+    #[default]
+    DesktopEntry,
+    // the `Exec=` line of the desktop file, run directly
+    Exec,
+}
 
-        if pb.extension() == Some(OsStr::new("svg")) {
-            return true;
+impl LaunchMethod {
+    pub fn name(&self) -> String {
+        match self {
+            LaunchMethod::DesktopEntry => fl!("launch-desktop-entry"),
+            LaunchMethod::Exec => fl!("launch-exec"),
         }
     }
-    false
 }
 
-pub fn themes_path(theme_file: &str) -> PathBuf {
-    if let Some(xdg_data) = dirs::data_dir() {
-        let path = xdg_data.join("quick-webapps/themes");
+pub fn program_in_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
 
-        if !path.exists() {
-            create_dir_all(&path).unwrap();
+pub fn max_download_size() -> u64 {
+    AppConfig::config().max_download_size
+}
+
+// Reads the response body in chunks and gives up as soon as it exceeds `limit`,
+// so a huge "favicon" is never buffered as a whole.
+pub async fn read_body_limited(
+    mut response: reqwest::Response,
+    limit: u64,
+) -> Result<Bytes, Error> {
+    let too_large = |response: &reqwest::Response| Error::TooLarge {
+        url: response.url().to_string(),
+        limit,
+    };
+
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit)
+    {
+        return Err(too_large(&response));
+    }
+
+    let mut buffer: Vec<u8> = Vec::new();
+
+    while let Some(chunk) = response.chunk().await? {
+        if (buffer.len() + chunk.len()) as u64 > limit {
+            return Err(too_large(&response));
         }
 
-        return path.join(theme_file);
+        buffer.extend_from_slice(&chunk);
     }
 
-    PathBuf::new()
+    Ok(Bytes::from(buffer))
 }
 
-pub fn database_path(entry: &str) -> PathBuf {
-    if let Some(xdg_data) = dirs::data_dir() {
-        let path = xdg_data.join("quick-webapps/database");
+// Characters of an `Exec` argument which require it to be quoted.
+const EXEC_RESERVED: &[char] = &[
+    ' ', '\t', '\n', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(', ')',
+    '`',
+];
 
-        if !path.exists() {
-            create_dir_all(&path).unwrap();
+// Double quoted `Exec` argument, the characters still special inside the
+// quotes are escaped with a backslash.
+pub fn quote_exec_arg(arg: &str) -> String {
+    let mut quoted = String::from('"');
+
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
         }
+        quoted.push(c);
+    }
 
-        return path.join(entry);
+    quoted.push('"');
+    quoted
+}
+
+// `Exec` argument, quoted only when it holds reserved characters.
+pub fn exec_arg(arg: &str) -> String {
+    match arg.contains(EXEC_RESERVED) {
+        true => quote_exec_arg(arg),
+        false => arg.to_string(),
     }
+}
 
-    PathBuf::new()
+// Value of a string key like `Name=`, which can't span lines.
+pub fn escape_desktop_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
 }
 
-pub fn desktop_files_location(filename: &str) -> PathBuf {
-    if let Some(xdg_data) = dirs::data_dir() {
-        let dir = xdg_data.join("applications");
+// Reverses `escape_desktop_value`, escapes of other characters are kept as written.
+pub fn unescape_desktop_value(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
 
-        if !dir.exists() {
-            let _ = create_dir_all(&dir);
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
         }
 
-        return dir.join(format!("dev.heppen.webapps.{}.desktop", filename));
+        match chars.next() {
+            Some('s') => unescaped.push(' '),
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some('\\') => unescaped.push('\\'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
     }
 
-    PathBuf::new()
+    unescaped
+}
+
+// Value of `Exec=`: a `%` would be read as a field code, and the already
+// quoted command line is escaped once more as a string.
+pub fn escape_exec(exec: &str) -> String {
+    escape_desktop_value(&exec.replace('%', "%%"))
 }
 
-pub fn icons_location() -> PathBuf {
-    if let Some(home_dir) = dirs::home_dir() {
-        return home_dir.join(".local/share/icons");
+// Path of a `file://` url, for web apps of a local page like a web UI in development.
+pub fn local_file_path(url: &str) -> Option<PathBuf> {
+    let url = Url::parse(url).ok()?;
+
+    if url.scheme() != "file" {
+        return None;
     }
 
-    PathBuf::new()
+    url.to_file_path().ok()
 }
 
-pub fn system_icons() -> PathBuf {
-    PathBuf::from_str("/usr/share/icons").unwrap_or_default()
+pub fn url_valid(url: &str) -> bool {
+    Url::parse(url).is_ok()
 }
 
-pub fn qwa_icons_location() -> PathBuf {
-    icons_location().join("QuickWebApps")
+// Host in the form used for names and cache keys: lowercase and without the
+// trailing dot of a fully qualified name, `github.com` for `GitHub.COM.`.
+pub fn normalized_host(url: &Url) -> Option<String> {
+    Some(url.host_str()?.trim_end_matches('.').to_lowercase())
 }
 
-pub fn is_sandboxed() -> bool {
-    PathBuf::from("/.flatpak-info").exists()
+// Form used to compare web app urls: scheme, host, port and path, where default
+// ports and trailing slashes are dropped. Query and fragment are ignored.
+pub fn normalized_url(url: &str) -> Option<String> {
+    let url = Url::parse(url.trim()).ok()?;
+    let mut normalized = format!("{}://{}", url.scheme(), normalized_host(&url)?);
+
+    // `Url` already leaves out the port when it's the default one of the scheme
+    if let Some(port) = url.port() {
+        normalized.push_str(&format!(":{}", port));
+    }
+
+    normalized.push_str(url.path().trim_end_matches('/'));
+
+    Some(normalized)
 }
 
-pub fn fd_entries() -> Vec<DesktopEntry> {
-    let mut paths = Vec::new();
+// The url without `user:password@`, when it has any. Credentials in the url
+// would end up readable by anyone in the desktop file's `Exec=` line.
+pub fn without_credentials(url: &str) -> Option<String> {
+    let mut parsed = Url::parse(url).ok()?;
 
-    // this is workaround for flatpak sandbox
-    if is_sandboxed() {
-        if let Some(home) = dirs::home_dir() {
-            paths.push(home.join(".local/share/flatpak/exports/share/applications"));
-        }
-        paths.push("/var/lib/flatpak/exports/share/applications".into());
-        paths.push("/run/host/usr/share/applications".into());
-        paths.push("/run/host/usr/local/share/applications".into());
-    };
+    if parsed.username().is_empty() && parsed.password().is_none() {
+        return None;
+    }
 
-    default_paths().for_each(|path| {
-        if !paths.contains(&path) {
-            paths.push(path)
-        }
-    });
+    parsed.set_username("").ok()?;
+    parsed.set_password(None).ok()?;
 
-    Iter::new(paths.into_iter())
-        .entries(Some(&LOCALES))
-        .collect::<Vec<DesktopEntry>>()
+    Some(parsed.to_string())
 }
 
-pub fn get_icon_name_from_url(url: &str) -> String {
-    match Url::parse(url) {
-        Ok(url) => match url.host_str() {
-            Some(host) => {
-                let parts: Vec<&str> = host.split('.').collect();
-                parts[parts.len() - 2].to_string()
-            }
-            None => String::new(),
-        },
-        Err(_) => String::new(),
+// Removes query parameters matching `params`, where a trailing `*` matches
+// any suffix, like `utm_*`. Other parameters keep their order.
+pub fn strip_tracking_params(url: &str, params: &[String]) -> String {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+
+    let tracking = |name: &str| {
+        params.iter().any(|param| match param.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == param,
+        })
+    };
+
+    let kept: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(name, _)| !tracking(name))
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+
+    if parsed.query_pairs().count() == kept.len() {
+        return url.to_string();
+    }
+
+    if kept.is_empty() {
+        parsed.set_query(None);
+    } else {
+        parsed.query_pairs_mut().clear().extend_pairs(kept);
     }
+
+    parsed.to_string()
 }
 
-pub async fn find_icon(path: PathBuf, icon_name: String) -> Vec<String> {
-    let mut icons: Vec<String> = Vec::new();
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReachStatus {
+    Reachable,
+    RedirectedTo(String),
+    AuthRequired,
+    NotFound,
+    NetworkError(String),
+    // not checked at all, because of offline mode
+    Skipped,
+}
 
-    for entry in WalkDir::new(&path).into_iter().filter_map(|e| e.ok()) {
-        if let Some(filename) = entry.file_name().to_str() {
-            if filename.contains(&icon_name) {
-                if is_svg(filename) {
-                    if let Some(path) = entry.path().to_str() {
-                        if let Ok(buffer) = tokio::fs::read_to_string(&mut path.to_string()).await {
-                            let options = usvg::Options::default();
-                            if let Ok(parsed) = usvg::Tree::from_str(&buffer, &options) {
-                                let size = parsed.size();
-                                if size.width() >= ICON_SIZE as f32
-                                    && size.height() >= ICON_SIZE as f32
-                                    && !icons.contains(&path.to_string())
-                                {
-                                    icons.push(path.to_string())
-                                }
-                            }
-                        }
-                    }
-                } else if let Some(path) = entry.path().to_str() {
-                    if let Ok(image) = ImageReader::open(path) {
-                        if let Ok(img) = image.decode() {
-                            if img.width() >= ICON_SIZE
-                                && img.height() >= ICON_SIZE
-                                && !icons.contains(&path.to_string())
-                            {
-                                icons.push(path.to_string())
-                            }
-                        }
-                    }
+impl ReachStatus {
+    fn from_response(requested: &Url, response: &reqwest::Response) -> Self {
+        let status = response.status();
+
+        match status.as_u16() {
+            401 | 403 => ReachStatus::AuthRequired,
+            404 | 410 => ReachStatus::NotFound,
+            _ if status.is_success() => {
+                if response.url() != requested {
+                    ReachStatus::RedirectedTo(response.url().to_string())
+                } else {
+                    ReachStatus::Reachable
                 }
             }
+            _ => ReachStatus::NetworkError(status.to_string()),
         }
     }
+}
 
-    icons
+fn client_builder() -> reqwest::ClientBuilder {
+    let timeout = std::time::Duration::from_secs(AppConfig::config().request_timeout);
+
+    reqwest::Client::builder().timeout(timeout)
+}
+
+pub fn http_client() -> reqwest::Client {
+    client_builder().build().unwrap_or_default()
 }
 
-pub async fn find_icons(icon_name: String, url: String) -> Vec<String> {
-    let mut result: Vec<String> = Vec::new();
+// Some CDNs negotiate the content and answer with an HTML page
+// unless the request says it wants an image.
+pub fn icon_client() -> reqwest::Client {
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(
+        reqwest::header::ACCEPT,
+        reqwest::header::HeaderValue::from_static(
+            "image/svg+xml,image/png,image/webp,image/x-icon,image/*;q=0.8,*/*;q=0.5",
+        ),
+    );
+
+    let mut builder = client_builder().default_headers(headers);
+
+    // only for icons of self-hosted services with self-signed certificates,
+    // no other request ever skips the verification
+    if AppConfig::config().accept_invalid_icon_certs {
+        tracing::warn!("INSECURE: TLS certificates are not verified when downloading icons");
+        builder = builder.danger_accept_invalid_certs(true);
+    }
 
-    if url_valid(&url) {
-        if let Ok(data) = favicon::download_favicon(&url).await {
-            result.extend(data)
-        }
+    builder.build().unwrap_or_default()
+}
+
+// Lets the user know about a mistyped or moved url before the web app is created.
+// Some servers don't implement HEAD, those are asked again with GET.
+pub async fn check_url_reachable(url: &str) -> ReachStatus {
+    if let Some(path) = local_file_path(url) {
+        return match path.is_file() {
+            true => ReachStatus::Reachable,
+            false => ReachStatus::NotFound,
+        };
+    }
+
+    if IconSearchOptions::from_config().offline {
+        return ReachStatus::Skipped;
+    }
+
+    let requested = match Url::parse(url) {
+        Ok(url) => url,
+        Err(err) => return ReachStatus::NetworkError(err.to_string()),
     };
 
-    result.extend(find_icon(icons_location(), icon_name.clone()).await);
-    result.extend(find_icon(system_icons(), icon_name).await);
+    let client = http_client();
 
-    result
+    let status = match client.head(requested.clone()).send().await {
+        Ok(response) => ReachStatus::from_response(&requested, &response),
+        Err(err) => ReachStatus::NetworkError(err.to_string()),
+    };
+
+    if matches!(
+        status,
+        ReachStatus::Reachable | ReachStatus::RedirectedTo(_)
+    ) {
+        return status;
+    }
+
+    match client.get(requested.clone()).send().await {
+        Ok(response) => ReachStatus::from_response(&requested, &response),
+        Err(err) => {
+            tracing::debug!("{} is not reachable: {}", url, err);
+            ReachStatus::NetworkError(err.to_string())
+        }
+    }
 }
 
-pub fn convert_raster_to_svg_format(img_slice: Bytes, icon_name: &str) -> String {
-    let save_path = icon_save_path(icon_name);
+// Text of the `<title>` element with the site name suffix, like " | GitHub", removed.
+pub fn extract_page_title(html: &str) -> Option<String> {
+    let lowercase = html.to_ascii_lowercase();
+    let start = lowercase.find("<title")?;
+    let content_start = start + lowercase[start..].find('>')? + 1;
+    let content_end = content_start + lowercase[content_start..].find("</title")?;
+
+    let title = html[content_start..content_end]
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .replace("&amp;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">");
+
+    let name = [" | ", " - ", " – ", " — ", " · "]
+        .iter()
+        .filter_map(|separator| title.split_once(separator).map(|(name, _)| name.trim()))
+        .filter(|name| !name.is_empty())
+        .min_by_key(|name| name.len())
+        .unwrap_or(title.trim());
+
+    if name.is_empty() {
+        return None;
+    }
 
-    if let Ok(data) = load_from_memory(&img_slice) {
-        let (width, height) = data.dimensions();
-        let mut image_buffer = Vec::new();
-        let mut image_cursor = Cursor::new(&mut image_buffer);
+    Some(name.to_string())
+}
 
-        data.write_to(&mut image_cursor, image::ImageFormat::Png)
-            .unwrap();
+pub async fn fetch_page_title(url: &str) -> Option<String> {
+    if let Some(path) = local_file_path(url) {
+        let page = tokio::fs::read(path).await.ok()?;
+        return extract_page_title(&String::from_utf8_lossy(&page));
+    }
 
-        let encoded_img = BASE64_STANDARD.encode(image_buffer);
+    if IconSearchOptions::from_config().offline {
+        return None;
+    }
+
+    let response = http_client().get(url).send().await.ok()?;
+
+    if !response.status().is_success() {
+        return None;
+    }
 
-        // Create an SVG document and embed the image
-        let image_element = Image::new()
-            .set("x", 0)
-            .set("y", 0)
-            .set("width", width)
-            .set("height", height)
-            .set("href", format!("data:image/png;base64,{}", encoded_img));
+    let body = read_body_limited(response, max_download_size())
+        .await
+        .ok()?;
 
-        let document = Document::new()
-            .set("width", width)
-            .set("height", height)
-            .add(image_element);
+    extract_page_title(&String::from_utf8_lossy(&body))
+}
 
-        // Save the SVG document
+pub fn is_svg(path: &str) -> bool {
+    if !url_valid(path) {
+        let Ok(pb) = PathBuf::from_str(path);
 
-        let _ = svg::save(&save_path, &document).is_ok();
+        if pb.extension() == Some(OsStr::new("svg")) {
+            return true;
+        }
     }
+    false
+}
 
-    save_path
+// Who the desktop files and icons are installed for. The database, themes
+// and profiles always stay with the user.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum InstallScope {
+    #[default]
+    User,
+    System,
 }
 
-fn icon_save_path(icon_name: &str) -> String {
-    qwa_icons_location()
-        .join(format!("{}.svg", icon_name))
-        .to_str()
-        .unwrap()
-        .to_string()
+// Locations of everything the app writes. With `--data-dir` they all live under
+// the given directory, so the real `~/.local/share` is never touched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataPaths {
+    // XDG data home, holding desktop files and the app's own data
+    data: Option<PathBuf>,
+    icons: Option<PathBuf>,
+    // `/usr/share`, used for the system scope
+    shared: PathBuf,
+    // XDG config home and `/etc/xdg`, holding menu definitions
+    config: Option<PathBuf>,
+    sysconf: PathBuf,
+    scope: InstallScope,
 }
 
-pub async fn move_icon(path: &str, output_name: &str) -> String {
-    create_dir_all(qwa_icons_location()).expect("cant create folder for your icons");
+impl DataPaths {
+    pub fn system() -> Self {
+        Self {
+            data: dirs::data_dir(),
+            icons: home_dir().map(|home| home.join(".local/share/icons")),
+            shared: PathBuf::from("/usr/share"),
+            config: dirs::config_dir(),
+            sysconf: PathBuf::from("/etc/xdg"),
+            scope: InstallScope::User,
+        }
+    }
 
-    let icon_name = output_name.replace(' ', "");
+    pub fn with_root(root: PathBuf) -> Self {
+        Self {
+            icons: Some(root.join("icons")),
+            shared: root.join("usr/share"),
+            config: Some(root.join("config")),
+            sysconf: root.join("etc/xdg"),
+            data: Some(root),
+            scope: InstallScope::User,
+        }
+    }
 
-    let Ok(p) = PathBuf::from_str(&icon_save_path(&icon_name));
+    pub fn scope(mut self, scope: InstallScope) -> Self {
+        self.scope = scope;
+        self
+    }
 
-    if p.exists() {
-        std::fs::remove_file(p).unwrap();
+    pub fn current() -> Self {
+        // tests never touch the user's data
+        #[cfg(test)]
+        let data_dir = Some(crate::testing::data_dir());
+        #[cfg(not(test))]
+        let data_dir = ARGS.data_dir.clone();
+
+        let paths = match data_dir {
+            Some(root) => Self::with_root(root),
+            None => Self::system(),
+        };
+
+        match ARGS.system {
+            true => paths.scope(InstallScope::System),
+            false => paths,
+        }
     }
 
-    if url_valid(path) {
-        let response = reqwest::get(path).await.expect("sending request");
+    // Installing for all users needs write access to `/usr/share`, which is
+    // better found out before anything is written.
+    pub fn check_writable(&self) -> Result<()> {
+        if self.scope == InstallScope::User {
+            return Ok(());
+        }
 
-        if response.status().is_success() {
-            let content: Bytes = response.bytes().await.expect("getting image bytes");
+        for dir in [self.shared.join("applications"), self.shared.join("icons")] {
+            let probe = dir.join(".qwa-write-test");
 
-            return convert_raster_to_svg_format(content, &icon_name);
+            if let Err(err) = fs::write(&probe, b"").and_then(|_| fs::remove_file(&probe)) {
+                bail!(
+                    "installing for all users requires write access to {:?}, \
+                     run as administrator: {}",
+                    dir,
+                    err
+                );
+            }
         }
 
-        return String::new();
-    };
+        Ok(())
+    }
 
-    if !is_svg(path) {
-        if let Ok(mut file) = File::open(path) {
-            let mut buffer = Vec::new();
-            file.read_to_end(&mut buffer).unwrap();
-            let content = Bytes::from(buffer);
+    fn data_subdir(&self, subdir: &str) -> Result<PathBuf, Error> {
+        let path = self.data.as_ref().ok_or(Error::NoHomeDir)?.join(subdir);
 
-            return convert_raster_to_svg_format(content, &icon_name);
+        if !path.exists() {
+            if let Err(err) = create_dir_all(&path) {
+                tracing::error!("can't create {:?}: {}", path, err);
+            }
         }
-    };
 
-    let save_path = icon_save_path(&icon_name);
-    let _ = copy(path, &save_path);
+        Ok(path)
+    }
+
+    pub fn applications(&self) -> Result<PathBuf, Error> {
+        match self.scope {
+            InstallScope::User => self.data_subdir("applications"),
+            InstallScope::System => Ok(self.shared.join("applications")),
+        }
+    }
+
+    pub fn desktop_directories(&self) -> Result<PathBuf, Error> {
+        match self.scope {
+            InstallScope::User => self.data_subdir("desktop-directories"),
+            InstallScope::System => Ok(self.shared.join("desktop-directories")),
+        }
+    }
+
+    pub fn merged_menus(&self) -> Result<PathBuf, Error> {
+        match self.scope {
+            InstallScope::User => Ok(self
+                .config
+                .as_ref()
+                .ok_or(Error::NoHomeDir)?
+                .join("menus/applications-merged")),
+            InstallScope::System => Ok(self.sysconf.join("menus/applications-merged")),
+        }
+    }
+
+    pub fn dbus_services(&self) -> Result<PathBuf, Error> {
+        match self.scope {
+            InstallScope::User => Ok(self
+                .data
+                .as_ref()
+                .ok_or(Error::NoHomeDir)?
+                .join("dbus-1/services")),
+            InstallScope::System => Ok(self.shared.join("dbus-1/services")),
+        }
+    }
+
+    pub fn database(&self) -> Result<PathBuf, Error> {
+        self.data_subdir("quick-webapps/database")
+    }
+
+    pub fn themes(&self) -> Result<PathBuf, Error> {
+        self.data_subdir("quick-webapps/themes")
+    }
+
+    pub fn profiles(&self) -> Result<PathBuf, Error> {
+        self.data
+            .as_ref()
+            .map(|data| data.join("quick-webapps/profiles"))
+            .ok_or(Error::NoHomeDir)
+    }
+
+    pub fn icons(&self) -> Result<PathBuf, Error> {
+        if self.scope == InstallScope::System {
+            return Ok(self.shared.join("icons"));
+        }
 
-    save_path
+        self.icons.clone().ok_or(Error::NoHomeDir)
+    }
 }
 
-pub async fn image_handle(path: String) -> Option<Icon> {
-    if url_valid(&path) {
-        if let Ok(response) = reqwest::Client::new().get(&path).send().await {
-            if let Ok(bytes) = response.bytes().await {
-                let options = usvg::Options::default();
-                if let Ok(parsed) = usvg::Tree::from_data(&bytes, &options) {
-                    let size = parsed.size();
-                    if size.width() >= 96.0 && size.height() >= 96.0 {
-                        let handle = widget::svg::Handle::from_memory(bytes.to_vec());
-                        return Some(Icon::new(IconType::Svg(handle), path, true));
-                    }
-                }
-                if let Ok(image_reader) =
-                    ImageReader::new(Cursor::new(&bytes)).with_guessed_format()
-                {
-                    if let Ok(image) = image_reader.decode() {
-                        if image.width() >= ICON_SIZE && image.height() >= ICON_SIZE {
-                            let handle = iced_core::image::Handle::from_bytes(bytes);
-                            return Some(Icon::new(IconType::Raster(handle), path, true));
-                        }
-                    };
-                }
-            }
+pub fn themes_path(theme_file: &str) -> Result<PathBuf, Error> {
+    Ok(DataPaths::current().themes()?.join(theme_file))
+}
+
+pub fn database_path(entry: &str) -> Result<PathBuf, Error> {
+    Ok(DataPaths::current().database()?.join(entry))
+}
+
+pub fn applications_location() -> Result<PathBuf, Error> {
+    DataPaths::current().applications()
+}
+
+// Desktop entry id of the web app, also used as its window class.
+pub fn webapp_id(codename: &str) -> String {
+    format!("{}.{}", APP_ID, codename)
+}
+
+// Path of the desktop file for the given web app. Used both when creating
+// and when looking up or removing the app, so they always agree.
+pub fn desktop_file_for(app_id: &str) -> Result<PathBuf, Error> {
+    Ok(applications_location()?.join(format!("{}.desktop", webapp_id(app_id))))
+}
+
+// `DBusActivatable` is only valid with a session service named after the web app.
+pub fn dbus_service_installed(app_id: &str) -> bool {
+    DataPaths::current()
+        .dbus_services()
+        .is_ok_and(|dir| dir.join(format!("{}.service", webapp_id(app_id))).exists())
+}
+
+pub fn home_dir() -> Option<PathBuf> {
+    dirs::home_dir()
+        .or_else(|| std::env::var_os("HOME").map(PathBuf::from))
+        .filter(|path| !path.as_os_str().is_empty())
+}
+
+// Homebrew on Linux installs into `$HOMEBREW_PREFIX`, by default the shared
+// `/home/linuxbrew/.linuxbrew` or `~/.linuxbrew` for a single user.
+pub fn brew_prefixes() -> Vec<PathBuf> {
+    let mut prefixes = Vec::new();
+
+    if let Some(prefix) = homebrew_prefix() {
+        prefixes.push(prefix);
+    }
+    prefixes.push(PathBuf::from("/home/linuxbrew/.linuxbrew"));
+    if let Some(home) = home_dir() {
+        prefixes.push(home.join(".linuxbrew"));
+    }
+
+    let mut existing: Vec<PathBuf> = Vec::new();
+    for prefix in prefixes {
+        if prefix.is_dir() && !existing.contains(&prefix) {
+            existing.push(prefix);
         }
-    };
+    }
 
-    let Ok(result_path) = PathBuf::from_str(&path);
+    existing
+}
 
-    if result_path.is_file() {
-        if is_svg(&path) {
-            let handle = widget::svg::Handle::from_path(&result_path);
+fn homebrew_prefix() -> Option<PathBuf> {
+    // tests install into a temporary prefix
+    #[cfg(test)]
+    if let Some(prefix) = crate::testing::brew_prefix() {
+        return Some(prefix);
+    }
 
-            return Some(Icon::new(IconType::Svg(handle), path, false));
-        } else {
-            let mut data: Vec<_> = Vec::new();
+    std::env::var_os("HOMEBREW_PREFIX")
+        .filter(|prefix| !prefix.is_empty())
+        .map(PathBuf::from)
+}
 
-            if let Ok(mut file) = fs::File::open(&result_path) {
-                let _ = file.read_to_end(&mut data);
-            }
+pub fn icons_location() -> Result<PathBuf, Error> {
+    DataPaths::current().icons()
+}
 
-            if let Ok(image_reader) = ImageReader::new(Cursor::new(&data)).with_guessed_format() {
-                if let Ok(image) = image_reader.decode() {
-                    if image.width() >= ICON_SIZE && image.height() >= ICON_SIZE {
-                        let handle = iced_core::image::Handle::from_bytes(data);
+pub fn system_icons() -> PathBuf {
+    PathBuf::from_str("/usr/share/icons").unwrap_or_default()
+}
 
-                        return Some(Icon::new(IconType::Raster(handle), path, false));
-                    }
-                };
+pub fn qwa_icons_location() -> Result<PathBuf, Error> {
+    Ok(icons_location()?.join("QuickWebApps"))
+}
+
+// Makes sure icons can be written to our icons directory. A directory left
+// read-only is repaired when we own it, one owned by somebody else (usually
+// root, after running once with sudo) gets an error explaining the fix.
+pub fn ensure_icons_writable() -> Result<PathBuf> {
+    let dir = qwa_icons_location()?;
+
+    if let Err(err) = create_dir_all(&dir) {
+        bail!("can't create icons directory {:?}: {}", dir, err);
+    }
+
+    let probe = dir.join(".qwa-write-test");
+    let writable = || fs::write(&probe, b"").and_then(|_| fs::remove_file(&probe));
+
+    let Err(err) = writable() else {
+        return Ok(dir);
+    };
+
+    let owner = fs::metadata(&dir).map(|meta| meta.uid()).ok();
+    let current = fs::metadata("/proc/self").map(|meta| meta.uid()).ok();
+
+    if owner.is_some() && owner == current {
+        if let Ok(meta) = fs::metadata(&dir) {
+            let mut permissions = meta.permissions();
+            permissions.set_mode(permissions.mode() | 0o700);
+
+            if fs::set_permissions(&dir, permissions).is_ok() && writable().is_ok() {
+                tracing::warn!("icons directory {:?} was read-only, repaired", dir);
+                return Ok(dir);
             }
         }
-    };
 
-    None
+        bail!(
+            "icons directory {:?} isn't writable ({}), fix it with `chmod -R u+rwX {}`",
+            dir,
+            err,
+            dir.display()
+        );
+    }
+
+    bail!(
+        "icons directory {:?} isn't writable ({}), it's owned by uid {} instead of uid {}, \
+         fix it with `sudo chown -R $USER {}`",
+        dir,
+        err,
+        owner.map_or("?".to_string(), |uid| uid.to_string()),
+        current.map_or("?".to_string(), |uid| uid.to_string()),
+        dir.display()
+    );
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum IconType {
-    Raster(widget::image::Handle),
-    Svg(widget::svg::Handle),
+pub fn hicolor_icons_location() -> Result<PathBuf, Error> {
+    Ok(icons_location()?.join("hicolor"))
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Icon {
-    pub icon: IconType,
-    pub path: String,
-    pub is_favicon: bool,
+// Returns a file path which can be displayed for the given `Icon=` value.
+// Icons installed into the hicolor theme are referenced only by their name.
+pub fn icon_path(icon: &str) -> String {
+    if icon.contains('/') {
+        return icon.to_string();
+    }
+
+    if let Ok(hicolor) = hicolor_icons_location() {
+        let candidates = [
+            hicolor.join(format!("256x256/apps/{}.png", icon)),
+            hicolor.join(format!("scalable/apps/{}.svg", icon)),
+        ];
+
+        if let Some(found) = candidates.iter().find(|path| path.exists()) {
+            return found.to_string_lossy().to_string();
+        }
+    }
+
+    if let Some(found) = named_icon_path(icon) {
+        return found.to_string_lossy().to_string();
+    }
+
+    icon.to_string()
 }
 
-impl Icon {
-    pub fn new(icon: IconType, path: String, is_favicon: bool) -> Self {
-        Self {
-            icon,
-            path,
-            is_favicon,
+// Sizes looked up for named icons, largest first.
+const NAMED_ICON_SIZES: [u32; 11] = [512, 256, 192, 128, 96, 64, 48, 32, 24, 22, 16];
+
+// File of a theme icon referenced by its freedesktop name, like `discord`.
+// Scalable icons are preferred, then the largest raster one. The known theme
+// layouts are checked directly, it runs for every listed web app.
+pub fn named_icon_path(name: &str) -> Option<PathBuf> {
+    if name.is_empty() || name.contains('/') {
+        return None;
+    }
+
+    let mut themes = Vec::new();
+
+    for root in icon_search_roots(is_sandboxed()) {
+        let Ok(entries) = fs::read_dir(&root) else {
+            continue;
+        };
+
+        themes.extend(
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir()),
+        );
+    }
+
+    let (svg, png) = (&format!("{}.svg", name), &format!("{}.png", name));
+    let pixmaps = PathBuf::from("/usr/share/pixmaps");
+
+    let scalable = themes
+        .iter()
+        .flat_map(|theme| {
+            [
+                theme.join("scalable/apps").join(svg),
+                theme.join("apps/scalable").join(svg),
+            ]
+        })
+        .chain([pixmaps.join(svg)]);
+
+    let raster = NAMED_ICON_SIZES.iter().flat_map(|size| {
+        themes.iter().flat_map(move |theme| {
+            [
+                theme.join(format!("{}x{}/apps", size, size)).join(png),
+                theme.join(format!("apps/{}", size)).join(png),
+                theme.join(format!("apps/{}", size)).join(svg),
+            ]
+        })
+    });
+
+    scalable
+        .chain(raster)
+        .chain([pixmaps.join(png)])
+        .find(|path| path.is_file())
+}
+
+pub fn is_sandboxed() -> bool {
+    PathBuf::from("/.flatpak-info").exists()
+}
+
+// Directories searched for local icons. Inside flatpak `/usr/share/icons`
+// belongs to the runtime, so icons of the host are read from `/run/host`.
+// Icons exported by installed flatpak apps are searched too, so a web app
+// can reuse the icon of the matching app.
+pub fn icon_search_roots(sandboxed: bool) -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+
+    match icons_location() {
+        Ok(location) => roots.push(location),
+        Err(err) => tracing::warn!("skipping user icons: {}", err),
+    }
+
+    if sandboxed {
+        roots.push("/run/host/usr/share/icons".into());
+        roots.push("/run/host/usr/local/share/icons".into());
+    }
+
+    roots.push(system_icons());
+
+    if let Some(home) = home_dir() {
+        roots.push(home.join(".local/share/flatpak/exports/share/icons"));
+    }
+    roots.push("/var/lib/flatpak/exports/share/icons".into());
+
+    roots
+}
+
+pub fn fd_entries() -> Vec<DesktopEntry> {
+    let mut paths = Vec::new();
+
+    // this is workaround for flatpak sandbox
+    if is_sandboxed() {
+        if let Some(home) = home_dir() {
+            paths.push(home.join(".local/share/flatpak/exports/share/applications"));
+        }
+        paths.push("/var/lib/flatpak/exports/share/applications".into());
+        paths.push("/run/host/usr/share/applications".into());
+        paths.push("/run/host/usr/local/share/applications".into());
+    };
+
+    let brew_paths = brew_prefixes()
+        .into_iter()
+        .map(|prefix| prefix.join("share/applications"));
+
+    default_paths().chain(brew_paths).for_each(|path| {
+        if !paths.contains(&path) {
+            paths.push(path)
+        }
+    });
+
+    Iter::new(paths.into_iter())
+        .entries(Some(&LOCALES))
+        .collect::<Vec<DesktopEntry>>()
+}
+
+// Hosts which can't be reached by external favicon services,
+// like mDNS names, single label intranet hosts or raw IP addresses.
+pub fn is_local_host(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Domain(domain)) => domain.ends_with(".local") || !domain.contains('.'),
+        Some(Host::Ipv4(_)) | Some(Host::Ipv6(_)) => true,
+        None => false,
+    }
+}
+
+// IP literals can't be split into labels, so the whole address is turned
+// into a name which is safe to use as a filename, e.g. `2001:db8::1` -> `2001-db8-1`.
+fn sanitize_ip_host(host: &str) -> String {
+    host.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+
+pub fn get_icon_name_from_url(url: &str) -> String {
+    // a local page is named by its file, or its directory for an `index.html`
+    if let Some(path) = local_file_path(url) {
+        let name = match path.file_stem() {
+            Some(stem) if stem != "index" => Some(stem),
+            _ => path.parent().and_then(|parent| parent.file_name()),
+        };
+
+        return name
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+    }
+
+    match Url::parse(url) {
+        Ok(url) => match url.host() {
+            Some(Host::Domain(_)) => {
+                let domain = normalized_host(&url).unwrap_or_default();
+
+                // main label of the registrable domain, `example` for both
+                // `www.example.com` and `www.example.co.uk`
+                if let Some(name) =
+                    psl::domain_str(&domain).and_then(|registrable| registrable.split('.').next())
+                {
+                    return name.to_string();
+                }
+
+                let parts: Vec<&str> = domain.split('.').filter(|p| !p.is_empty()).collect();
+
+                match parts.as_slice() {
+                    [] => String::new(),
+                    [name] => name.to_string(),
+                    [name, .., "local"] => name.to_string(),
+                    [.., name, _] => name.to_string(),
+                }
+            }
+            Some(Host::Ipv4(ip)) => sanitize_ip_host(&ip.to_string()),
+            Some(Host::Ipv6(ip)) => sanitize_ip_host(&ip.to_string()),
+            None => String::new(),
+        },
+        Err(_) => String::new(),
+    }
+}
+
+// Default name for a web app created straight from an url, "Github" for https://github.com.
+pub fn default_name_from_url(url: &str) -> String {
+    let name = get_icon_name_from_url(url);
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => name,
+    }
+}
+
+// Missing or unreadable roots are common in sandboxes and on immutable systems,
+// so they are reported only the first time they are skipped.
+fn check_icons_root(path: &Path) -> std::io::Result<()> {
+    static REPORTED: Lazy<Mutex<HashSet<PathBuf>>> = Lazy::new(Default::default);
+
+    match fs::read_dir(path) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            let first_time = REPORTED
+                .lock()
+                .map(|mut reported| reported.insert(path.to_path_buf()))
+                .unwrap_or_default();
+
+            if first_time {
+                tracing::warn!("skipping icons directory {:?}: {}", path, err);
+            }
+
+            Err(err)
+        }
+    }
+}
+
+// Stops an icon search whose results aren't wanted anymore, shared between
+// the search and whoever started it.
+#[derive(Debug, Clone, Default)]
+pub struct SearchCancel(Arc<AtomicBool>);
+
+impl SearchCancel {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    // resolves once cancelled, for racing against downloads
+    async fn cancelled(&self) {
+        while !self.is_cancelled() {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+    }
+}
+
+// Monochrome `*-symbolic.svg` icon of a theme, which follows the panel's colors.
+pub fn is_symbolic_icon(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new("svg"))
+        && path
+            .file_stem()
+            .is_some_and(|stem| stem.to_string_lossy().ends_with("-symbolic"))
+}
+
+// Icons under `path` whose file name contains `icon_name`. An empty name
+// matches every icon, meant only for the app's own icons directory.
+pub async fn find_icon(
+    path: PathBuf,
+    icon_name: String,
+    max_depth: usize,
+    cancel: &SearchCancel,
+) -> Vec<String> {
+    let mut icons: Vec<String> = Vec::new();
+
+    if check_icons_root(&path).is_err() {
+        return icons;
+    }
+
+    // files are opened by their `Path`, so names which aren't valid UTF-8
+    // are still found, only the returned string is converted lossily
+    for entry in WalkDir::new(&path)
+        .max_depth(max_depth)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        if cancel.is_cancelled() {
+            tracing::debug!("icon search in {:?} cancelled", path);
+            break;
+        }
+
+        if !entry.file_name().to_string_lossy().contains(&icon_name) {
+            continue;
+        }
+
+        let path = entry.path();
+        let display = path.to_string_lossy().to_string();
+
+        if icons.contains(&display) {
+            continue;
+        }
+
+        // symbolic icons are drawn small, yet scale as they are vectors
+        if let Some((width, height)) = icon_dimensions(path).await {
+            if is_symbolic_icon(path) || (width >= ICON_SIZE as f32 && height >= ICON_SIZE as f32) {
+                icons.push(display)
+            }
+        }
+    }
+
+    icons
+}
+
+// Size of the file when it was decoded and its dimensions, none when it couldn't be.
+type DimensionsEntry = (Option<std::time::SystemTime>, u64, Option<(f32, f32)>);
+
+// Dimensions of an icon file. They are kept for the app lifetime with the file's
+// modification time and size, so repeated searches decode only changed files.
+pub async fn icon_dimensions(path: &Path) -> Option<(f32, f32)> {
+    static DIMENSIONS: Lazy<Mutex<HashMap<PathBuf, DimensionsEntry>>> = Lazy::new(Default::default);
+
+    let metadata = fs::metadata(path).ok()?;
+    let (modified, len) = (metadata.modified().ok(), metadata.len());
+
+    if let Some((cached_modified, cached_len, dimensions)) = DIMENSIONS
+        .lock()
+        .ok()
+        .and_then(|cache| cache.get(path).cloned())
+    {
+        if cached_modified == modified && cached_len == len {
+            return dimensions;
+        }
+    }
+
+    #[cfg(test)]
+    crate::testing::count_decode();
+
+    let dimensions = if path.extension() == Some(OsStr::new("svg")) {
+        tokio::fs::read(path).await.ok().and_then(|buffer| {
+            let parsed = usvg::Tree::from_data(&buffer, &usvg::Options::default()).ok()?;
+            Some((parsed.size().width(), parsed.size().height()))
+        })
+    } else {
+        ImageReader::open(path)
+            .ok()
+            .and_then(|image| image.decode().ok())
+            .map(|img| (img.width() as f32, img.height() as f32))
+    };
+
+    if let Ok(mut cache) = DIMENSIONS.lock() {
+        cache.insert(path.to_path_buf(), (modified, len, dimensions));
+    }
+
+    dimensions
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IconSearchOptions {
+    // disables every outgoing request made while looking for icons
+    pub offline: bool,
+    // skips cached favicons and downloads them again
+    pub force_refresh: bool,
+    pub max_depth: usize,
+    // see `favicon::service_favicon`
+    pub service_fallback: bool,
+}
+
+impl IconSearchOptions {
+    pub fn from_config() -> Self {
+        let config = AppConfig::config();
+
+        Self {
+            offline: ARGS.offline || config.offline_mode,
+            force_refresh: ARGS.refresh,
+            max_depth: config.icon_search_depth,
+            service_fallback: config.favicon_service_fallback,
+        }
+    }
+
+    pub fn refresh(mut self) -> Self {
+        self.force_refresh = true;
+        self
+    }
+}
+
+// Where a found icon comes from, so the icon picker can group them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconOrigin {
+    // the site itself or a favicon service
+    Favicon,
+    // the user's icons, including icons imported into this app
+    UserIconsDir,
+    SystemIconsDir,
+    // `*-symbolic` icons of the themes, used by their name
+    Symbolic,
+}
+
+impl IconOrigin {
+    pub fn name(&self) -> String {
+        match self {
+            IconOrigin::Favicon => fl!("icons-from-website"),
+            IconOrigin::UserIconsDir => fl!("icons-from-user"),
+            IconOrigin::SystemIconsDir => fl!("icons-from-system"),
+            IconOrigin::Symbolic => fl!("icons-symbolic"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundIcon {
+    pub path: String,
+    pub origin: IconOrigin,
+}
+
+impl FoundIcon {
+    // Picked symbolic icons are referenced by their theme name, so they
+    // follow the theme instead of pointing at one file.
+    pub fn theme_name(&self) -> Option<String> {
+        if self.origin != IconOrigin::Symbolic {
+            return None;
+        }
+
+        Path::new(&self.path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+    }
+}
+
+// Icons found by `find_icons_reporting`, with the problems which may explain
+// a short or empty list.
+#[derive(Debug, Clone, Default)]
+pub struct IconSearchResult {
+    pub icons: Vec<FoundIcon>,
+    pub warnings: Vec<String>,
+    // stopped before the end, so the icons are incomplete
+    pub cancelled: bool,
+}
+
+impl IconSearchResult {
+    fn extend(&mut self, paths: Vec<String>, origin: IconOrigin) {
+        self.icons
+            .extend(paths.into_iter().map(|path| FoundIcon { path, origin }));
+    }
+
+    pub fn paths(self) -> Vec<String> {
+        self.icons.into_iter().map(|icon| icon.path).collect()
+    }
+}
+
+pub async fn find_icons_reporting(
+    icon_name: String,
+    url: String,
+    options: IconSearchOptions,
+    cancel: SearchCancel,
+) -> IconSearchResult {
+    let mut result = IconSearchResult::default();
+
+    if local_file_path(&url).is_some() {
+        tracing::debug!("local page, skipping favicon lookup for {:?}", url);
+    } else if options.offline {
+        tracing::debug!("offline, skipping favicon lookup for {:?}", url);
+        result.warnings.push(fl!("favicon-lookup-skipped"));
+    } else if url_valid(&url) {
+        let download = tokio::select! {
+            download = favicon::download_favicon(&url, options.force_refresh) => download,
+            _ = cancel.cancelled() => {
+                result.cancelled = true;
+                return result;
+            }
+        };
+
+        match download {
+            Ok(data) => {
+                tracing::debug!("found {} favicons for {}", data.len(), url);
+                result.extend(data, IconOrigin::Favicon)
+            }
+            Err(err) => {
+                tracing::warn!("favicon lookup for {} failed: {}", url, err);
+                result
+                    .warnings
+                    .push(fl!("favicon-lookup-failed", error = err.to_string()));
+            }
+        }
+    } else {
+        tracing::debug!("skipping favicon lookup for {:?}", url);
+    };
+
+    // every file name contains an empty name, it would decode all icons of the system
+    let roots = match icon_name.trim().is_empty() {
+        true => {
+            tracing::debug!("no icon name for {:?}, skipping icon themes", url);
+            Vec::new()
+        }
+        false => icon_search_roots(is_sandboxed()),
+    };
+
+    search_icon_roots(&mut result, roots, &icon_name, options.max_depth, &cancel).await;
+
+    if cancel.is_cancelled() {
+        result.cancelled = true;
+        return result;
+    }
+
+    if result.icons.is_empty()
+        && options.service_fallback
+        && !options.offline
+        && local_file_path(&url).is_none()
+    {
+        if let Some(icon) = favicon::service_favicon(&url).await {
+            tracing::debug!("using favicon service icon for {}", url);
+            result.extend(vec![icon], IconOrigin::Favicon);
+        }
+    }
+
+    result
+}
+
+// Icons named `icon_name` in the theme directories `roots`, tagged with the
+// directory they were found in.
+async fn search_icon_roots(
+    result: &mut IconSearchResult,
+    roots: Vec<PathBuf>,
+    icon_name: &str,
+    max_depth: usize,
+    cancel: &SearchCancel,
+) {
+    let user_icons = icons_location().ok();
+
+    for root in roots {
+        if cancel.is_cancelled() {
+            return;
+        }
+
+        let origin = match user_icons.as_ref() == Some(&root) {
+            true => IconOrigin::UserIconsDir,
+            false => IconOrigin::SystemIconsDir,
+        };
+
+        // roots which don't exist are expected, only unreadable ones are worth a warning
+        if let Err(err) = check_icons_root(&root) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                result.warnings.push(fl!(
+                    "icons-dir-unreadable",
+                    path = root.to_string_lossy().to_string(),
+                    error = err.to_string()
+                ));
+            }
+            continue;
+        }
+
+        let icons = find_icon(root.clone(), icon_name.to_string(), max_depth, cancel).await;
+        tracing::debug!(
+            "found {} icons matching {:?} in {:?}",
+            icons.len(),
+            icon_name,
+            root
+        );
+        let (symbolic, icons): (Vec<String>, Vec<String>) = icons
+            .into_iter()
+            .partition(|icon| is_symbolic_icon(Path::new(icon)));
+        result.extend(icons, origin);
+        result.extend(symbolic, IconOrigin::Symbolic);
+    }
+}
+
+pub async fn find_icons(icon_name: String, url: String, options: IconSearchOptions) -> Vec<String> {
+    find_icons_reporting(icon_name, url, options, SearchCancel::default())
+        .await
+        .paths()
+}
+
+fn first_frame<'a>(decoder: impl AnimationDecoder<'a>) -> Result<DynamicImage> {
+    match decoder.into_frames().next() {
+        Some(frame) => Ok(DynamicImage::ImageRgba8(frame?.into_buffer())),
+        None => bail!("animation has no frames"),
+    }
+}
+
+// Animated GIF, APNG and WebP favicons are reduced to their first frame, so icons
+// are always plain still images. The flag tells whether the source was animated.
+pub fn decode_still_image(data: &[u8]) -> Result<(DynamicImage, bool), Error> {
+    match image::guess_format(data)? {
+        image::ImageFormat::Gif => Ok((first_frame(GifDecoder::new(Cursor::new(data))?)?, true)),
+        image::ImageFormat::Png => {
+            let decoder = PngDecoder::new(Cursor::new(data))?;
+
+            if decoder.is_apng()? {
+                return Ok((first_frame(decoder.apng()?)?, true));
+            }
+
+            Ok((load_from_memory(data)?, false))
+        }
+        image::ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(Cursor::new(data))?;
+
+            if decoder.has_animation() {
+                return Ok((first_frame(decoder)?, true));
+            }
+
+            Ok((load_from_memory(data)?, false))
+        }
+        _ => Ok((load_from_memory(data)?, false)),
+    }
+}
+
+fn encode_png(image: &DynamicImage) -> Result<Vec<u8>, Error> {
+    let mut buffer = Vec::new();
+    image.write_to(&mut Cursor::new(&mut buffer), image::ImageFormat::Png)?;
+
+    Ok(buffer)
+}
+
+// Image data which can be displayed right away, animations are replaced by their still frame.
+fn raster_handle(data: Bytes) -> Option<(iced_core::image::Handle, Option<SourceFormat>)> {
+    let format = image::guess_format(&data)
+        .ok()
+        .and_then(SourceFormat::from_image);
+    let (image, animated) = decode_still_image(&data).ok()?;
+
+    if image.width() < ICON_SIZE || image.height() < ICON_SIZE {
+        return None;
+    }
+
+    let handle = if animated {
+        iced_core::image::Handle::from_bytes(encode_png(&image).ok()?)
+    } else {
+        iced_core::image::Handle::from_bytes(data)
+    };
+
+    Some((handle, format))
+}
+
+pub fn convert_raster_to_svg_format(img_slice: Bytes, icon_name: &str) -> Result<String, Error> {
+    let save_path = icon_save_path(icon_name, "svg")?;
+
+    let (data, _) = decode_still_image(&img_slice)?;
+    let (width, height) = data.dimensions();
+    let encoded_img = BASE64_STANDARD.encode(encode_png(&data)?);
+
+    // the image is centered on a square canvas, and the viewBox lets
+    // renderers scale it to any panel size
+    let side = width.max(height);
+    let image_element = Image::new()
+        .set("x", (side - width) / 2)
+        .set("y", (side - height) / 2)
+        .set("width", width)
+        .set("height", height)
+        .set("href", format!("data:image/png;base64,{}", encoded_img));
+
+    let document = Document::new()
+        .set("width", side)
+        .set("height", side)
+        .set("viewBox", (0, 0, side, side))
+        .add(image_element);
+
+    svg::save(&save_path, &document)?;
+
+    Ok(save_path)
+}
+
+// Decoding and encoding a large download takes a while, so async callers run the
+// conversion on the blocking pool. Outside of a tokio runtime it runs in place.
+pub async fn convert_raster_to_svg_format_async(
+    img_slice: Bytes,
+    icon_name: &str,
+) -> Result<String, Error> {
+    if tokio::runtime::Handle::try_current().is_err() {
+        return convert_raster_to_svg_format(img_slice, icon_name);
+    }
+
+    let icon_name = icon_name.to_string();
+
+    tokio::task::spawn_blocking(move || convert_raster_to_svg_format(img_slice, &icon_name))
+        .await
+        .map_err(|err| anyhow!("icon conversion failed: {}", err))?
+}
+
+// Raster image embedded by `convert_raster_to_svg_format`, if the SVG is such a wrapper.
+pub fn extract_wrapped_raster(svg: &str) -> Option<Bytes> {
+    let start = svg.find("data:image/")?;
+    let data = &svg[start..];
+    let encoded_start = data.find(";base64,")? + ";base64,".len();
+    let encoded_end = data[encoded_start..].find(['"', '\''])? + encoded_start;
+
+    BASE64_STANDARD
+        .decode(&data[encoded_start..encoded_end])
+        .ok()
+        .map(Bytes::from)
+}
+
+// Re-installs icons saved by older versions as SVG wrapped rasters in the given
+// native format. Returns the old paths together with their new `Icon=` values.
+pub fn migrate_wrapped_icons(format: IconFormat) -> Result<Vec<(String, String)>> {
+    let format = match format {
+        IconFormat::Svg => IconFormat::Png,
+        format => format,
+    };
+    let mut migrated = Vec::new();
+
+    for entry in fs::read_dir(qwa_icons_location()?)?.filter_map(|e| e.ok()) {
+        let path = entry.path();
+
+        if path.extension() != Some(OsStr::new("svg")) {
+            continue;
+        }
+
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+
+        let (Some(raster), Some(icon_name)) = (
+            extract_wrapped_raster(&content),
+            path.file_stem().and_then(|stem| stem.to_str()),
+        ) else {
+            continue;
+        };
+
+        match install_icon(raster, icon_name, format) {
+            Ok(icon) => {
+                if let Err(err) = fs::remove_file(&path) {
+                    tracing::warn!("can't remove migrated icon {:?}: {}", path, err);
+                }
+                migrated.push((path.to_string_lossy().to_string(), icon));
+            }
+            Err(err) => tracing::warn!("failed to migrate icon {:?}: {}", path, err),
+        }
+    }
+
+    tracing::info!("migrated {} wrapped icons", migrated.len());
+
+    Ok(migrated)
+}
+
+// Whether any pixel of the image is at least partly transparent.
+fn has_transparency(image: &DynamicImage) -> bool {
+    image.color().has_alpha() && image.to_rgba8().pixels().any(|pixel| pixel[3] < u8::MAX)
+}
+
+// Opaque icons, mostly photos and screenshots, are a lot smaller as lossless
+// WebP. Icons with transparency stay PNG, which every desktop displays.
+fn save_png_icon(img_slice: &Bytes, icon_name: &str) -> Result<String, Error> {
+    let (image, _) = decode_still_image(img_slice)?;
+
+    let (extension, format) = if AppConfig::config().compact_icons && !has_transparency(&image) {
+        ("webp", image::ImageFormat::WebP)
+    } else {
+        ("png", image::ImageFormat::Png)
+    };
+
+    let save_path = icon_save_path(icon_name, extension)?;
+    image.save_with_format(&save_path, format)?;
+
+    Ok(save_path)
+}
+
+// Name of the web app's icon in the shared hicolor theme. It carries the app id,
+// so it can't replace the icon of an installed application.
+pub fn hicolor_icon_name(icon_name: &str) -> String {
+    webapp_id(&sanitize_icon_name(icon_name))
+}
+
+fn save_hicolor_png_set(img_slice: &Bytes, icon_name: &str) -> Result<String, Error> {
+    let hicolor = hicolor_icons_location()?;
+    let hicolor_name = hicolor_icon_name(icon_name);
+    let (image, _) = decode_still_image(img_slice)?;
+
+    for size in HICOLOR_SIZES {
+        let dir = hicolor.join(format!("{}x{}/apps", size, size));
+        create_dir_all(&dir)?;
+
+        image
+            .resize(size, size, FilterType::Lanczos3)
+            .save_with_format(
+                dir.join(format!("{}.png", hicolor_name)),
+                image::ImageFormat::Png,
+            )?;
+    }
+
+    Ok(hicolor_name)
+}
+
+// `Icon=` value of an icon installed as `icon_name`. Icons in the hicolor theme are
+// referenced by name so the theme picks the right size, others by absolute path.
+pub fn desktop_icon_value(icon_name: &str, format: IconFormat) -> Result<String, Error> {
+    match format {
+        IconFormat::Svg => icon_save_path(icon_name, "svg"),
+        IconFormat::Png => icon_save_path(icon_name, "png"),
+        IconFormat::HicolorPngSet => Ok(hicolor_icon_name(icon_name)),
+    }
+}
+
+// Installs raster image data in the chosen format and returns the `Icon=` value.
+pub fn install_icon(
+    img_slice: Bytes,
+    icon_name: &str,
+    format: IconFormat,
+) -> Result<String, Error> {
+    match format {
+        IconFormat::Svg => convert_raster_to_svg_format(img_slice, icon_name)?,
+        // the extension depends on the image, so the saved path is the value
+        IconFormat::Png => return save_png_icon(&img_slice, icon_name),
+        IconFormat::HicolorPngSet => save_hicolor_png_set(&img_slice, icon_name)?,
+    };
+
+    desktop_icon_value(icon_name, format)
+}
+
+// Renders the SVG centered on a transparent `size`x`size` canvas, keeping its aspect ratio.
+pub fn rasterize_svg(data: &[u8], size: u32) -> Result<Vec<u8>> {
+    let tree = resvg::usvg::Tree::from_data(data, &resvg::usvg::Options::default())?;
+
+    let Some(mut pixmap) = resvg::tiny_skia::Pixmap::new(size, size) else {
+        bail!("invalid icon size {}", size);
+    };
+
+    let svg_size = tree.size();
+    let scale = size as f32 / svg_size.width().max(svg_size.height());
+    let transform = resvg::tiny_skia::Transform::from_scale(scale, scale).post_translate(
+        (size as f32 - svg_size.width() * scale) / 2.0,
+        (size as f32 - svg_size.height() * scale) / 2.0,
+    );
+
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    Ok(pixmap.encode_png()?)
+}
+
+fn install_svg_icon(data: &[u8], icon_name: &str, format: IconFormat) -> Result<String, Error> {
+    match format {
+        IconFormat::HicolorPngSet => {
+            let hicolor = hicolor_icons_location()?;
+            let hicolor_name = hicolor_icon_name(icon_name);
+            let dir = hicolor.join("scalable/apps");
+            create_dir_all(&dir)?;
+            fs::write(dir.join(format!("{}.svg", hicolor_name)), data)?;
+
+            // not every launcher picks scalable icons, so sized ones are installed too
+            for size in HICOLOR_SIZES {
+                let dir = hicolor.join(format!("{}x{}/apps", size, size));
+                create_dir_all(&dir)?;
+                fs::write(
+                    dir.join(format!("{}.png", hicolor_name)),
+                    rasterize_svg(data, size)?,
+                )?;
+            }
+
+            Ok(hicolor_name)
+        }
+        IconFormat::Png => {
+            let save_path = icon_save_path(icon_name, "png")?;
+            fs::write(&save_path, rasterize_svg(data, 256)?)?;
+
+            Ok(save_path)
+        }
+        IconFormat::Svg => {
+            let save_path = icon_save_path(icon_name, "svg")?;
+            fs::write(&save_path, data)?;
+
+            Ok(save_path)
+        }
+    }
+}
+
+// SVG has no magic bytes, downloads and files without the `.svg` extension
+// are recognized by their start.
+pub fn looks_like_svg(data: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(&data[..data.len().min(1024)]);
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+
+    (head.starts_with("<?xml") || head.starts_with("<svg") || head.starts_with("<!DOCTYPE svg"))
+        && head.contains("<svg")
+}
+
+fn icon_save_path(icon_name: &str, extension: &str) -> Result<String, Error> {
+    Ok(qwa_icons_location()?
+        .join(format!("{}.{}", sanitize_icon_name(icon_name), extension))
+        .to_string_lossy()
+        .to_string())
+}
+
+// Short FNV-1a hash of the icon data, appended to hashed icon names.
+fn content_hash(data: &[u8]) -> String {
+    let hash = data.iter().fold(0x811c9dc5_u32, |hash, byte| {
+        (hash ^ *byte as u32).wrapping_mul(0x01000193)
+    });
+
+    format!("{:08x}", hash)
+}
+
+// File stem safe for icon files and `Icon=` lookups, also used for codenames.
+// Spaces are dropped like before, any other character outside of ASCII letters,
+// digits, `-` and `_` is replaced, and a hash of the original name is appended
+// then, so names differing only in such characters don't collide.
+pub fn sanitize_icon_name(name: &str) -> String {
+    let mut lossy = false;
+    let stem: String = name
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                lossy = true;
+                '_'
+            }
+        })
+        .collect();
+
+    if stem.trim_matches('_').is_empty() {
+        return format!("icon-{}", content_hash(name.as_bytes()));
+    }
+
+    if lossy {
+        return format!("{}-{}", stem, content_hash(name.as_bytes()));
+    }
+
+    stem
+}
+
+// Whether a file stem is `icon_name` itself or `icon_name` with a content hash suffix.
+fn is_icon_variant(stem: &str, icon_name: &str) -> bool {
+    match stem.strip_prefix(icon_name) {
+        Some("") => true,
+        Some(rest) => {
+            rest.len() == 9
+                && rest.starts_with('-')
+                && rest[1..].chars().all(|c| c.is_ascii_hexdigit())
+        }
+        None => false,
+    }
+}
+
+// Whether `icon` is the `Icon=` value of an icon already installed for `icon_name`,
+// hashed or not.
+pub fn is_installed_icon(icon: &str, icon_name: &str) -> bool {
+    let icon_name = sanitize_icon_name(icon_name);
+    let path = Path::new(icon);
+
+    if !icon.contains('/') {
+        return is_icon_variant(icon, &webapp_id(&icon_name));
+    }
+
+    let in_icons_dir = match (path.parent(), qwa_icons_location()) {
+        (Some(parent), Ok(location)) => parent == location,
+        _ => false,
+    };
+
+    in_icons_dir
+        && path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| is_icon_variant(stem, &icon_name))
+}
+
+// Removes every installed icon of the sanitized `icon_name`, including hashed
+// variants from earlier replacements, except the files in `keep`: the newly
+// installed icon and the file it was installed from. In the shared hicolor
+// theme only the files named after the web app id are ours.
+fn remove_icon_variants(icon_name: &str, keep: &[PathBuf]) {
+    let hicolor_name = webapp_id(icon_name);
+    let mut dirs = Vec::new();
+
+    if let Ok(qwa_icons) = qwa_icons_location() {
+        dirs.push((qwa_icons, icon_name));
+    }
+
+    if let Ok(hicolor) = hicolor_icons_location() {
+        dirs.push((hicolor.join("scalable/apps"), &hicolor_name));
+        dirs.extend(HICOLOR_SIZES.iter().map(|size| {
+            (
+                hicolor.join(format!("{}x{}/apps", size, size)),
+                hicolor_name.as_str(),
+            )
+        }));
+    }
+
+    for (dir, icon_name) in dirs {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let variant = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .map(|stem| stem.strip_suffix(".orig").unwrap_or(stem))
+                .is_some_and(|stem| is_icon_variant(stem, icon_name));
+
+            if variant && !keep.contains(&path) {
+                if let Err(err) = fs::remove_file(&path) {
+                    tracing::warn!("can't remove previous icon {:?}: {}", path, err);
+                }
+            }
+        }
+    }
+}
+
+// Detected format of icon data, `is_svg` tells apart SVG which has no magic bytes.
+fn detect_source_format(data: &[u8], is_svg: bool) -> Option<SourceFormat> {
+    if is_svg {
+        return Some(SourceFormat::Svg);
+    }
+
+    image::guess_format(data)
+        .ok()
+        .and_then(SourceFormat::from_image)
+}
+
+// Keeps the icon as it was downloaded or picked as `<name>.orig.<ext>`,
+// so it can be installed again later without downloading it.
+fn save_original_icon(data: &[u8], icon_name: &str, is_svg: bool) -> Result<Option<PathBuf>> {
+    let Some(format) = detect_source_format(data, is_svg) else {
+        tracing::debug!(
+            "unknown format of the original {} icon, not kept",
+            icon_name
+        );
+        return Ok(None);
+    };
+
+    let path = qwa_icons_location()?.join(format!("{}.orig.{}", icon_name, format.extension()));
+    fs::write(&path, data)?;
+
+    Ok(Some(path))
+}
+
+// Files written when installing `icon_name`, whose `Icon=` value is `value`.
+fn installed_icon_files(
+    value: &str,
+    icon_name: &str,
+    format: IconFormat,
+    svg: bool,
+) -> Vec<PathBuf> {
+    let IconFormat::HicolorPngSet = format else {
+        return vec![PathBuf::from(value)];
+    };
+
+    let Ok(hicolor) = hicolor_icons_location() else {
+        return Vec::new();
+    };
+    let icon_name = hicolor_icon_name(icon_name);
+
+    let mut files: Vec<PathBuf> = HICOLOR_SIZES
+        .iter()
+        .map(|size| hicolor.join(format!("{}x{}/apps/{}.png", size, size, icon_name)))
+        .collect();
+
+    if svg {
+        files.push(hicolor.join(format!("scalable/apps/{}.svg", icon_name)));
+    }
+
+    files
+}
+
+// Menu folder holding every web app, for desktops following the freedesktop
+// menu specification. The web apps join it through `MENU_CATEGORY`.
+pub fn write_menu_folder(name: &str) -> Result<()> {
+    let paths = DataPaths::current();
+    let directories = paths.desktop_directories()?;
+    let menus = paths.merged_menus()?;
+
+    create_dir_all(&directories)?;
+    create_dir_all(&menus)?;
+
+    fs::write(
+        directories.join(MENU_DIRECTORY_FILE),
+        format!(
+            "[Desktop Entry]\nType=Directory\nName={}\nIcon={}\n",
+            escape_desktop_value(name),
+            APP_ID
+        ),
+    )?;
+
+    fs::write(
+        menus.join(MENU_FILE),
+        format!(
+            r#"<!DOCTYPE Menu PUBLIC "-//freedesktop//DTD Menu 1.0//EN"
+ "http://www.freedesktop.org/standards/menu-spec/1.0/menu.dtd">
+<Menu>
+  <Name>Applications</Name>
+  <Menu>
+    <Name>{}</Name>
+    <Directory>{}</Directory>
+    <Include>
+      <Category>{}</Category>
+    </Include>
+  </Menu>
+</Menu>
+"#,
+            APP_ID, MENU_DIRECTORY_FILE, MENU_CATEGORY
+        ),
+    )?;
+
+    Ok(())
+}
+
+pub fn remove_menu_folder() {
+    let paths = DataPaths::current();
+
+    let files = [
+        paths
+            .desktop_directories()
+            .map(|dir| dir.join(MENU_DIRECTORY_FILE)),
+        paths.merged_menus().map(|dir| dir.join(MENU_FILE)),
+    ];
+
+    for file in files.into_iter().flatten().filter(|file| file.exists()) {
+        if let Err(err) = fs::remove_file(&file) {
+            tracing::warn!("can't remove {:?}: {}", file, err);
+        }
+    }
+}
+
+// Kept original of the web app's icon, see `save_original_icon`.
+pub fn original_icon(codename: &str) -> Option<PathBuf> {
+    let icon_name = sanitize_icon_name(codename);
+
+    fs::read_dir(qwa_icons_location().ok()?)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| stem == format!("{}.orig", icon_name))
+        })
+}
+
+// Removes kept originals of the web app's icon, see `save_original_icon`.
+pub fn remove_original_icons(codename: &str) {
+    let icon_name = sanitize_icon_name(codename);
+    let Ok(entries) = qwa_icons_location().and_then(|dir| Ok(fs::read_dir(dir)?)) else {
+        return;
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let original = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.strip_suffix(".orig"))
+            .is_some_and(|stem| is_icon_variant(stem, &icon_name));
+
+        if original {
+            if let Err(err) = fs::remove_file(&path) {
+                tracing::warn!("can't remove original icon {:?}: {}", path, err);
+            }
+        }
+    }
+}
+
+pub async fn move_icon(path: &str, output_name: &str, format: IconFormat) -> Result<String, Error> {
+    DataPaths::current().check_writable()?;
+    ensure_icons_writable()?;
+
+    let base_name = sanitize_icon_name(output_name);
+
+    let (content, svg_type): (Bytes, bool) = if url_valid(path) {
+        let response = icon_client().get(path).send().await?;
+
+        if !response.status().is_success() {
+            return Err(Error::Http {
+                url: path.to_string(),
+                status: response.status(),
+            });
+        }
+
+        let svg_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("image/svg"));
+
+        (
+            read_body_limited(response, max_download_size()).await?,
+            svg_type,
+        )
+    } else {
+        match fs::read(path) {
+            Ok(data) => (Bytes::from(data), false),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Err(Error::IconNotFound(PathBuf::from(path)));
+            }
+            Err(err) => return Err(err.into()),
+        }
+    };
+
+    // SVG favicons are installed as they are, the raster decoders can't read them
+    let svg = svg_type || is_svg(path) || looks_like_svg(&content);
+
+    let icon_name = if AppConfig::config().hashed_icon_names {
+        format!("{}-{}", base_name, content_hash(&content))
+    } else {
+        base_name.clone()
+    };
+
+    let installed = if svg {
+        install_svg_icon(&content, &icon_name, format)?
+    } else if format == IconFormat::Svg {
+        convert_raster_to_svg_format_async(content.clone(), &icon_name).await?;
+        desktop_icon_value(&icon_name, format)?
+    } else {
+        install_icon(content.clone(), &icon_name, format)?
+    };
+
+    // previous icons are removed only once the new one is in place,
+    // a failed install leaves the web app with its old icon
+    let mut keep = installed_icon_files(&installed, &icon_name, format, svg);
+    keep.push(PathBuf::from(path));
+
+    if AppConfig::config().keep_original_icons {
+        match save_original_icon(&content, &base_name, svg) {
+            Ok(original) => keep.extend(original),
+            Err(err) => tracing::warn!("can't keep the original icon of {}: {}", base_name, err),
+        }
+    }
+
+    remove_icon_variants(&base_name, &keep);
+
+    Ok(installed)
+}
+
+pub async fn image_handle(path: String) -> Option<Icon> {
+    if url_valid(&path) {
+        match icon_client().get(&path).send().await {
+            Ok(response) if !response.status().is_success() => {
+                tracing::warn!("downloading icon {} failed: {}", path, response.status());
+            }
+            Ok(response) => match read_body_limited(response, max_download_size()).await {
+                Err(err) => tracing::warn!("downloading icon {} failed: {}", path, err),
+                Ok(bytes) => {
+                    let options = usvg::Options::default();
+                    if let Ok(parsed) = usvg::Tree::from_data(&bytes, &options) {
+                        let size = parsed.size();
+                        if size.width() >= 96.0 && size.height() >= 96.0 {
+                            let handle = widget::svg::Handle::from_memory(bytes.to_vec());
+                            return Some(Icon::new(
+                                IconType::Svg(handle),
+                                path,
+                                true,
+                                Some(SourceFormat::Svg),
+                            ));
+                        }
+                    }
+                    if let Some((handle, format)) = raster_handle(bytes) {
+                        return Some(Icon::new(IconType::Raster(handle), path, true, format));
+                    }
+                }
+            },
+            Err(err) => tracing::warn!("downloading icon {} failed: {}", path, err),
+        }
+    };
+
+    let Ok(result_path) = PathBuf::from_str(&path);
+
+    if result_path.is_file() {
+        if is_svg(&path) {
+            let handle = widget::svg::Handle::from_path(&result_path);
+
+            return Some(Icon::new(
+                IconType::Svg(handle),
+                path,
+                false,
+                Some(SourceFormat::Svg),
+            ));
+        } else {
+            let mut data: Vec<_> = Vec::new();
+
+            if let Ok(mut file) = fs::File::open(&result_path) {
+                let _ = file.read_to_end(&mut data);
+            }
+
+            if let Some((handle, format)) = raster_handle(Bytes::from(data)) {
+                return Some(Icon::new(IconType::Raster(handle), path, false, format));
+            }
+        }
+    };
+
+    None
+}
+
+// Format of the image an icon was decoded from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceFormat {
+    Png,
+    Jpeg,
+    Gif,
+    WebP,
+    Svg,
+    Ico,
+}
+
+impl SourceFormat {
+    pub fn from_image(format: image::ImageFormat) -> Option<Self> {
+        match format {
+            image::ImageFormat::Png => Some(SourceFormat::Png),
+            image::ImageFormat::Jpeg => Some(SourceFormat::Jpeg),
+            image::ImageFormat::Gif => Some(SourceFormat::Gif),
+            image::ImageFormat::WebP => Some(SourceFormat::WebP),
+            image::ImageFormat::Ico => Some(SourceFormat::Ico),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SourceFormat::Png => "png",
+            SourceFormat::Jpeg => "jpg",
+            SourceFormat::Gif => "gif",
+            SourceFormat::WebP => "webp",
+            SourceFormat::Svg => "svg",
+            SourceFormat::Ico => "ico",
+        }
+    }
+
+    pub fn is_vector(&self) -> bool {
+        matches!(self, SourceFormat::Svg)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IconType {
+    Raster(widget::image::Handle),
+    Svg(widget::svg::Handle),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Icon {
+    pub icon: IconType,
+    pub path: String,
+    pub is_favicon: bool,
+    pub format: Option<SourceFormat>,
+}
+
+impl Icon {
+    pub fn new(
+        icon: IconType,
+        path: String,
+        is_favicon: bool,
+        format: Option<SourceFormat>,
+    ) -> Self {
+        Self {
+            icon,
+            path,
+            is_favicon,
+            format,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::browser::Browser;
+    use crate::config::DEFAULT_ICON_SEARCH_DEPTH;
+    use crate::testing::{MockServer, Route};
+    use std::time::Duration;
+
+    // what `DataPaths::system()` finds without a home directory
+    fn homeless() -> DataPaths {
+        DataPaths {
+            data: None,
+            icons: None,
+            config: None,
+            ..DataPaths::with_root(PathBuf::from("/nonexistent"))
+        }
+    }
+
+    #[test]
+    fn normalized_host_drops_case_and_trailing_dot() {
+        let url = Url::parse("https://GitHub.COM./").unwrap();
+        assert_eq!(normalized_host(&url).as_deref(), Some("github.com"));
+    }
+
+    #[test]
+    fn normalized_url_ignores_case_default_ports_and_query() {
+        assert_eq!(
+            normalized_url(" HTTPS://Example.COM.:443/path/?q=1#top ").as_deref(),
+            Some("https://example.com/path")
+        );
+        assert_eq!(
+            normalized_url("https://example.com/path"),
+            normalized_url("https://example.com/path/")
+        );
+        assert_eq!(
+            normalized_url("http://example.com:8080/").as_deref(),
+            Some("http://example.com:8080")
+        );
+        assert_ne!(
+            normalized_url("http://example.com/"),
+            normalized_url("https://example.com/")
+        );
+        assert_eq!(normalized_url("example.com"), None);
+    }
+
+    #[test]
+    fn icon_name_is_the_main_label_of_the_domain() {
+        assert_eq!(
+            get_icon_name_from_url("https://www.example.com/path"),
+            "example"
+        );
+        assert_eq!(
+            get_icon_name_from_url("https://WWW.Example.CO.UK./"),
+            "example"
+        );
+        assert_eq!(
+            get_icon_name_from_url("https://shop.example.com.au"),
+            "example"
+        );
+        assert_eq!(get_icon_name_from_url("https://mail.google.com"), "google");
+        assert_eq!(get_icon_name_from_url("https://user.github.io/app"), "user");
+        assert_eq!(get_icon_name_from_url("http://nas.local:5000"), "nas");
+        assert_eq!(get_icon_name_from_url("http://localhost:8080"), "localhost");
+    }
+
+    #[test]
+    fn local_network_urls_are_supported() {
+        for (url, name) in [
+            ("http://nas.local:5000/", "nas"),
+            ("http://192.168.1.5:8123", "192-168-1-5"),
+            ("http://[fe80::1]:8080/", "fe80-1"),
+        ] {
+            assert!(url_valid(url), "{}", url);
+            assert!(is_local_host(&Url::parse(url).unwrap()), "{}", url);
+            assert_eq!(get_icon_name_from_url(url), name);
+        }
+
+        assert_eq!(get_icon_name_from_url("http://localhost:8080"), "localhost");
+        assert!(!is_local_host(&Url::parse("https://example.com").unwrap()));
+    }
+
+    #[test]
+    fn icon_name_of_ip_hosts_and_invalid_urls() {
+        assert_eq!(
+            get_icon_name_from_url("http://192.168.1.10:8080"),
+            "192-168-1-10"
+        );
+        assert_eq!(
+            get_icon_name_from_url("http://[2001:db8::1]/"),
+            "2001-db8-1"
+        );
+        assert_eq!(get_icon_name_from_url("http://intranet/"), "intranet");
+        assert_eq!(get_icon_name_from_url("not a url"), "");
+
+        for url in [
+            "http://10.0.0.1/",
+            "https://[::1]:8443/",
+            "http://[2001:db8::ff00:42:8329]",
+        ] {
+            let name = get_icon_name_from_url(url);
+
+            assert!(!name.is_empty(), "{}", url);
+            assert_eq!(sanitize_icon_name(&name), name);
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_icon_download_is_logged_as_warning() {
+        let server = MockServer::start(vec![("/gone.png", Route::status(404))]).await;
+        let logs = crate::testing::capture_logs();
+
+        assert!(image_handle(server.url("/gone.png")).await.is_none());
+
+        let output = logs.output();
+        assert!(output.contains("WARN"), "{}", output);
+        assert!(output.contains("404"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn missing_icon_roots_are_skipped_and_reported_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("usr/share/icons");
+        let logs = crate::testing::capture_logs();
+
+        for _ in 0..2 {
+            let icons = find_icon(
+                missing.clone(),
+                "firefox".to_string(),
+                5,
+                &SearchCancel::default(),
+            )
+            .await;
+            assert!(icons.is_empty());
+        }
+
+        assert_eq!(
+            check_icons_root(&missing).unwrap_err().kind(),
+            std::io::ErrorKind::NotFound
+        );
+        assert_eq!(logs.output().matches("skipping icons directory").count(), 1);
+    }
+
+    #[test]
+    fn host_icons_are_searched_inside_flatpak() {
+        let host = PathBuf::from("/run/host/usr/share/icons");
+
+        let roots = icon_search_roots(true);
+        assert!(roots.contains(&host));
+        assert!(roots.contains(&icons_location().unwrap()));
+        assert!(roots.contains(&system_icons()));
+
+        assert!(!icon_search_roots(false).contains(&host));
+    }
+
+    #[tokio::test]
+    async fn flatpak_exported_icons_are_found() {
+        let root = crate::testing::data_root();
+        let roots = icon_search_roots(false);
+        assert!(roots.contains(&PathBuf::from("/var/lib/flatpak/exports/share/icons")));
+        if let Some(home) = home_dir() {
+            assert!(roots.contains(&home.join(".local/share/flatpak/exports/share/icons")));
+        }
+
+        let exports = root.path().join("flatpak/exports/share/icons");
+        let apps = exports.join("hicolor/128x128/apps");
+        fs::create_dir_all(&apps).unwrap();
+        fs::write(apps.join("com.discordapp.Discord.png"), png_icon(128)).unwrap();
+
+        let mut result = IconSearchResult::default();
+        search_icon_roots(
+            &mut result,
+            vec![exports],
+            "com.discordapp.Discord",
+            DEFAULT_ICON_SEARCH_DEPTH,
+            &SearchCancel::default(),
+        )
+        .await;
+
+        assert_eq!(
+            result.icons,
+            vec![FoundIcon {
+                path: apps
+                    .join("com.discordapp.Discord.png")
+                    .to_string_lossy()
+                    .to_string(),
+                origin: IconOrigin::SystemIconsDir,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_downloads_are_rejected() {
+        let _root = crate::testing::data_root();
+        let body = vec![0; 2048];
+        let server = MockServer::start(vec![
+            ("/sized", Route::ok("image/png", body.clone())),
+            ("/streamed", Route::ok("image/png", body).without_length()),
+            (
+                "/huge.png",
+                Route::ok("image/png", vec![0; max_download_size() as usize + 1]),
+            ),
+        ])
+        .await;
+
+        // rejected by the declared length, or while the body is read
+        for path in ["/sized", "/streamed"] {
+            let response = http_client().get(server.url(path)).send().await.unwrap();
+            let result = read_body_limited(response, 1024).await;
+            assert!(
+                matches!(result, Err(Error::TooLarge { limit: 1024, .. })),
+                "{}",
+                path
+            );
+
+            let response = http_client().get(server.url(path)).send().await.unwrap();
+            assert_eq!(read_body_limited(response, 2048).await.unwrap().len(), 2048);
+        }
+
+        assert!(matches!(
+            move_icon(&server.url("/huge.png"), "Example1234", IconFormat::Png).await,
+            Err(Error::TooLarge { .. })
+        ));
+        assert!(image_handle(server.url("/huge.png")).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn reachability_of_each_status() {
+        let server = MockServer::start(vec![
+            ("/", Route::ok("text/html", "<html></html>")),
+            ("/old", Route::redirect("/new")),
+            ("/new", Route::ok("text/html", "<html></html>")),
+            ("/login", Route::status(401)),
+            ("/private", Route::status(403)),
+            ("HEAD /get-only", Route::status(405)),
+            ("/get-only", Route::ok("text/html", "<html></html>")),
+            ("/broken", Route::status(500)),
+        ])
+        .await;
+
+        for (path, status) in [
+            ("/", ReachStatus::Reachable),
+            ("/old", ReachStatus::RedirectedTo(server.url("/new"))),
+            ("/login", ReachStatus::AuthRequired),
+            ("/private", ReachStatus::AuthRequired),
+            ("/missing", ReachStatus::NotFound),
+            ("/get-only", ReachStatus::Reachable),
+        ] {
+            assert_eq!(
+                check_url_reachable(&server.url(path)).await,
+                status,
+                "{}",
+                path
+            );
+        }
+        assert!(matches!(
+            check_url_reachable(&server.url("/broken")).await,
+            ReachStatus::NetworkError(_)
+        ));
+
+        // nothing listens on the port of a closed listener
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", closed.local_addr().unwrap());
+        drop(closed);
+        assert!(matches!(
+            check_url_reachable(&url).await,
+            ReachStatus::NetworkError(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn redirect_target_drives_name_and_icon() {
+        let target = MockServer::start(vec![(
+            "/home",
+            Route::ok("text/html", r#"<link rel="icon" href="/brand.svg">"#),
+        )])
+        .await;
+        // the same server under another host name
+        let final_url = target.url("/home").replace("127.0.0.1", "localhost");
+        let typed = MockServer::start(vec![
+            ("/", Route::redirect("/moved")),
+            ("/moved", Route::redirect(&final_url)),
+        ])
+        .await;
+
+        let status = check_url_reachable(&typed.url("/")).await;
+        assert_eq!(status, ReachStatus::RedirectedTo(final_url.clone()));
+
+        assert_eq!(default_name_from_url(&typed.url("/")), "127-0-0-1");
+        assert_eq!(default_name_from_url(&final_url), "Localhost");
+        assert_eq!(get_icon_name_from_url(&final_url), "localhost");
+        assert_eq!(
+            favicon::download_favicon(&final_url, true).await.unwrap(),
+            vec![final_url.replace("/home", "/brand.svg")]
+        );
+    }
+
+    #[tokio::test]
+    async fn reachability_is_not_checked_offline() {
+        let server = MockServer::start(vec![("/", Route::ok("text/html", ""))]).await;
+        crate::testing::set_config(AppConfig {
+            offline_mode: true,
+            ..AppConfig::default()
+        });
+
+        assert_eq!(
+            check_url_reachable(&server.url("/")).await,
+            ReachStatus::Skipped
+        );
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn icons_with_non_utf8_names_are_found() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("hicolor/256x256/apps");
+        fs::create_dir_all(&dir).unwrap();
+        let icon = dir.join(OsStr::from_bytes(b"example-\xff.png"));
+        fs::write(&icon, png_icon(256)).unwrap();
+
+        let icons = find_icon(
+            root.path().to_path_buf(),
+            "example".to_string(),
+            DEFAULT_ICON_SEARCH_DEPTH,
+            &SearchCancel::default(),
+        )
+        .await;
+
+        assert_eq!(icons, vec![icon.to_string_lossy().to_string()]);
+    }
+
+    #[tokio::test]
+    async fn icons_below_the_search_depth_are_not_scanned() {
+        let root = tempfile::tempdir().unwrap();
+        let shallow = root.path().join("hicolor/256x256/apps");
+        let deep = root.path().join("hicolor/256x256/apps/nested/more");
+        fs::create_dir_all(&deep).unwrap();
+        fs::write(shallow.join("example.png"), png_icon(256)).unwrap();
+        fs::write(deep.join("example.png"), png_icon(256)).unwrap();
+
+        let cancel = SearchCancel::default();
+        let find = |depth| {
+            find_icon(
+                root.path().to_path_buf(),
+                "example".to_string(),
+                depth,
+                &cancel,
+            )
+        };
+
+        assert_eq!(
+            find(DEFAULT_ICON_SEARCH_DEPTH).await,
+            vec![shallow.join("example.png").to_string_lossy().to_string()]
+        );
+        assert_eq!(find(6).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn empty_icon_name_decodes_no_theme_icons() {
+        let options = IconSearchOptions {
+            offline: true,
+            force_refresh: false,
+            max_depth: DEFAULT_ICON_SEARCH_DEPTH,
+            service_fallback: false,
+        };
+        let decodes = crate::testing::decodes();
+
+        for icon_name in ["", "  "] {
+            let result = find_icons_reporting(
+                icon_name.to_string(),
+                "not a url".to_string(),
+                options,
+                SearchCancel::default(),
+            )
+            .await;
+
+            assert!(result.icons.is_empty());
+            assert!(!result.cancelled);
+        }
+        assert_eq!(crate::testing::decodes(), decodes);
+    }
+
+    #[tokio::test]
+    async fn cancelled_search_skips_the_icon_walk() {
+        let root = tempfile::tempdir().unwrap();
+        let apps = root.path().join("hicolor/64x64/apps");
+        fs::create_dir_all(&apps).unwrap();
+        for size in [16, 32, 48] {
+            fs::write(apps.join(format!("example-{}.png", size)), png_icon(64)).unwrap();
+        }
+
+        let cancel = SearchCancel::default();
+        cancel.cancel();
+        let decodes = crate::testing::decodes();
+
+        let mut result = IconSearchResult::default();
+        search_icon_roots(
+            &mut result,
+            vec![root.path().to_path_buf()],
+            "example",
+            DEFAULT_ICON_SEARCH_DEPTH,
+            &cancel,
+        )
+        .await;
+        assert!(result.icons.is_empty());
+
+        let found = find_icon(
+            root.path().to_path_buf(),
+            "example".to_string(),
+            DEFAULT_ICON_SEARCH_DEPTH,
+            &cancel,
+        )
+        .await;
+        assert!(found.is_empty());
+        assert_eq!(crate::testing::decodes(), decodes);
+    }
+
+    #[tokio::test]
+    async fn cancelling_stops_a_pending_favicon_download() {
+        let server = MockServer::start(vec![(
+            "/",
+            Route::ok("text/html", "<html></html>").slow(Duration::from_secs(5)),
+        )])
+        .await;
+        let cancel = SearchCancel::default();
+        let options = IconSearchOptions {
+            offline: false,
+            force_refresh: true,
+            max_depth: DEFAULT_ICON_SEARCH_DEPTH,
+            service_fallback: false,
+        };
+
+        let started = std::time::Instant::now();
+        let search = find_icons_reporting(
+            "example".to_string(),
+            server.url("/"),
+            options,
+            cancel.clone(),
+        );
+        let (result, _) = tokio::join!(search, async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            cancel.cancel();
+        });
+
+        assert!(result.cancelled);
+        assert!(result.icons.is_empty());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn offline_search_makes_no_requests() {
+        let server = MockServer::start(vec![
+            ("/", Route::ok("text/html", "<html></html>")),
+            ("/favicon.ico", Route::ok("image/x-icon", "ico")),
+        ])
+        .await;
+        let options = IconSearchOptions {
+            offline: true,
+            force_refresh: true,
+            max_depth: 1,
+            service_fallback: true,
+        };
+
+        let result = find_icons_reporting(
+            String::new(),
+            server.url("/"),
+            options,
+            SearchCancel::default(),
+        )
+        .await;
+
+        assert!(result.icons.is_empty());
+        assert_eq!(result.warnings, vec![fl!("favicon-lookup-skipped")]);
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_lookups_are_reported_as_warnings() {
+        let _root = crate::testing::data_root();
+        // a file where the icons directory should be can't be read
+        let icons = icons_location().unwrap();
+        fs::write(&icons, b"").unwrap();
+        let options = IconSearchOptions {
+            offline: false,
+            force_refresh: true,
+            max_depth: 1,
+            service_fallback: false,
+        };
+
+        let result = find_icons_reporting(
+            "example".to_string(),
+            "http://127.0.0.1:1/".to_string(),
+            options,
+            SearchCancel::default(),
+        )
+        .await;
+
+        assert!(result.icons.is_empty());
+        assert_eq!(result.warnings.len(), 2, "{:?}", result.warnings);
+        assert!(result.warnings[0].starts_with("Favicon download failed"));
+        assert!(result.warnings[1].contains(&icons.to_string_lossy().to_string()));
+    }
+
+    #[tokio::test]
+    async fn favicon_service_is_asked_only_when_nothing_was_found() {
+        let _root = crate::testing::data_root();
+        let service = MockServer::start(vec![(
+            "HEAD /ip3/example.invalid.ico",
+            Route::ok("image/x-icon", ""),
+        )])
+        .await;
+        crate::testing::set_favicon_service(service.url("/ip3"));
+        let options = IconSearchOptions {
+            offline: false,
+            force_refresh: true,
+            max_depth: DEFAULT_ICON_SEARCH_DEPTH,
+            service_fallback: true,
+        };
+        let search = |options: IconSearchOptions| {
+            find_icons_reporting(
+                "example".to_string(),
+                "https://example.invalid/".to_string(),
+                options,
+                SearchCancel::default(),
+            )
+        };
+
+        let result = search(options).await;
+        assert_eq!(
+            result.paths(),
+            vec![service.url("/ip3/example.invalid.ico")]
+        );
+        assert_eq!(service.requests().len(), 1);
+
+        let disabled = search(IconSearchOptions {
+            service_fallback: false,
+            ..options
+        })
+        .await;
+        assert!(disabled.icons.is_empty());
+
+        let apps = icons_location().unwrap().join("hicolor/48x48/apps");
+        fs::create_dir_all(&apps).unwrap();
+        fs::write(apps.join("example.png"), png_icon(48)).unwrap();
+        let found = search(options).await;
+        assert_eq!(
+            found.paths(),
+            vec![apps.join("example.png").to_string_lossy().to_string()]
+        );
+
+        assert_eq!(service.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn icons_are_tagged_with_their_origin() {
+        let root = crate::testing::data_root();
+        let server = MockServer::start(vec![
+            ("/", Route::ok("text/html", "<html></html>")),
+            ("/favicon.ico", Route::ok("image/x-icon", "ico")),
+        ])
+        .await;
+        let options = IconSearchOptions {
+            offline: false,
+            force_refresh: true,
+            max_depth: DEFAULT_ICON_SEARCH_DEPTH,
+            service_fallback: false,
+        };
+
+        let favicons = find_icons_reporting(
+            String::new(),
+            server.url("/"),
+            options,
+            SearchCancel::default(),
+        )
+        .await;
+        assert_eq!(
+            favicons.icons,
+            vec![FoundIcon {
+                path: server.url("/favicon.ico"),
+                origin: IconOrigin::Favicon
+            }]
+        );
+
+        let user = icons_location().unwrap().join("hicolor/48x48/apps");
+        let system = root.path().join("system/hicolor/48x48/apps");
+        for dir in [&user, &system] {
+            fs::create_dir_all(dir).unwrap();
+            fs::write(dir.join("example.png"), png_icon(48)).unwrap();
+        }
+        fs::write(
+            system.join("example-symbolic.svg"),
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16"/>"#,
+        )
+        .unwrap();
+
+        let mut result = IconSearchResult::default();
+        let roots = vec![icons_location().unwrap(), root.path().join("system")];
+        search_icon_roots(
+            &mut result,
+            roots,
+            "example",
+            DEFAULT_ICON_SEARCH_DEPTH,
+            &SearchCancel::default(),
+        )
+        .await;
+
+        let origin = |path: PathBuf| {
+            result
+                .icons
+                .iter()
+                .find(|icon| icon.path == path.to_string_lossy())
+                .map(|icon| icon.origin)
+        };
+        assert_eq!(result.icons.len(), 3);
+        assert_eq!(
+            origin(user.join("example.png")),
+            Some(IconOrigin::UserIconsDir)
+        );
+        assert_eq!(
+            origin(system.join("example.png")),
+            Some(IconOrigin::SystemIconsDir)
+        );
+        assert_eq!(
+            origin(system.join("example-symbolic.svg")),
+            Some(IconOrigin::Symbolic)
+        );
+    }
+
+    #[tokio::test]
+    async fn small_symbolic_icons_are_found_and_picked_by_name() {
+        let _root = crate::testing::data_root();
+        let small = r#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16"/>"#;
+        let apps = icons_location().unwrap().join("hicolor/scalable/apps");
+        fs::create_dir_all(&apps).unwrap();
+        fs::write(apps.join("example-symbolic.svg"), small).unwrap();
+        fs::write(apps.join("example.svg"), small).unwrap();
+
+        assert!(is_symbolic_icon(&apps.join("example-symbolic.svg")));
+        assert!(!is_symbolic_icon(&apps.join("example.svg")));
+        assert!(!is_symbolic_icon(&apps.join("example-symbolic.png")));
+
+        let found = find_icon(
+            icons_location().unwrap(),
+            "example".into(),
+            DEFAULT_ICON_SEARCH_DEPTH,
+            &SearchCancel::default(),
+        )
+        .await;
+        let symbolic = apps.join("example-symbolic.svg");
+        assert_eq!(found, vec![symbolic.to_string_lossy().to_string()]);
+
+        let picked = FoundIcon {
+            path: found[0].clone(),
+            origin: IconOrigin::Symbolic,
+        };
+        let name = picked.theme_name().unwrap();
+        assert_eq!(name, "example-symbolic");
+        assert_eq!(named_icon_path(&name), Some(symbolic));
+
+        let themed = FoundIcon {
+            origin: IconOrigin::UserIconsDir,
+            ..picked
+        };
+        assert_eq!(themed.theme_name(), None);
+    }
+
+    #[tokio::test]
+    async fn icons_record_their_source_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = DynamicImage::new_rgba8(128, 128);
+
+        for (format, expected) in [
+            (image::ImageFormat::Png, SourceFormat::Png),
+            (image::ImageFormat::Jpeg, SourceFormat::Jpeg),
+            (image::ImageFormat::WebP, SourceFormat::WebP),
+            (image::ImageFormat::Ico, SourceFormat::Ico),
+            (image::ImageFormat::Gif, SourceFormat::Gif),
+        ] {
+            let path = dir.path().join(format!("icon.{}", expected.extension()));
+            // JPEG has no alpha channel, icons inside ICO files need one
+            let image = match format {
+                image::ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8()),
+                _ => image.clone(),
+            };
+            image.save_with_format(&path, format).unwrap();
+
+            let icon = image_handle(path.to_string_lossy().to_string())
+                .await
+                .unwrap();
+            assert_eq!(icon.format, Some(expected));
+            assert!(!icon.is_favicon);
+        }
+
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="128" height="128"/>"#;
+        let path = dir.path().join("icon.svg");
+        fs::write(&path, svg).unwrap();
+        let icon = image_handle(path.to_string_lossy().to_string())
+            .await
+            .unwrap();
+        assert_eq!(icon.format, Some(SourceFormat::Svg));
+
+        let server = MockServer::start(vec![("/icon", Route::ok("image/svg+xml", svg))]).await;
+        let icon = image_handle(server.url("/icon")).await.unwrap();
+        assert_eq!(icon.format, Some(SourceFormat::Svg));
+        assert!(icon.is_favicon);
+    }
+
+    #[test]
+    fn desktop_values_round_trip() {
+        let value = "line\nbreak\ttab\rreturn \\ backslash";
+        let escaped = escape_desktop_value(value);
+
+        assert_eq!(escaped, "line\\nbreak\\ttab\\rreturn \\\\ backslash");
+        assert_eq!(unescape_desktop_value(&escaped), value);
+        assert_eq!(unescape_desktop_value("a\\sb\\;c"), "a b\\;c");
+    }
+
+    #[test]
+    fn exec_args_are_quoted_when_needed() {
+        assert_eq!(exec_arg("https://example.com/"), "https://example.com/");
+        assert_eq!(exec_arg("with space"), "\"with space\"");
+        assert_eq!(
+            quote_exec_arg("a \"b\" $c `d` \\e"),
+            "\"a \\\"b\\\" \\$c \\`d\\` \\\\e\""
+        );
+    }
+
+    #[test]
+    fn escape_exec_doubles_percent_signs() {
+        assert_eq!(
+            escape_exec("min \"https://example.com/a%20b\""),
+            "min \"https://example.com/a%%20b\""
+        );
+        assert_eq!(escape_exec("firefox \"a\\\\b\""), "firefox \"a\\\\\\\\b\"");
+    }
+
+    #[test]
+    fn tracking_params_are_removed() {
+        let params = vec!["utm_*".to_string(), "fbclid".to_string()];
+
+        assert_eq!(
+            strip_tracking_params(
+                "https://example.com/?a=1&utm_source=x&fbclid=y&b=2",
+                &params
+            ),
+            "https://example.com/?a=1&b=2"
+        );
+        assert_eq!(
+            strip_tracking_params("https://example.com/?utm_medium=x", &params),
+            "https://example.com/"
+        );
+        assert_eq!(
+            strip_tracking_params("https://example.com/?fbclid_other=1", &params),
+            "https://example.com/?fbclid_other=1"
+        );
+        assert_eq!(
+            strip_tracking_params("https://example.com/?utm_source=x", &[]),
+            "https://example.com/?utm_source=x"
+        );
+        assert_eq!(strip_tracking_params("not a url", &params), "not a url");
+    }
+
+    #[test]
+    fn page_title_without_site_name() {
+        assert_eq!(
+            extract_page_title(
+                "<html><head><TITLE lang=\"en\">\n  Pull requests · GitHub \n</TITLE>"
+            )
+            .as_deref(),
+            Some("Pull requests")
+        );
+        assert_eq!(
+            extract_page_title("<title>Tom &amp; Jerry - Cartoons | Site</title>").as_deref(),
+            Some("Tom & Jerry")
+        );
+        assert_eq!(
+            extract_page_title("<title>Plain</title>").as_deref(),
+            Some("Plain")
+        );
+        assert_eq!(extract_page_title("<title> </title>"), None);
+        assert_eq!(extract_page_title("<h1>No title</h1>"), None);
+    }
+
+    #[tokio::test]
+    async fn page_title_is_fetched_when_there_is_one() {
+        let server = MockServer::start(vec![
+            (
+                "/titled",
+                Route::ok("text/html", "<title>Inbox | Example Mail</title>"),
+            ),
+            ("/untitled", Route::ok("text/html", "<h1>Example</h1>")),
+        ])
+        .await;
+
+        assert_eq!(
+            fetch_page_title(&server.url("/titled")).await.as_deref(),
+            Some("Inbox")
+        );
+        assert_eq!(fetch_page_title(&server.url("/untitled")).await, None);
+        assert_eq!(fetch_page_title(&server.url("/missing")).await, None);
+    }
+
+    #[test]
+    fn icon_names_are_safe_and_distinct() {
+        let safe = |name: &str| {
+            name.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+
+        assert_eq!(sanitize_icon_name("Web App"), "WebApp");
+        assert_eq!(sanitize_icon_name("my-app_2"), "my-app_2");
+        assert!(sanitize_icon_name("Café").starts_with("Caf_-"));
+        assert!(sanitize_icon_name("Chat 💬").starts_with("Chat_-"));
+        assert!(sanitize_icon_name("???").starts_with("icon-"));
+        assert_ne!(sanitize_icon_name("a/b"), sanitize_icon_name("a:b"));
+        assert_ne!(sanitize_icon_name("a.b"), sanitize_icon_name("a_b"));
+        for name in ["../../etc", "a/b", "app.example.com", "🦀"] {
+            let sanitized = sanitize_icon_name(name);
+            assert!(safe(&sanitized), "{}", sanitized);
+            assert_eq!(sanitize_icon_name(&sanitized), sanitized);
+        }
+    }
+
+    #[test]
+    fn icons_are_saved_under_their_sanitized_name() {
+        let _root = crate::testing::data_root();
+        let path = icon_save_path("My App/../x", "png").unwrap();
+
+        assert_eq!(
+            Path::new(&path).parent(),
+            Some(qwa_icons_location().unwrap().as_path())
+        );
+        assert!(path.ends_with(&format!("{}.png", sanitize_icon_name("My App/../x"))));
+    }
+
+    #[test]
+    fn named_icons_resolve_in_the_icon_theme() {
+        let _root = crate::testing::data_root();
+        let apps = icons_location().unwrap().join("hicolor/scalable/apps");
+        fs::create_dir_all(&apps).unwrap();
+        fs::write(apps.join("qwa-test-chat.svg"), "<svg/>").unwrap();
+
+        let found = named_icon_path("qwa-test-chat").unwrap();
+        assert_eq!(found, apps.join("qwa-test-chat.svg"));
+        assert_eq!(icon_path("qwa-test-chat"), found.to_string_lossy());
+
+        assert_eq!(named_icon_path("qwa-test-unknown"), None);
+        assert_eq!(named_icon_path("hicolor/scalable/apps/qwa-test-chat"), None);
+        assert_eq!(named_icon_path(""), None);
+    }
+
+    #[test]
+    fn only_own_hicolor_icons_are_replaced() {
+        let _root = crate::testing::data_root();
+        let apps = hicolor_icons_location().unwrap().join("48x48/apps");
+        let qwa_icons = ensure_icons_writable().unwrap();
+        fs::create_dir_all(&apps).unwrap();
+
+        let users = apps.join("Example.png");
+        let ours = apps.join(format!("{}.png", webapp_id("Example")));
+        let hashed = apps.join(format!("{}-0123abcd.png", webapp_id("Example")));
+        let kept = qwa_icons.join("Example-89abcdef.png");
+        let previous = qwa_icons.join("Example.png");
+        for path in [&users, &ours, &hashed, &kept, &previous] {
+            fs::write(path, b"").unwrap();
+        }
+
+        remove_icon_variants("Example", std::slice::from_ref(&kept));
+
+        assert!(users.exists());
+        assert!(kept.exists());
+        assert!(!ours.exists());
+        assert!(!hashed.exists());
+        assert!(!previous.exists());
+    }
+
+    #[tokio::test]
+    async fn original_icon_keeps_the_detected_extension() {
+        let root = crate::testing::data_root();
+        let source = root.path().join("icon.dat");
+        fs::write(&source, png_icon(64)).unwrap();
+        let source = source.to_str().unwrap();
+
+        // off by default
+        move_icon(source, "Example1234", IconFormat::Svg)
+            .await
+            .unwrap();
+        assert_eq!(original_icon("Example1234"), None);
+
+        crate::testing::set_config(AppConfig {
+            keep_original_icons: true,
+            ..Default::default()
+        });
+        move_icon(source, "Example1234", IconFormat::Svg)
+            .await
+            .unwrap();
+        let original = original_icon("Example1234").unwrap();
+        assert_eq!(
+            original,
+            qwa_icons_location().unwrap().join("Example1234.orig.png")
+        );
+        assert_eq!(fs::read(&original).unwrap(), png_icon(64));
+
+        remove_original_icons("Example1234");
+        assert!(!original.exists());
+    }
+
+    #[tokio::test]
+    async fn unchanged_icons_are_decoded_once() {
+        let root = crate::testing::data_root();
+        let apps = root.path().join("hicolor/64x64/apps");
+        fs::create_dir_all(&apps).unwrap();
+        for name in ["example.png", "example-dark.png"] {
+            fs::write(apps.join(name), png_icon(64)).unwrap();
+        }
+        let cancel = SearchCancel::default();
+        let search = || {
+            find_icon(
+                root.path().to_path_buf(),
+                "example".to_string(),
+                DEFAULT_ICON_SEARCH_DEPTH,
+                &cancel,
+            )
+        };
+
+        let decodes = crate::testing::decodes();
+        assert_eq!(search().await.len(), 2);
+        assert_eq!(crate::testing::decodes(), decodes + 2);
+
+        assert_eq!(search().await.len(), 2);
+        assert_eq!(crate::testing::decodes(), decodes + 2);
+
+        // a changed file is decoded again
+        fs::write(apps.join("example.png"), png_icon(128)).unwrap();
+        assert_eq!(search().await.len(), 2);
+        assert_eq!(crate::testing::decodes(), decodes + 3);
+    }
+
+    #[tokio::test]
+    async fn unwritable_icons_dir_is_an_error() {
+        let root = crate::testing::data_root();
+        let source = root.path().join("icon.png");
+        fs::write(&source, png_icon(64)).unwrap();
+
+        // a file in the way can't be written to, whoever runs the test
+        let icons = qwa_icons_location().unwrap();
+        fs::create_dir_all(icons.parent().unwrap()).unwrap();
+        fs::write(&icons, b"").unwrap();
+
+        let err = ensure_icons_writable().unwrap_err().to_string();
+        assert!(err.contains(&format!("{:?}", icons)), "{}", err);
+        assert!(
+            move_icon(source.to_str().unwrap(), "Example1234", IconFormat::Png)
+                .await
+                .is_err()
+        );
+
+        // a read-only directory of the user is repaired
+        fs::remove_file(&icons).unwrap();
+        fs::create_dir_all(&icons).unwrap();
+        fs::set_permissions(&icons, fs::Permissions::from_mode(0o500)).unwrap();
+
+        assert_eq!(ensure_icons_writable().unwrap(), icons);
+        assert!(
+            move_icon(source.to_str().unwrap(), "Example1234", IconFormat::Png)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn menu_folder_is_written_and_removed() {
+        let _root = crate::testing::data_root();
+        let paths = DataPaths::current();
+        let directory = paths
+            .desktop_directories()
+            .unwrap()
+            .join(MENU_DIRECTORY_FILE);
+        let menu = paths.merged_menus().unwrap().join(MENU_FILE);
+
+        write_menu_folder("Web\nApps").unwrap();
+
+        let entry = fs::read_to_string(&directory).unwrap();
+        assert!(
+            entry.contains("Type=Directory\nName=Web\\nApps\n"),
+            "{}",
+            entry
+        );
+        let menu_xml = fs::read_to_string(&menu).unwrap();
+        assert!(menu_xml.contains(&format!("<Directory>{}</Directory>", MENU_DIRECTORY_FILE)));
+        assert!(menu_xml.contains(&format!("<Category>{}</Category>", MENU_CATEGORY)));
+
+        remove_menu_folder();
+        assert!(!directory.exists());
+        assert!(!menu.exists());
+    }
+
+    #[test]
+    fn svg_is_recognized_by_content() {
+        assert!(looks_like_svg(
+            b"\xef\xbb\xbf  <?xml version=\"1.0\"?>\n<svg xmlns=\"\"/>"
+        ));
+        assert!(looks_like_svg(b"<svg viewBox=\"0 0 1 1\"></svg>"));
+        assert!(!looks_like_svg(b"<?xml version=\"1.0\"?><html/>"));
+        assert!(!looks_like_svg(b"\x89PNG\r\n\x1a\n"));
+    }
+
+    #[test]
+    fn undecodable_images_are_decode_errors() {
+        assert!(matches!(
+            decode_still_image(b"not an image"),
+            Err(Error::Decode(_))
+        ));
+        assert!(matches!(
+            decode_still_image(&png_icon(16)[..32]),
+            Err(Error::Decode(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn missing_icons_are_reported_by_kind() {
+        let root = crate::testing::data_root();
+        let server = MockServer::start(vec![("/gone.png", Route::status(404))]).await;
+
+        let missing = root.path().join("missing.png");
+        assert!(matches!(
+            move_icon(missing.to_str().unwrap(), "Example1234", IconFormat::Png).await,
+            Err(Error::IconNotFound(path)) if path == missing
+        ));
+        assert!(matches!(
+            move_icon(&server.url("/gone.png"), "Example1234", IconFormat::Png).await,
+            Err(Error::Http { status, .. }) if status == reqwest::StatusCode::NOT_FOUND
+        ));
+    }
+
+    #[test]
+    fn only_opaque_icons_are_compacted() {
+        let _root = crate::testing::data_root();
+        ensure_icons_writable().unwrap();
+        crate::testing::set_config(AppConfig {
+            compact_icons: true,
+            ..Default::default()
+        });
+        let encode = |image: image::RgbaImage| {
+            let mut data = Vec::new();
+            image
+                .write_to(&mut Cursor::new(&mut data), image::ImageFormat::Png)
+                .unwrap();
+            Bytes::from(data)
+        };
+
+        let mut transparent = image::RgbaImage::from_pixel(64, 64, image::Rgba([255, 0, 0, 255]));
+        transparent.put_pixel(0, 0, image::Rgba([0, 0, 0, 0]));
+        let saved = save_png_icon(&encode(transparent), "Transparent").unwrap();
+        assert!(saved.ends_with("Transparent.png"), "{}", saved);
+        assert_eq!(
+            image::guess_format(&fs::read(&saved).unwrap()).unwrap(),
+            image::ImageFormat::Png
+        );
+
+        let photo = image::RgbaImage::from_fn(64, 64, |x, y| {
+            image::Rgba([(x * 4) as u8, (y * 4) as u8, 128, 255])
+        });
+        let saved = save_png_icon(&encode(photo.clone()), "Photo").unwrap();
+        assert!(saved.ends_with("Photo.webp"), "{}", saved);
+        let decoded = load_from_memory(&fs::read(&saved).unwrap()).unwrap();
+        assert_eq!(decoded.to_rgba8(), photo);
+    }
+
+    fn animated_gif() -> Bytes {
+        let mut data = Vec::new();
+        {
+            let mut encoder = image::codecs::gif::GifEncoder::new(&mut data);
+            for color in [[255, 0, 0, 255], [0, 0, 255, 255]] {
+                let frame = image::RgbaImage::from_pixel(64, 64, image::Rgba(color));
+                encoder.encode_frame(image::Frame::new(frame)).unwrap();
+            }
+        }
+
+        Bytes::from(data)
+    }
+
+    #[tokio::test]
+    async fn animated_favicons_become_their_first_frame() {
+        let _root = crate::testing::data_root();
+        let icons = ensure_icons_writable().unwrap();
+
+        let (still, animated) = decode_still_image(&animated_gif()).unwrap();
+        assert!(animated);
+        assert_eq!(still.to_rgba8().get_pixel(0, 0).0, [255, 0, 0, 255]);
+
+        let path = icons.join("animated.gif");
+        fs::write(&path, animated_gif()).unwrap();
+        let icon = image_handle(path.to_string_lossy().to_string())
+            .await
+            .unwrap();
+        assert_eq!(icon.format, Some(SourceFormat::Gif));
+        assert!(matches!(icon.icon, IconType::Raster(_)));
+
+        let svg = convert_raster_to_svg_format(animated_gif(), "Example1234").unwrap();
+        let embedded = extract_wrapped_raster(&fs::read_to_string(svg).unwrap()).unwrap();
+        assert_eq!(
+            image::guess_format(&embedded).unwrap(),
+            image::ImageFormat::Png
+        );
+        assert_eq!(
+            load_from_memory(&embedded).unwrap().to_rgba8(),
+            still.to_rgba8()
+        );
+    }
+
+    fn png_icon(size: u32) -> Bytes {
+        let image = DynamicImage::new_rgba8(size, size);
+        Bytes::from(encode_png(&image).unwrap())
+    }
+
+    #[test]
+    fn icon_formats_install_their_files() {
+        let _root = crate::testing::data_root();
+        let icons = ensure_icons_writable().unwrap();
+        let hicolor = hicolor_icons_location().unwrap();
+
+        let svg = install_icon(png_icon(256), "Example1234", IconFormat::Svg).unwrap();
+        assert_eq!(PathBuf::from(&svg), icons.join("Example1234.svg"));
+        assert!(extract_wrapped_raster(&fs::read_to_string(&svg).unwrap()).is_some());
+        assert_eq!(
+            desktop_icon_value("Example1234", IconFormat::Svg).unwrap(),
+            svg
+        );
+
+        let png = install_icon(png_icon(256), "Example1234", IconFormat::Png).unwrap();
+        assert_eq!(PathBuf::from(&png), icons.join("Example1234.png"));
+        assert_eq!(image::open(&png).unwrap().dimensions(), (256, 256));
+        assert_eq!(
+            desktop_icon_value("Example1234", IconFormat::Png).unwrap(),
+            png
+        );
+
+        // referenced by name, which carries the app id
+        let name = install_icon(png_icon(256), "Example1234", IconFormat::HicolorPngSet).unwrap();
+        assert_eq!(name, webapp_id("Example1234"));
+        assert_eq!(
+            desktop_icon_value("Example1234", IconFormat::HicolorPngSet).unwrap(),
+            name
+        );
+
+        for size in HICOLOR_SIZES {
+            let path = hicolor.join(format!("{}x{}/apps/{}.png", size, size, name));
+            assert_eq!(image::open(path).unwrap().dimensions(), (size, size));
+        }
+        assert!(!hicolor.join("256x256/apps/Example1234.png").exists());
+    }
+
+    #[test]
+    fn svg_icons_install_into_hicolor_by_app_id() {
+        let _root = crate::testing::data_root();
+        let hicolor = hicolor_icons_location().unwrap();
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"/>"#;
+
+        let name = install_svg_icon(svg, "firefox", IconFormat::HicolorPngSet).unwrap();
+
+        assert_eq!(name, webapp_id("firefox"));
+        assert!(hicolor.join(format!("scalable/apps/{}.svg", name)).exists());
+        assert!(hicolor.join(format!("48x48/apps/{}.png", name)).exists());
+        assert!(!hicolor.join("scalable/apps/firefox.svg").exists());
+        assert!(is_installed_icon(&name, "firefox"));
+        assert!(!is_installed_icon("firefox", "firefox"));
+    }
+
+    #[test]
+    fn svg_is_rasterized_at_the_requested_size() {
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="32" height="16">
+            <rect width="32" height="16" fill="#ff0000"/></svg>"##;
+
+        let png = rasterize_svg(svg, 256).unwrap();
+        let image = image::load_from_memory_with_format(&png, image::ImageFormat::Png)
+            .unwrap()
+            .to_rgba8();
+
+        assert_eq!(image.dimensions(), (256, 256));
+        // centered, the wide image leaves the top and bottom transparent
+        assert_eq!(image.get_pixel(128, 128).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(128, 10).0[3], 0);
+
+        assert!(rasterize_svg(b"not an svg", 256).is_err());
+    }
+
+    #[tokio::test]
+    async fn svg_conversion_off_the_runtime_matches_the_blocking_one() {
+        let _root = crate::testing::data_root();
+        ensure_icons_writable().unwrap();
+        let data = png_icon(512);
+
+        let blocking = convert_raster_to_svg_format(data.clone(), "Blocking1234").unwrap();
+        let pooled = convert_raster_to_svg_format_async(data.clone(), "Pooled1234")
+            .await
+            .unwrap();
+
+        assert_ne!(blocking, pooled);
+        assert_eq!(fs::read(&blocking).unwrap(), fs::read(&pooled).unwrap());
+
+        // undecodable downloads fail the same way on the blocking pool
+        let broken = convert_raster_to_svg_format_async(Bytes::from_static(b"not an image"), "x");
+        assert!(broken.await.is_err());
+    }
+
+    #[test]
+    fn wrapped_icons_migrate_to_their_original_raster() {
+        let _root = crate::testing::data_root();
+        let icons = ensure_icons_writable().unwrap();
+        let original = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(64, 48, |x, y| {
+            image::Rgba([x as u8 * 4, y as u8 * 5, 128, 255])
+        }));
+        let data = Bytes::from(encode_png(&original).unwrap());
+
+        let wrapped = convert_raster_to_svg_format(data, "Example1234").unwrap();
+        let extracted = extract_wrapped_raster(&fs::read_to_string(&wrapped).unwrap()).unwrap();
+        assert_eq!(load_from_memory(&extracted).unwrap(), original);
+
+        // a plain SVG isn't touched
+        let plain = icons.join("Plain1234.svg");
+        fs::write(&plain, r#"<svg xmlns="http://www.w3.org/2000/svg"/>"#).unwrap();
+
+        let migrated = migrate_wrapped_icons(IconFormat::Png).unwrap();
+        let installed = icons.join("Example1234.png");
+
+        assert_eq!(
+            migrated,
+            vec![(wrapped.clone(), installed.to_string_lossy().to_string())]
+        );
+        assert!(!Path::new(&wrapped).exists());
+        assert!(plain.exists());
+        assert_eq!(image::open(&installed).unwrap(), original);
+    }
+
+    #[test]
+    fn missing_home_dir_is_reported() {
+        let paths = homeless();
+
+        assert!(matches!(paths.applications(), Err(Error::NoHomeDir)));
+        assert!(matches!(paths.desktop_directories(), Err(Error::NoHomeDir)));
+        assert!(matches!(paths.merged_menus(), Err(Error::NoHomeDir)));
+        assert!(matches!(paths.database(), Err(Error::NoHomeDir)));
+        assert!(matches!(paths.themes(), Err(Error::NoHomeDir)));
+        assert!(matches!(paths.profiles(), Err(Error::NoHomeDir)));
+        assert!(matches!(paths.icons(), Err(Error::NoHomeDir)));
+        assert!(matches!(
+            Browser::default().profile_path(),
+            Err(Error::NoHomeDir)
+        ));
+
+        // the system scope doesn't depend on the home directory
+        let paths = homeless().scope(InstallScope::System);
+        assert!(paths.applications().is_ok());
+        assert!(paths.merged_menus().is_ok());
+    }
+
+    #[test]
+    fn scope_routes_desktop_files_and_icons() {
+        let root = crate::testing::data_root();
+        let user = DataPaths::with_root(root.path().to_path_buf());
+        let system = user.clone().scope(InstallScope::System);
+
+        assert_eq!(
+            user.applications().unwrap(),
+            root.path().join("applications")
+        );
+        assert_eq!(user.icons().unwrap(), root.path().join("icons"));
+        assert!(user.check_writable().is_ok());
+
+        let shared = root.path().join("usr/share");
+        assert_eq!(system.applications().unwrap(), shared.join("applications"));
+        assert_eq!(system.icons().unwrap(), shared.join("icons"));
+
+        let err = system.check_writable().unwrap_err().to_string();
+        assert!(err.contains("run as administrator"), "{}", err);
+
+        fs::create_dir_all(shared.join("applications")).unwrap();
+        fs::create_dir_all(shared.join("icons")).unwrap();
+        assert!(system.check_writable().is_ok());
+    }
+
+    #[test]
+    fn paths_without_home_are_never_relative() {
+        let home = std::env::var_os("HOME");
+        std::env::remove_var("HOME");
+
+        let paths = DataPaths::system();
+        let results = [
+            paths.applications(),
+            paths.desktop_directories(),
+            paths.merged_menus(),
+            paths.database(),
+            paths.themes(),
+            paths.profiles(),
+        ];
+
+        if let Some(home) = home {
+            std::env::set_var("HOME", home);
+        }
+
+        // dirs may still find the home directory in the passwd database
+        for result in results {
+            match result {
+                Ok(path) => assert!(path.is_absolute(), "{:?}", path),
+                Err(err) => assert!(matches!(err, Error::NoHomeDir)),
+            }
         }
     }
 }
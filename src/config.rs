@@ -1,15 +1,92 @@
 use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
 
-use crate::{APP_ID, CONFIG_VERSION};
+use crate::{
+    common::{IconFormat, LaunchMethod},
+    APP_ID, CONFIG_VERSION,
+};
 
-#[derive(Debug, Default, Clone, CosmicConfigEntry, Eq, PartialEq)]
+pub const DEFAULT_MAX_DOWNLOAD_SIZE: u64 = 10 * 1024 * 1024;
+pub const DEFAULT_REQUEST_TIMEOUT: u64 = 10;
+// icons root / theme / size / category / icon
+pub const DEFAULT_ICON_SEARCH_DEPTH: usize = 4;
+
+pub fn default_tracking_params() -> Vec<String> {
+    ["utm_*", "fbclid", "gclid", "mc_eid", "msclkid"]
+        .map(String::from)
+        .to_vec()
+}
+
+#[derive(Debug, Clone, CosmicConfigEntry, Eq, PartialEq)]
 #[version = 1]
 pub struct AppConfig {
     pub app_theme: String,
+    pub icon_format: IconFormat,
+    // icon files get a content hash suffix, so a replaced icon
+    // never shows up from a stale icon theme cache
+    pub hashed_icon_names: bool,
+    // icons are also kept as downloaded, next to the installed ones
+    pub keep_original_icons: bool,
+    // png icons without transparency are stored as webp, which is smaller
+    // for photo like icons but not shown by every desktop
+    pub compact_icons: bool,
+    pub offline_mode: bool,
+    // asks a third party favicon service when nothing else was found,
+    // which tells that service what site the web app is for
+    pub favicon_service_fallback: bool,
+    // skips TLS verification for icon downloads, for self-signed homelab services
+    pub accept_invalid_icon_certs: bool,
+    // starting value of the per app isolated profile switch
+    pub isolate_profiles: bool,
+    pub launch_method: LaunchMethod,
+    // web apps are collected in a menu folder, named after the localized
+    // "Web Apps" when the name is empty
+    pub menu_folder: bool,
+    pub menu_folder_name: String,
+    // in bytes, applies to every downloaded icon
+    pub max_download_size: u64,
+    // in seconds
+    pub request_timeout: u64,
+    // how many directories below an icons root are scanned
+    pub icon_search_depth: usize,
+    pub strip_tracking_params: bool,
+    // query parameters removed from web app urls, `*` matches any suffix
+    pub tracking_params: Vec<String>,
+    // `BrowserId`s left out of the browser picker
+    pub hidden_browsers: Vec<String>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            app_theme: String::new(),
+            icon_format: IconFormat::default(),
+            hashed_icon_names: false,
+            keep_original_icons: false,
+            compact_icons: false,
+            offline_mode: false,
+            favicon_service_fallback: false,
+            accept_invalid_icon_certs: false,
+            isolate_profiles: true,
+            launch_method: LaunchMethod::default(),
+            menu_folder: false,
+            menu_folder_name: String::new(),
+            max_download_size: DEFAULT_MAX_DOWNLOAD_SIZE,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            icon_search_depth: DEFAULT_ICON_SEARCH_DEPTH,
+            strip_tracking_params: false,
+            tracking_params: default_tracking_params(),
+            hidden_browsers: Vec::new(),
+        }
+    }
 }
 
 impl AppConfig {
     pub fn config_handler() -> Option<cosmic_config::Config> {
+        // tests never read or change the user's settings
+        if cfg!(test) {
+            return None;
+        }
+
         cosmic_config::Config::new(APP_ID, CONFIG_VERSION).ok()
     }
     pub fn config() -> AppConfig {
@@ -20,6 +97,10 @@ impl AppConfig {
                     config
                 })
             }
+            // see `testing::set_config`
+            #[cfg(test)]
+            None => crate::testing::config(),
+            #[cfg(not(test))]
             None => AppConfig::default(),
         }
     }
@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+
+// Failures of creating a web app, from downloading its icon to writing the
+// launcher, told apart so the UI can explain what went wrong.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("network error: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("{url} answered with {status}")]
+    Http {
+        url: String,
+        status: reqwest::StatusCode,
+    },
+    #[error("{url} exceeds the download limit of {limit} bytes")]
+    TooLarge { url: String, limit: u64 },
+    #[error("can't decode image: {0}")]
+    Decode(#[from] image::ImageError),
+    #[error("invalid url {0:?}")]
+    InvalidUrl(String),
+    #[error("browser {0} isn't installed")]
+    BrowserNotFound(String),
+    #[error("icon {0:?} not found")]
+    IconNotFound(PathBuf),
+    #[error("could not determine home directory")]
+    NoHomeDir,
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
@@ -1,6 +1,36 @@
+use once_cell::sync::Lazy;
 use reqwest::Client;
+use std::{collections::HashMap, sync::Mutex};
 use url::Url;
 
+use crate::common::{self, SourceFormat};
+
+static ICON_CACHE: Lazy<IconCache> = Lazy::new(IconCache::default);
+// manifests by page url, the editor's name and the letter icon's colors
+// come from the same manifest
+static MANIFEST_CACHE: Lazy<Mutex<HashMap<String, Option<WebManifest>>>> =
+    Lazy::new(Default::default);
+
+// Keeps favicons found for every page during the app lifetime,
+// so opening the icon picker again doesn't repeat the same requests.
+// Pages of one site can declare different icons, so it's keyed by url.
+#[derive(Debug, Default)]
+pub struct IconCache {
+    entries: Mutex<HashMap<String, Vec<FaviconCandidate>>>,
+}
+
+impl IconCache {
+    pub fn get(&self, url: &str) -> Option<Vec<FaviconCandidate>> {
+        self.entries.lock().ok()?.get(url).cloned()
+    }
+
+    pub fn insert(&self, url: String, icons: Vec<FaviconCandidate>) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(url, icons);
+        }
+    }
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct FaviconResponse {
     pub url: String,
@@ -17,29 +47,834 @@ pub struct FaviconIcon {
     pub href: String,
 }
 
-pub async fn download_favicon(url: &str) -> anyhow::Result<Vec<String>> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaviconOrigin {
+    // declared by the page, as found by the favicon extractor service
+    Declared,
+    // found at a well known location on the site
+    Probed,
+    // Windows tile image, from a meta tag or `browserconfig.xml`
+    Tile,
+}
+
+// A favicon found for a site, with what is known about it before downloading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaviconCandidate {
+    pub url: String,
+    pub origin: FaviconOrigin,
+    // largest side in pixels, as declared by the page
+    pub size: Option<u32>,
+    // guessed from the file extension
+    pub format: Option<SourceFormat>,
+}
+
+impl FaviconCandidate {
+    fn new(url: String, origin: FaviconOrigin, sizes: &str) -> Self {
+        let format = url
+            .rsplit('/')
+            .next()
+            .and_then(|name| name.split(['?', '#']).next())
+            .and_then(|name| name.rsplit_once('.'))
+            .and_then(|(_, extension)| format_from_extension(extension));
+
+        Self {
+            size: parse_sizes(sizes),
+            url,
+            origin,
+            format,
+        }
+    }
+
+    // Short description like "declared, png, 192px".
+    pub fn label(&self) -> String {
+        let mut label = match self.origin {
+            FaviconOrigin::Declared => String::from("declared"),
+            FaviconOrigin::Probed => String::from("probed"),
+            FaviconOrigin::Tile => String::from("tile"),
+        };
+
+        if let Some(format) = self.format {
+            label.push_str(&format!(", {}", format.extension()));
+        }
+
+        if let Some(size) = self.size {
+            label.push_str(&format!(", {}px", size));
+        }
+
+        label
+    }
+}
+
+fn format_from_extension(extension: &str) -> Option<SourceFormat> {
+    if extension.eq_ignore_ascii_case("svg") {
+        return Some(SourceFormat::Svg);
+    }
+
+    image::ImageFormat::from_extension(extension).and_then(SourceFormat::from_image)
+}
+
+// Largest side from a `sizes` attribute like "16x16 32x32", "any" is left unknown.
+fn parse_sizes(sizes: &str) -> Option<u32> {
+    sizes
+        .split_whitespace()
+        .filter_map(|size| {
+            let (width, height) = size.split_once(['x', 'X'])?;
+            Some(width.parse::<u32>().ok()?.max(height.parse().ok()?))
+        })
+        .max()
+}
+
+// Well known locations probed on the site itself. `/favicon.ico` goes first,
+// the others are tried only when it's missing.
+pub const FAVICON_PROBE_PATHS: [&str; 10] = [
+    "/favicon.ico",
+    "/favicon.png",
+    "/favicon.svg",
+    "/apple-touch-icon.png",
+    "/assets/favicon.svg",
+    "/static/icons/icon.png",
+    "/static/favicon.ico",
+    "/assets/favicon.ico",
+    "/images/favicon.ico",
+    "/img/favicon.ico",
+];
+
+// Square logos of `browserconfig.xml` with their sizes, wide tiles don't fit an icon.
+const TILE_LOGOS: [(&str, &str); 3] = [
+    ("square310x310logo", "310x310"),
+    ("square150x150logo", "150x150"),
+    ("square70x70logo", "70x70"),
+];
+
+// Opening tags `<name ...>` of an html or xml document.
+fn find_tags<'a>(document: &'a str, name: &str) -> Vec<&'a str> {
+    let lowercase = document.to_ascii_lowercase();
+    let pattern = format!("<{}", name.to_ascii_lowercase());
+    let mut tags = Vec::new();
+    let mut offset = 0;
+
+    while let Some(start) = lowercase[offset..].find(&pattern).map(|i| i + offset) {
+        let after = start + pattern.len();
+        let Some(end) = lowercase[after..].find('>').map(|i| i + after) else {
+            break;
+        };
+
+        if lowercase[after..].starts_with(|c: char| c.is_whitespace() || c == '/' || c == '>') {
+            tags.push(&document[start..end]);
+        }
+
+        offset = end;
+    }
+
+    tags
+}
+
+// Value of a quoted attribute of a tag found by `find_tags`.
+fn tag_attribute(tag: &str, attribute: &str) -> Option<String> {
+    let lowercase = tag.to_ascii_lowercase();
+    let pattern = format!("{}=", attribute.to_ascii_lowercase());
+
+    lowercase.match_indices(&pattern).find_map(|(start, _)| {
+        let preceded_by_space = lowercase[..start].ends_with(char::is_whitespace);
+        let value = &tag[start + pattern.len()..];
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value = &value[1..];
+
+        preceded_by_space.then(|| value[..value.find(quote).unwrap_or(value.len())].to_string())
+    })
+}
+
+// `content` of the `<meta name="...">` tag with the given name.
+fn meta_content(html: &str, name: &str) -> Option<String> {
+    find_tags(html, "meta").into_iter().find_map(|tag| {
+        tag_attribute(tag, "name")
+            .filter(|value| value.eq_ignore_ascii_case(name))
+            .and_then(|_| tag_attribute(tag, "content"))
+    })
+}
+
+// Bodies are capped by `read_body_limited` and slow servers cut off by the
+// client's timeout, a failed page only drops the candidates it would give.
+async fn fetch_text(client: &Client, url: &Url) -> Option<String> {
+    let response = match client.get(url.clone()).send().await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            tracing::debug!("{} answered with {}", url, response.status());
+            return None;
+        }
+        Err(err) => {
+            tracing::debug!("can't fetch {}: {}", url, err);
+            return None;
+        }
+    };
+
+    match common::read_body_limited(response, common::max_download_size()).await {
+        Ok(body) => Some(String::from_utf8_lossy(&body).to_string()),
+        Err(err) => {
+            tracing::warn!("skipping {}: {}", url, err);
+            None
+        }
+    }
+}
+
+// Colors declared by the web app manifest, as `#rrggbb` or `#rgb`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManifestColors {
+    pub theme: Option<String>,
+    pub background: Option<String>,
+}
+
+// Only hex colors are taken, they end up in a generated SVG.
+fn hex_color(value: &serde_json::Value) -> Option<String> {
+    let color = value.as_str()?.trim();
+    let digits = color.strip_prefix('#')?;
+
+    (matches!(digits.len(), 3 | 6) && digits.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| color.to_ascii_lowercase())
+}
+
+// Web app manifest linked by the page.
+async fn fetch_manifest(url: &str) -> Option<serde_json::Value> {
+    let url = Url::parse(url).ok()?;
+    // the manifest lists the icons, of self-hosted services too
+    let client = common::icon_client();
+
+    let html = fetch_text(&client, &url).await?;
+    let manifest = find_tags(&html, "link").into_iter().find_map(|tag| {
+        tag_attribute(tag, "rel")
+            .filter(|rel| rel.eq_ignore_ascii_case("manifest"))
+            .and_then(|_| tag_attribute(tag, "href"))
+    })?;
+    let manifest = url.join(&manifest).ok()?;
+
+    serde_json::from_str(&fetch_text(&client, &manifest).await?)
+        .map_err(|err| tracing::debug!("invalid manifest {}: {}", manifest, err))
+        .ok()
+}
+
+// Manifest fields naming the app, in order of preference. The short name is
+// meant for launchers, "Maps" rather than "Google Maps".
+pub const MANIFEST_NAME_FIELDS: [&str; 2] = ["short_name", "name"];
+
+pub fn manifest_app_name(manifest: &serde_json::Value) -> Option<String> {
+    MANIFEST_NAME_FIELDS.iter().find_map(|field| {
+        manifest
+            .get(field)
+            .and_then(|name| name.as_str())
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(String::from)
+    })
+}
+
+// Fields of the web app manifest the app uses.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WebManifest {
+    pub name: Option<String>,
+    pub colors: ManifestColors,
+}
+
+impl WebManifest {
+    pub fn from_json(json: &serde_json::Value) -> Self {
+        Self {
+            name: manifest_app_name(json),
+            colors: ManifestColors {
+                theme: json.get("theme_color").and_then(hex_color),
+                background: json.get("background_color").and_then(hex_color),
+            },
+        }
+    }
+}
+
+// Manifest linked by the page, fetched once per url.
+pub async fn web_manifest(url: &str) -> Option<WebManifest> {
+    if let Some(cached) = MANIFEST_CACHE
+        .lock()
+        .ok()
+        .and_then(|cache| cache.get(url).cloned())
+    {
+        return cached;
+    }
+
+    let manifest = fetch_manifest(url)
+        .await
+        .map(|json| WebManifest::from_json(&json));
+
+    if let Ok(mut cache) = MANIFEST_CACHE.lock() {
+        cache.insert(url.to_string(), manifest.clone());
+    }
+
+    manifest
+}
+
+// Name of the app declared by the manifest linked by the page.
+pub async fn manifest_name(url: &str) -> Option<String> {
+    web_manifest(url).await?.name
+}
+
+// `theme_color` and `background_color` of the manifest linked by the page.
+pub async fn manifest_colors(url: &str) -> Option<ManifestColors> {
+    let colors = web_manifest(url).await?.colors;
+
+    (colors != ManifestColors::default()).then_some(colors)
+}
+
+// Icons of the page's `<link>` tags, including the legacy `shortcut icon`
+// relation which some sites still use alone.
+fn linked_favicons(url: &Url, html: &str) -> Vec<FaviconCandidate> {
+    find_tags(html, "link")
+        .into_iter()
+        .filter(|tag| {
+            tag_attribute(tag, "rel").is_some_and(|rel| {
+                rel.split_whitespace().any(|relation| {
+                    relation.eq_ignore_ascii_case("icon")
+                        || relation.eq_ignore_ascii_case("apple-touch-icon")
+                })
+            })
+        })
+        .filter_map(|tag| {
+            let href = url.join(&tag_attribute(tag, "href")?).ok()?;
+            let sizes = tag_attribute(tag, "sizes").unwrap_or_default();
+
+            Some(FaviconCandidate::new(
+                href.to_string(),
+                FaviconOrigin::Declared,
+                &sizes,
+            ))
+        })
+        .collect()
+}
+
+// Legacy Windows tile images, which are often a decent square logo of sites
+// declaring nothing else. `msapplication-config` can point to another
+// `browserconfig.xml` than the default one, or disable it with `none`.
+async fn tile_favicons(client: &Client, url: &Url, html: &str) -> Vec<FaviconCandidate> {
+    let mut found = Vec::new();
+
+    if let Some(image) = meta_content(html, "msapplication-TileImage") {
+        if let Ok(image) = url.join(&image) {
+            found.push(FaviconCandidate::new(
+                image.to_string(),
+                FaviconOrigin::Tile,
+                "144x144",
+            ));
+        }
+    }
+
+    let config = match meta_content(html, "msapplication-config") {
+        Some(config) if config.eq_ignore_ascii_case("none") => return found,
+        Some(config) => url.join(&config),
+        None => url.join("/browserconfig.xml"),
+    };
+
+    let Ok(config) = config else {
+        return found;
+    };
+
+    let Some(xml) = fetch_text(client, &config).await else {
+        return found;
+    };
+
+    for (name, sizes) in TILE_LOGOS {
+        let logos = find_tags(&xml, name)
+            .into_iter()
+            .filter_map(|tag| tag_attribute(tag, "src"))
+            .filter_map(|src| config.join(&src).ok());
+
+        for logo in logos {
+            found.push(FaviconCandidate::new(
+                logo.to_string(),
+                FaviconOrigin::Tile,
+                sizes,
+            ));
+        }
+    }
+
+    for image in find_tags(&xml, "TileImage")
+        .into_iter()
+        .filter_map(|tag| tag_attribute(tag, "src"))
+        .filter_map(|src| config.join(&src).ok())
+    {
+        found.push(FaviconCandidate::new(
+            image.to_string(),
+            FaviconOrigin::Tile,
+            "",
+        ));
+    }
+
+    found
+}
+
+// Last resort used only when enabled in the settings, as it sends the host
+// of every looked up site to DuckDuckGo.
+pub const FAVICON_SERVICE_URL: &str = "https://icons.duckduckgo.com/ip3";
+
+fn favicon_service_url() -> String {
+    // tests answer with a mock server
+    #[cfg(test)]
+    if let Some(service) = crate::testing::favicon_service() {
+        return service;
+    }
+
+    FAVICON_SERVICE_URL.to_string()
+}
+
+pub async fn service_favicon(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+
+    if common::is_local_host(&url) {
+        return None;
+    }
+
+    let service = favicon_service_url();
+    let candidate = format!("{}/{}.ico", service, common::normalized_host(&url)?);
+    // a public service, never asked without verifying its certificate
+    let response = common::http_client().head(&candidate).send().await.ok()?;
+
+    if response.status().is_success() {
+        return Some(candidate);
+    }
+
+    tracing::debug!("{} has no icon for {}", service, url);
+
+    None
+}
+
+async fn extractor_favicons(
+    client: &Client,
+    domain: &str,
+) -> anyhow::Result<Vec<FaviconCandidate>> {
+    let request = client
+        .get(format!(
+            "https://www.faviconextractor.com/api/favicon/{}",
+            domain
+        ))
+        .send()
+        .await?;
+
+    let body = common::read_body_limited(request, common::max_download_size()).await?;
+    let response: FaviconResponse = serde_json::from_slice(&body)?;
+
+    if response.status == 200 {
+        return Ok(response
+            .icons
+            .into_iter()
+            .map(|icon| FaviconCandidate::new(icon.href, FaviconOrigin::Declared, &icon.sizes))
+            .collect());
+    }
+
+    Ok(Vec::new())
+}
+
+async fn probe_favicons(
+    client: &Client,
+    url: &Url,
+    known: &[FaviconCandidate],
+) -> Vec<FaviconCandidate> {
+    let mut found = Vec::new();
+
+    for path in FAVICON_PROBE_PATHS {
+        let Ok(candidate) = url.join(path) else {
+            continue;
+        };
+
+        let response = match client.head(candidate.clone()).send().await {
+            Ok(response) => response,
+            Err(err) => {
+                tracing::debug!("probing {} failed: {}", candidate, err);
+                continue;
+            }
+        };
+
+        if response.status().is_success() {
+            if !known.iter().any(|icon| icon.url == candidate.as_str()) {
+                found.push(FaviconCandidate::new(
+                    candidate.to_string(),
+                    FaviconOrigin::Probed,
+                    "",
+                ));
+            }
+
+            if path == "/favicon.ico" {
+                break;
+            }
+        }
+    }
+
+    found
+}
+
+// Every favicon found for the site, largest first with vector icons ahead of all.
+pub async fn favicon_candidates(
+    url: &str,
+    force_refresh: bool,
+) -> anyhow::Result<Vec<FaviconCandidate>> {
     let mut favicons = Vec::new();
 
     let url = Url::parse(url)?;
+    let cache_key = common::normalized_url(url.as_str()).unwrap_or_default();
 
-    if let Some(domain) = url.domain() {
-        let request = Client::new()
-            .get(format!(
-                "https://www.faviconextractor.com/api/favicon/{}",
-                domain
-            ))
-            .send()
-            .await?;
+    if !force_refresh {
+        if let Some(cached) = ICON_CACHE.get(&cache_key) {
+            return Ok(cached);
+        }
+    }
 
-        let response: FaviconResponse = request.json().await?;
+    let client = common::http_client();
 
-        if response.status == 200 {
-            response
-                .icons
-                .iter()
-                .for_each(|icon| favicons.push(icon.href.clone()));
+    // external favicon services can't reach hosts from local network
+    if !common::is_local_host(&url) {
+        if let Some(domain) = url.domain().and(common::normalized_host(&url)) {
+            match extractor_favicons(&client, &domain).await {
+                Ok(icons) => favicons.extend(icons),
+                Err(err) => tracing::warn!("favicon service lookup for {} failed: {}", domain, err),
+            }
         }
     }
 
+    // the page is asked like the icons, it's how self-hosted ones are found
+    let icon_client = common::icon_client();
+    let page = fetch_text(&icon_client, &url).await;
+    let html = page.as_deref().unwrap_or_default();
+
+    for linked in linked_favicons(&url, html) {
+        if !favicons.iter().any(|icon| icon.url == linked.url) {
+            favicons.push(linked);
+        }
+    }
+
+    let probed = probe_favicons(&icon_client, &url, &favicons).await;
+    favicons.extend(probed);
+
+    for tile in tile_favicons(&icon_client, &url, html).await {
+        if !favicons.iter().any(|icon| icon.url == tile.url) {
+            favicons.push(tile);
+        }
+    }
+
+    // stable, so candidates without a known size keep their order,
+    // tile images come last as they're meant for the Windows start menu
+    favicons.sort_by_key(|icon| {
+        (
+            icon.origin == FaviconOrigin::Tile,
+            icon.format != Some(SourceFormat::Svg),
+            std::cmp::Reverse(icon.size.unwrap_or_default()),
+        )
+    });
+
+    for icon in &favicons {
+        tracing::debug!("favicon {} ({})", icon.url, icon.label());
+    }
+
+    // tells an unreachable site apart from one without icons, and isn't cached
+    if favicons.is_empty() && page.is_none() {
+        anyhow::bail!("can't fetch {}", url);
+    }
+
+    ICON_CACHE.insert(cache_key, favicons.clone());
+
     Ok(favicons)
 }
+
+pub async fn download_favicon(url: &str, force_refresh: bool) -> anyhow::Result<Vec<String>> {
+    Ok(favicon_candidates(url, force_refresh)
+        .await?
+        .into_iter()
+        .map(|icon| icon.url)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::testing::{MockServer, Route};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn local_hosts_are_asked_directly() {
+        let server = MockServer::start(vec![
+            ("/", Route::ok("text/html", "<html></html>")),
+            ("/favicon.ico", Route::ok("image/x-icon", "ico")),
+        ])
+        .await;
+
+        let icons = download_favicon(&server.url("/"), true).await.unwrap();
+
+        assert_eq!(icons, vec![server.url("/favicon.ico")]);
+        assert!(service_favicon(&server.url("/")).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn self_signed_icons_are_fetched_only_when_enabled() {
+        let server = MockServer::start_tls(vec![
+            (
+                "/",
+                Route::ok(
+                    "text/html",
+                    r#"<title>Home</title><link rel="icon" href="/icon.png">"#,
+                ),
+            ),
+            ("/icon.png", Route::ok("image/png", "png")),
+        ])
+        .await;
+
+        assert!(download_favicon(&server.url("/"), true).await.is_err());
+
+        crate::testing::set_config(AppConfig {
+            accept_invalid_icon_certs: true,
+            ..Default::default()
+        });
+        let icons = download_favicon(&server.url("/"), true).await.unwrap();
+        assert_eq!(icons, vec![server.url("/icon.png")]);
+
+        // anything but icons keeps verifying the certificate
+        assert_eq!(common::fetch_page_title(&server.url("/")).await, None);
+    }
+
+    #[tokio::test]
+    async fn alternative_locations_are_probed_without_favicon_ico() {
+        let server = MockServer::start(vec![
+            (
+                "/",
+                Route::ok(
+                    "text/html",
+                    r#"<link rel="icon" href="/assets/favicon.svg">"#,
+                ),
+            ),
+            ("/favicon.png", Route::ok("image/png", "png")),
+            ("/assets/favicon.svg", Route::ok("image/svg+xml", "<svg/>")),
+        ])
+        .await;
+
+        let icons = download_favicon(&server.url("/"), true).await.unwrap();
+
+        // the linked icon isn't listed again by probing
+        assert_eq!(
+            icons,
+            vec![
+                server.url("/assets/favicon.svg"),
+                server.url("/favicon.png")
+            ]
+        );
+        assert!(server.requests().contains(&"HEAD /favicon.ico".to_string()));
+    }
+
+    #[tokio::test]
+    async fn tile_image_is_a_candidate_of_sites_without_favicons() {
+        let server = MockServer::start(vec![(
+            "/app/",
+            Route::ok(
+                "text/html",
+                r#"<meta name="msapplication-TileImage" content="tiles/mstile-144x144.png">"#,
+            ),
+        )])
+        .await;
+
+        let candidates = favicon_candidates(&server.url("/app/"), true)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            candidates,
+            vec![FaviconCandidate::new(
+                server.url("/app/tiles/mstile-144x144.png"),
+                FaviconOrigin::Tile,
+                "144x144",
+            )]
+        );
+        assert!(server
+            .requests()
+            .contains(&"GET /browserconfig.xml".to_string()));
+    }
+
+    #[tokio::test]
+    async fn shortcut_icon_links_are_candidates() {
+        let server = MockServer::start(vec![
+            (
+                "/",
+                Route::ok(
+                    "text/html",
+                    r#"<link rel="SHORTCUT ICON" href="/static/site.ico">"#,
+                ),
+            ),
+            ("/static/site.ico", Route::ok("image/x-icon", "ico")),
+        ])
+        .await;
+
+        let candidates = favicon_candidates(&server.url("/"), true).await.unwrap();
+
+        assert_eq!(
+            candidates,
+            vec![FaviconCandidate::new(
+                server.url("/static/site.ico"),
+                FaviconOrigin::Declared,
+                "",
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn common_ico_paths_are_probed_without_a_root_favicon() {
+        let server = MockServer::start(vec![
+            ("/", Route::ok("text/html", "<title>Example</title>")),
+            ("/static/favicon.ico", Route::ok("image/x-icon", "ico")),
+        ])
+        .await;
+
+        let candidates = favicon_candidates(&server.url("/"), true).await.unwrap();
+
+        assert_eq!(
+            candidates,
+            vec![FaviconCandidate::new(
+                server.url("/static/favicon.ico"),
+                FaviconOrigin::Probed,
+                "",
+            )]
+        );
+        assert!(server.requests().contains(&"HEAD /favicon.ico".to_string()));
+    }
+
+    #[tokio::test]
+    async fn browserconfig_logos_come_after_declared_icons() {
+        let server = MockServer::start(vec![
+            (
+                "/",
+                Route::ok(
+                    "text/html",
+                    r#"<link rel="icon" href="/icon.png" sizes="32x32">
+                    <meta name="msapplication-config" content="/ie/browserconfig.xml">"#,
+                ),
+            ),
+            (
+                "/ie/browserconfig.xml",
+                Route::ok(
+                    "application/xml",
+                    r#"<?xml version="1.0" encoding="utf-8"?>
+                    <browserconfig><msapplication><tile>
+                        <square70x70logo src="small.png"/>
+                        <square150x150logo src="/tiles/medium.png"/>
+                        <wide310x150logo src="wide.png"/>
+                        <TileColor>#da532c</TileColor>
+                    </tile></msapplication></browserconfig>"#,
+                ),
+            ),
+        ])
+        .await;
+
+        let urls: Vec<String> = favicon_candidates(&server.url("/"), true)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|icon| icon.url)
+            .collect();
+
+        assert_eq!(
+            urls,
+            vec![
+                server.url("/icon.png"),
+                server.url("/tiles/medium.png"),
+                server.url("/ie/small.png"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn slow_pages_time_out_without_losing_other_candidates() {
+        let server = MockServer::start(vec![
+            (
+                "/",
+                Route::ok("text/html", r#"<link rel="icon" href="/icon.png">"#)
+                    .slow(Duration::from_secs(5)),
+            ),
+            ("/favicon.ico", Route::ok("image/x-icon", "ico")),
+        ])
+        .await;
+        crate::testing::set_config(AppConfig {
+            request_timeout: 1,
+            ..AppConfig::default()
+        });
+
+        let started = std::time::Instant::now();
+        let icons = download_favicon(&server.url("/"), true).await.unwrap();
+
+        assert_eq!(icons, vec![server.url("/favicon.ico")]);
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    #[tokio::test]
+    async fn too_large_pages_are_skipped_without_losing_other_candidates() {
+        let page = format!(r#"<link rel="icon" href="/icon.png">{}"#, " ".repeat(2048));
+        let browserconfig = r#"<browserconfig><msapplication><tile>
+            <square150x150logo src="/tile.png"/>
+        </tile></msapplication></browserconfig>"#;
+        crate::testing::set_config(AppConfig {
+            max_download_size: 1024,
+            ..AppConfig::default()
+        });
+
+        for page in [
+            Route::ok("text/html", page.clone()),
+            Route::ok("text/html", page.clone()).without_length(),
+        ] {
+            let server = MockServer::start(vec![
+                ("/", page),
+                ("/favicon.ico", Route::ok("image/x-icon", "ico")),
+                (
+                    "/browserconfig.xml",
+                    Route::ok("application/xml", browserconfig),
+                ),
+            ])
+            .await;
+
+            let icons = download_favicon(&server.url("/"), true).await.unwrap();
+
+            assert_eq!(
+                icons,
+                vec![server.url("/favicon.ico"), server.url("/tile.png")]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn favicons_are_cached_by_page_until_refreshed() {
+        let server = MockServer::start(vec![
+            (
+                "/",
+                Route::ok("text/html", r#"<link rel="icon" href="/home.png">"#),
+            ),
+            (
+                "/docs",
+                Route::ok("text/html", r#"<link rel="icon" href="/docs.png">"#),
+            ),
+        ])
+        .await;
+        let home = vec![server.url("/home.png")];
+
+        assert_eq!(
+            download_favicon(&server.url("/"), false).await.unwrap(),
+            home
+        );
+        let asked = server.requests().len();
+
+        // same page, answered from the cache
+        assert_eq!(
+            download_favicon(&server.url("/?a=1"), false).await.unwrap(),
+            home
+        );
+        assert_eq!(server.requests().len(), asked);
+
+        // another page of the same host declares its own icon
+        assert_eq!(
+            download_favicon(&server.url("/docs"), false).await.unwrap(),
+            vec![server.url("/docs.png")]
+        );
+        let asked = server.requests().len();
+
+        assert_eq!(
+            download_favicon(&server.url("/"), true).await.unwrap(),
+            home
+        );
+        assert!(server.requests()[asked..].contains(&"GET /".to_string()));
+    }
+}
@@ -1,21 +1,69 @@
 use crate::{
-    browser::{Browser, BrowserModel, Chromium, Falkon, Firefox},
-    common::{self, database_path, desktop_files_location},
+    browser::{
+        browser_by_id, chromium_app_id, chromium_app_installed, copy_profile,
+        default_chromium_profile, default_firefox_profile, detected_browsers, Browser, BrowserId,
+        BrowserModel, BrowserSource, Chromium, Engine, Falkon, Firefox, LaunchStrategy, Min, Otter,
+        ProfileSource,
+    },
+    common::{
+        self, applications_location, database_path, desktop_file_for, webapp_id, DataPaths,
+        IconFormat, IconSearchOptions, LaunchMethod,
+    },
+    config::AppConfig,
+    error::Error,
     pages::editor::Category,
     LOCALES,
 };
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use freedesktop_desktop_entry::DesktopEntry;
+use once_cell::sync::Lazy;
+use rand::{rng, Rng};
 use serde::{Deserialize, Serialize};
-use tracing::debug;
 use std::{
+    collections::{BTreeMap, HashSet},
     fs::{self},
     io::Read,
+    path::{Path, PathBuf},
+    sync::Mutex,
 };
 use tokio::{
-    fs::{remove_dir_all, remove_file, File},
+    fs::{remove_dir_all, remove_file, rename, File},
     io::AsyncWriteExt,
 };
+use tracing::debug;
+use walkdir::WalkDir;
+
+static RESERVED_CODENAMES: Lazy<Mutex<HashSet<String>>> = Lazy::new(Default::default);
+
+// Picks a codename which isn't used by any installed web app nor reserved by
+// a creation still in progress, so apps with the same name never clobber each other.
+pub fn reserve_codename(name: &str) -> String {
+    let base = common::sanitize_icon_name(name);
+    let mut reserved = RESERVED_CODENAMES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    loop {
+        let codename = format!("{}{}", base, rng().random_range(1000..10000));
+
+        if !reserved.contains(&codename)
+            && !desktop_file_for(&codename).is_ok_and(|path| path.exists())
+        {
+            reserved.insert(codename.clone());
+            return codename;
+        }
+    }
+}
+
+// Ends the reservation of `reserve_codename` once the creation finished or
+// failed, an installed web app is found by its desktop file instead.
+pub fn release_codename(codename: &str) {
+    RESERVED_CODENAMES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .remove(codename);
+}
 
 pub fn webapplauncher_is_valid(icon: &str, name: &str, url: &str) -> bool {
     if !common::url_valid(url) || name.is_empty() || icon.is_empty() || url.is_empty() {
@@ -25,10 +73,166 @@ pub fn webapplauncher_is_valid(icon: &str, name: &str, url: &str) -> bool {
     true
 }
 
+// Window size written as `WIDTHxHEIGHT`, both positive.
+pub fn parse_window_size(size: &str) -> Option<(u32, u32)> {
+    let (width, height) = size.trim().split_once(['x', 'X'])?;
+    let width = width.trim().parse::<u32>().ok()?;
+    let height = height.trim().parse::<u32>().ok()?;
+
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    Some((width, height))
+}
+
+const METADATA_PREFIX: &str = "X-QWA-Meta-";
+// written as `X-QWA-Version` into every desktop file
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Desktop entry keys may only hold `A-Za-z0-9-`.
+pub fn metadata_key_valid(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+// Window position written as `X,Y`, from the top left corner of the
+// whole desktop, so a monitor is chosen by its offset.
+pub fn parse_window_position(position: &str) -> Option<(i32, i32)> {
+    let (x, y) = position.trim().split_once(',')?;
+
+    Some((x.trim().parse::<i32>().ok()?, y.trim().parse::<i32>().ok()?))
+}
+
+// Schemes every browser already handles, a web app taking them over
+// would open every link of the system.
+const RESERVED_SCHEMES: [&str; 8] = [
+    "about",
+    "data",
+    "file",
+    "ftp",
+    "http",
+    "https",
+    "javascript",
+    "mailto",
+];
+
+// Valid url scheme (RFC 3986) which may be handed over to a web app.
+pub fn scheme_allowed(scheme: &str) -> bool {
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+
+    valid && !RESERVED_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str())
+}
+
+// Schemes are kept space separated, `slack:` and `slack://` are accepted too.
+pub fn split_schemes(schemes: &str) -> Vec<String> {
+    schemes
+        .split_whitespace()
+        .map(|scheme| scheme.trim_end_matches("://").trim_end_matches(':'))
+        .filter(|scheme| scheme_allowed(scheme))
+        .map(|scheme| scheme.to_ascii_lowercase())
+        .collect()
+}
+
+// Custom profile location, `~` stands for the home directory. Only absolute
+// paths are accepted, relative ones would depend on where the browser starts.
+pub fn parse_profile_dir(dir: &str) -> Option<PathBuf> {
+    let dir = dir.trim();
+
+    let path = match dir.strip_prefix("~/") {
+        Some(relative) => common::home_dir()?.join(relative),
+        None => PathBuf::from(dir),
+    };
+
+    path.is_absolute().then_some(path)
+}
+
+fn check_profile_dir(dir: &Path) -> Result<(), Error> {
+    let probe = dir.join(".qwa-write-test");
+
+    fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&probe, b""))
+        .and_then(|_| fs::remove_file(&probe))
+        .map_err(|err| anyhow!("profile location {:?} is not writable: {}", dir, err).into())
+}
+
+// Extra urls are kept space separated, urls can't contain a bare space.
+pub fn split_urls(urls: &str) -> Vec<String> {
+    urls.split_whitespace()
+        .filter(|url| common::url_valid(url))
+        .map(String::from)
+        .collect()
+}
+
+// Reads a web app back from its desktop file, for web apps without a record in
+// the database. Desktop files written by older versions lack some `X-QWA-*`
+// keys, those are reconstructed from the file name and the `Exec=` line.
+pub fn load_webapp(codename: &str) -> Result<WebAppLauncher, Error> {
+    load_webapp_with(codename, detected_browsers)
+}
+
+// `browsers` are only looked up when the desktop file's browser isn't found.
+fn load_webapp_with(
+    codename: &str,
+    browsers: impl FnOnce() -> Vec<Browser>,
+) -> Result<WebAppLauncher, Error> {
+    let path = desktop_file_for(codename)?;
+    let entry = DesktopEntry::from_path(&path, Some(&LOCALES))
+        .map_err(|err| anyhow!("can't read {:?}: {}", path, err))?;
+    let exec = entry.exec().unwrap_or_default().to_string();
+    let has_flags = entry
+        .groups
+        .group("Desktop Entry")
+        .is_some_and(|group| group.entry("X-QWA-Isolated").is_some());
+
+    let mut webapp = WebAppLauncher::from(entry);
+
+    if !has_flags {
+        webapp.isolate_profile = exec.contains("--user-data-dir") || exec.contains("--profile");
+        webapp.is_incognito = ["--incognito", "--inprivate", "--private-window"]
+            .iter()
+            .any(|flag| exec.contains(flag));
+    }
+
+    if webapp.codename.is_empty() {
+        webapp.codename = codename.to_string();
+    }
+
+    if webapp.url.is_empty() {
+        webapp.url = exec_url(&exec).unwrap_or_default();
+    }
+
+    if webapp.browser.model.is_none() {
+        let binary = exec.split_whitespace().next().unwrap_or_default();
+
+        if let Some(browser) = browsers()
+            .into_iter()
+            .find(|browser| browser.executable() == Some(binary))
+        {
+            if let Some(entry) = &browser.entry {
+                webapp.appid = entry.appid.clone();
+            }
+            webapp.browser = browser;
+        }
+    }
+
+    if webapp.browser.model.is_none() {
+        return Err(Error::BrowserNotFound(webapp.appid));
+    }
+
+    if !common::url_valid(&webapp.url) {
+        return Err(Error::InvalidUrl(webapp.url));
+    }
+
+    Ok(webapp)
+}
+
 pub fn installed_webapps() -> Vec<WebAppLauncher> {
     let mut webapps = Vec::new();
 
-    if let Ok(entries) = fs::read_dir(database_path("")) {
+    if let Ok(Ok(entries)) = database_path("").map(fs::read_dir) {
         for entry in entries {
             match entry {
                 Ok(entry) => {
@@ -48,207 +252,2715 @@ pub fn installed_webapps() -> Vec<WebAppLauncher> {
         }
     }
 
+    // desktop files whose record is missing are still listed,
+    // so they can be edited and saved again
+    if let Ok(Ok(entries)) = applications_location().map(fs::read_dir) {
+        let prefix = format!("{}.", crate::APP_ID);
+
+        for entry in entries.filter_map(|e| e.ok()) {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+
+            let Some(codename) = file_name
+                .strip_prefix(&prefix)
+                .and_then(|name| name.strip_suffix(".desktop"))
+                .filter(|codename| !codename.is_empty())
+            else {
+                continue;
+            };
+
+            if webapps.iter().any(|webapp| webapp.codename == codename) {
+                continue;
+            }
+
+            match load_webapp(codename) {
+                Ok(webapp) => webapps.push(webapp),
+                Err(err) => tracing::warn!("can't load web app {}: {}", codename, err),
+            }
+        }
+    }
+
     webapps
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
-pub struct WebAppLauncher {
-    pub appid: String,
-    pub codename: String,
-    pub browser: Browser,
-    pub name: String,
-    pub icon: String,
-    pub category: Category,
-    pub url: String,
-    pub custom_parameters: String,
-    pub isolate_profile: bool,
-    pub navbar: bool,
-    pub is_incognito: bool,
+// Profile directories left behind by removed web apps. Only directories created by
+// this app under its own profiles root are considered, never symlinks pointing
+// elsewhere, so profiles shared with the browser are never touched.
+pub fn orphaned_profiles() -> Vec<PathBuf> {
+    let Ok(root) = DataPaths::current().profiles() else {
+        return Vec::new();
+    };
+
+    // without a readable database every profile would look orphaned, and a
+    // record that can't be parsed hides which profile belongs to it
+    let Ok(Ok(records)) = database_path("").map(fs::read_dir) else {
+        return Vec::new();
+    };
+
+    for record in records {
+        let parsed = record
+            .map_err(anyhow::Error::from)
+            .and_then(|record| Ok(fs::read_to_string(record.path())?))
+            .and_then(|content| Ok(ron::from_str::<WebAppLauncher>(&content)?));
+
+        if let Err(err) = parsed {
+            tracing::warn!(
+                "not looking for orphaned profiles, unreadable record: {}",
+                err
+            );
+            return Vec::new();
+        }
+    }
+
+    let webapps = installed_webapps();
+    let mut orphans = Vec::new();
+
+    let Ok(browsers) = fs::read_dir(&root) else {
+        return orphans;
+    };
+
+    for browser in browsers.filter_map(|e| e.ok()) {
+        let Ok(profiles) = fs::read_dir(browser.path()) else {
+            continue;
+        };
+
+        let browser_appid = browser.file_name().to_string_lossy().to_string();
+
+        for profile in profiles.filter_map(|e| e.ok()) {
+            let path = profile.path();
+            let is_dir = profile.file_type().is_ok_and(|kind| kind.is_dir());
+            let codename = profile.file_name().to_string_lossy().to_string();
+
+            let used = webapps.iter().any(|webapp| {
+                webapp.codename == codename
+                    && (webapp.appid == browser_appid
+                        || webapp
+                            .browser
+                            .profile_path()
+                            .is_ok_and(|path| path == browser.path()))
+            });
+
+            if is_dir && !used && path.starts_with(&root) {
+                orphans.push(path);
+            }
+        }
+    }
+
+    orphans
 }
 
-impl From<DesktopEntry> for WebAppLauncher {
-    fn from(value: DesktopEntry) -> Self {
-        let group = value.groups.group("Desktop Entry");
+pub async fn remove_orphaned_profiles() -> usize {
+    let mut removed = 0;
 
-        match group {
-            Some(group) => Self {
-                appid: group
-                    .entry("X-QWA-Browser-Id")
-                    .unwrap_or_default()
-                    .to_string(),
-                codename: group
-                    .entry("X-QWA-Codename")
-                    .unwrap_or_default()
-                    .to_string(),
-                browser: Browser::from_appid(
-                    group
-                        .entry("X-QWA-Browser-Id")
-                        .unwrap_or_default()
-                        .to_string(),
-                ),
-                name: value.name(&LOCALES).unwrap_or_default().to_string(),
-                icon: value.icon().unwrap_or_default().to_string(),
-                category: Category::from(value.categories().unwrap_or_default().concat()),
-                url: group.entry("X-QWA-Url").unwrap_or_default().to_string(),
-                custom_parameters: group
-                    .entry("X-QWA-Parameters")
-                    .unwrap_or_default()
-                    .to_string(),
-                isolate_profile: group
-                    .entry("X-QWA-Isolated")
-                    .unwrap_or_default()
-                    .parse::<bool>()
-                    .unwrap_or_default(),
-                navbar: group
-                    .entry("X-QWA-Navbar")
-                    .unwrap_or_default()
-                    .parse::<bool>()
-                    .unwrap_or_default(),
-                is_incognito: group
-                    .entry("X-QWA-Private")
-                    .unwrap_or_default()
-                    .parse::<bool>()
-                    .unwrap_or_default(),
-            },
-            None => Self {
-                appid: String::new(),
-                codename: String::new(),
-                browser: Browser::default(),
-                name: String::new(),
-                icon: String::new(),
-                category: Category::default(),
-                url: String::new(),
-                custom_parameters: String::new(),
-                isolate_profile: false,
-                navbar: false,
-                is_incognito: false,
-            },
+    for path in orphaned_profiles() {
+        match remove_dir_all(&path).await {
+            Ok(()) => {
+                tracing::info!("removed orphaned profile {:?}", path);
+                removed += 1;
+            }
+            Err(err) => tracing::warn!("can't remove orphaned profile {:?}: {}", path, err),
         }
     }
+
+    removed
 }
 
-impl WebAppLauncher {
-    fn exec_firefox(&self, zen_browser: bool) -> String {
-        let profile_path = self.browser.profile_path.join(&self.codename);
+// Codenames of the web apps touched by `reconcile`.
+#[derive(Debug, Default, Clone)]
+pub struct ReconcileReport {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    // already up to date
+    pub skipped: Vec<String>,
+    pub failed: Vec<String>,
+}
 
-        Firefox::builder(self.browser.exec.clone())
-            .url(self.url.clone())
-            .codename(self.codename.clone())
-            .navbar(self.navbar)
-            .isolated(self.isolate_profile)
-            .profile_path(profile_path)
-            .zen_browser(zen_browser)
-            .private_mode(self.is_incognito)
-            .custom_args(self.custom_parameters.clone())
-            .build()
+impl ReconcileReport {
+    pub fn succeeded(&self) -> bool {
+        self.failed.is_empty()
     }
+}
 
-    fn exec_chromium(&self, microsoft_edge: bool) -> String {
-        let profile_dir = self.browser.profile_path.join(&self.codename);
+// Makes the installed web apps match `manifest`: missing ones are created and
+// changed ones updated. With `prune`, web apps not in the manifest are removed.
+pub async fn reconcile(manifest: &[WebAppLauncher], prune: bool) -> ReconcileReport {
+    reconcile_with(manifest, prune, |appid| {
+        Browser::from_appid(appid.to_string())
+    })
+    .await
+}
 
-        Chromium::builder(self.browser.exec.clone())
-            .url(self.url.clone())
-            .codename(self.codename.clone())
-            .isolated(self.isolate_profile)
-            .profile_path(profile_dir)
-            .ms_edge(microsoft_edge)
-            .private_mode(self.is_incognito)
-            .custom_args(self.custom_parameters.clone())
-            .build()
+async fn reconcile_with(
+    manifest: &[WebAppLauncher],
+    prune: bool,
+    browser_of: impl Fn(&str) -> Browser,
+) -> ReconcileReport {
+    let mut report = ReconcileReport::default();
+    let installed = installed_webapps();
+
+    for app in manifest {
+        let mut app = app.clone();
+        app.browser = browser_of(&app.appid);
+        // older installs are rewritten by this version
+        app.version = VERSION.to_string();
+
+        if app.browser.model.is_none() {
+            tracing::warn!("browser {} of {} isn't installed", app.appid, app.codename);
+            report.failed.push(app.codename);
+            continue;
+        }
+
+        // the browser always follows from the app id
+        let unchanged = installed.iter().any(|existing| {
+            WebAppLauncher {
+                browser: browser_of(&existing.appid),
+                ..existing.clone()
+            } == app
+        });
+
+        if unchanged {
+            report.skipped.push(app.codename);
+            continue;
+        }
+
+        let outcome = match app.upsert().await {
+            Ok(outcome) => app.save_record().map(|_| outcome),
+            Err(err) => Err(err.into()),
+        };
+
+        match outcome {
+            Ok(Upsert::Created) => report.created.push(app.codename),
+            Ok(Upsert::Updated) => report.updated.push(app.codename),
+            Err(err) => {
+                tracing::error!("failed to install {}: {}", app.codename, err);
+                report.failed.push(app.codename);
+            }
+        }
     }
 
-    fn exec_falkon(&self) -> String {
-        let profile_dir = self.browser.profile_path.join(&self.codename);
+    if prune {
+        for app in installed {
+            if manifest
+                .iter()
+                .any(|wanted| wanted.codename == app.codename)
+            {
+                continue;
+            }
 
-        Falkon::builder(self.browser.exec.clone())
-            .url(self.url.clone())
-            .codename(self.codename.clone())
-            .isolated(self.isolate_profile)
-            .profile_path(profile_dir)
-            .private_mode(self.is_incognito)
-            .custom_args(self.custom_parameters.clone())
-            .build()
+            match app.delete().await {
+                Ok(()) => report.removed.push(app.codename),
+                Err(err) => {
+                    tracing::error!("failed to remove {}: {}", app.codename, err);
+                    report.failed.push(app.codename);
+                }
+            }
+        }
     }
 
-    fn exec_string(&self) -> String {
-        if let Some(model) = &self.browser.model {
-            return match model {
-                BrowserModel::Brave => self.exec_chromium(false),
-                BrowserModel::Chrome => self.exec_chromium(false),
-                BrowserModel::Chromium => self.exec_chromium(false),
-                BrowserModel::Cromite => self.exec_chromium(false),
-                BrowserModel::Falkon => self.exec_falkon(),
-                BrowserModel::Firefox => self.exec_firefox(false),
-                BrowserModel::Floorp => self.exec_firefox(false),
-                BrowserModel::Librewolf => self.exec_firefox(false),
-                BrowserModel::MicrosoftEdge => self.exec_chromium(true),
-                BrowserModel::Vivaldi => self.exec_chromium(false),
-                BrowserModel::Waterfox => self.exec_firefox(false),
-                BrowserModel::Zen => self.exec_firefox(true),
-            };
+    report
+}
+
+// Smallest side of a raster icon which still looks sharp in app grids.
+pub const MIN_ICON_SIZE: u32 = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IconIssue {
+    Missing,
+    Broken,
+    LowResolution(u32, u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconAudit {
+    pub codename: String,
+    pub name: String,
+    pub icon: String,
+    pub issue: IconIssue,
+}
+
+// Real resolution of an installed icon. SVGs wrapping a raster image, as saved
+// by older versions, are as sharp as the embedded image.
+async fn icon_resolution(path: &Path) -> Result<Option<(u32, u32)>, IconIssue> {
+    if !path.exists() {
+        return Err(IconIssue::Missing);
+    }
+
+    if path.extension().is_some_and(|extension| extension == "svg") {
+        let content = fs::read_to_string(path).map_err(|_| IconIssue::Broken)?;
+
+        if let Some(raster) = common::extract_wrapped_raster(&content) {
+            let (image, _) = common::decode_still_image(&raster).map_err(|_| IconIssue::Broken)?;
+            return Ok(Some((image.width(), image.height())));
+        }
+
+        // scalable, only checked to be valid
+        return match common::icon_dimensions(path).await {
+            Some(_) => Ok(None),
+            None => Err(IconIssue::Broken),
         };
+    }
 
-        String::new()
+    match common::icon_dimensions(path).await {
+        Some((width, height)) => Ok(Some((width as u32, height as u32))),
+        None => Err(IconIssue::Broken),
     }
+}
 
-    pub async fn create(&self) -> Result<()> {
+// Installed web apps whose icon is missing, can't be decoded or is smaller
+// than `min_size`, so they can be given a better one.
+pub async fn audit_icons(min_size: u32) -> Vec<IconAudit> {
+    let mut audits = Vec::new();
 
-        debug!("create {:?}", self);
-        
-        let entry_location = desktop_files_location(&self.codename);
+    for webapp in installed_webapps() {
+        let path = PathBuf::from(common::icon_path(&webapp.icon));
+
+        let issue = match icon_resolution(&path).await {
+            Ok(Some((width, height))) if width.min(height) < min_size => {
+                IconIssue::LowResolution(width, height)
+            }
+            Ok(_) => continue,
+            Err(issue) => issue,
+        };
+
+        audits.push(IconAudit {
+            codename: webapp.codename,
+            name: webapp.name,
+            icon: webapp.icon,
+            issue,
+        });
+    }
+
+    audits
+}
+
+// Installs the hicolor icons of web apps again from their kept original or their
+// source url, so the sizes added for HiDPI displays are there too. Returns the
+// codenames of the upgraded web apps.
+pub async fn upgrade_icons() -> Vec<String> {
+    let mut upgraded = Vec::new();
 
-        if entry_location.exists() {
-            let _ = std::fs::remove_file(&entry_location);
+    let Ok(hicolor) = common::hicolor_icons_location() else {
+        return upgraded;
+    };
+    let largest = format!(
+        "{}x{}/apps",
+        common::HICOLOR_MAX_SIZE,
+        common::HICOLOR_MAX_SIZE
+    );
+
+    for mut webapp in installed_webapps() {
+        // icons referenced by path aren't part of the hicolor theme
+        if webapp.icon.contains('/')
+            || hicolor
+                .join(&largest)
+                .join(format!("{}.png", webapp.icon))
+                .exists()
+        {
+            continue;
         }
 
-        let mut desktop_entry = String::from("[Desktop Entry]\n");
-        desktop_entry.push_str(&format!("Name={}\n", self.name));
-        desktop_entry.push_str("Comment=Quick Web App\n");
-        desktop_entry.push_str(&format!("Exec={}\n", self.exec_string()));
-        desktop_entry.push_str(&format!("Icon={}\n", self.icon));
-        desktop_entry.push_str("Terminal=false\n");
-        desktop_entry.push_str("Type=Application\n");
-        desktop_entry.push_str(&format!("Categories={}\n", self.category.as_ref()));
-        desktop_entry.push_str("MimeType=text/html;text/xml;application/xhtml_xml;\n");
-        desktop_entry.push_str(&format!(
-            "StartupWMClass=dev.heppen.webapps.{}\n",
-            self.codename
-        ));
-        desktop_entry.push_str("StartupNotify=true\n");
-        desktop_entry.push_str(&format!("X-QWA-Codename={}\n", self.codename));
-        desktop_entry.push_str(&format!("X-QWA-Browser-Id={}\n", self.appid));
-        desktop_entry.push_str(&format!("X-QWA-Url={}\n", self.url));
-        desktop_entry.push_str(&format!("X-QWA-Navbar={}\n", self.navbar));
-        desktop_entry.push_str(&format!("X-QWA-Private={}\n", self.is_incognito));
-        desktop_entry.push_str(&format!("X-QWA-Isolated={}\n", self.isolate_profile));
-        desktop_entry.push_str(&format!("X-QWA-Parameters={}\n", self.custom_parameters));
+        let source = match common::original_icon(&webapp.codename) {
+            Some(path) => path.to_string_lossy().to_string(),
+            None if common::url_valid(&webapp.icon_source)
+                && !IconSearchOptions::from_config().offline =>
+            {
+                webapp.icon_source.clone()
+            }
+            None => {
+                tracing::debug!("no source to upgrade the icon of {}", webapp.codename);
+                continue;
+            }
+        };
 
-        if let Ok(mut f) = File::create(entry_location).await {
-            f.write_all(desktop_entry.as_bytes()).await?;
+        let icon =
+            match common::move_icon(&source, &webapp.codename, IconFormat::HicolorPngSet).await {
+                Ok(icon) => icon,
+                Err(err) => {
+                    tracing::warn!("can't upgrade the icon of {}: {}", webapp.codename, err);
+                    continue;
+                }
+            };
+
+        // hashed icon names change with the content
+        if icon != webapp.icon {
+            webapp.icon = icon;
+
+            let saved = match webapp.create().await {
+                Ok(()) => webapp.save_record(),
+                Err(err) => Err(err.into()),
+            };
+
+            if let Err(err) = saved {
+                tracing::warn!("can't update {}: {}", webapp.codename, err);
+                continue;
+            }
         }
 
-        Ok(())
+        upgraded.push(webapp.codename);
     }
 
-    pub async fn delete(&self) -> Result<()> {
-        let profile_path = self.browser.profile_path.join(&self.codename);
-        remove_file(desktop_files_location(&self.codename)).await?;
-        remove_dir_all(&profile_path).await?;
-        remove_file(database_path(&format!("{}.ron", self.codename))).await?;
+    upgraded
+}
 
-        Ok(())
+pub const BUNDLE_EXTENSION: &str = "qwa";
+const BUNDLE_CONFIG: &str = "webapp.ron";
+const BUNDLE_ICON_DIR: &str = "icon";
+const BUNDLE_PROFILE_DIR: &str = "profile";
+
+// Profile files of Firefox and Chromium based browsers holding logins,
+// cookies, history and open sessions, plus the locks of a running browser.
+// They never leave the machine, even when the profile is bundled.
+const PROFILE_SECRETS: [&str; 26] = [
+    "cookies.sqlite",
+    "formhistory.sqlite",
+    "key3.db",
+    "key4.db",
+    "logins.json",
+    "logins-backup.json",
+    "places.sqlite",
+    "sessionstore.jsonlz4",
+    "sessionstore-backups",
+    "signons.sqlite",
+    "webappsstore.sqlite",
+    "lock",
+    ".parentlock",
+    "parent.lock",
+    "Cookies",
+    "History",
+    "Local State",
+    "Login Data",
+    "Login Data For Account",
+    "Network Persistent State",
+    "Sessions",
+    "Session Storage",
+    "Web Data",
+    "SingletonCookie",
+    "SingletonLock",
+    "SingletonSocket",
+];
+
+fn profile_secret(name: &str) -> bool {
+    // sqlite keeps its journals next to the database
+    let name = ["-wal", "-shm", "-journal"]
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(name);
+
+    PROFILE_SECRETS.contains(&name)
+}
+
+// A single web app moved to another machine, a gzipped tarball holding this
+// as `webapp.ron`, the icon in `icon/` and optionally the profile in `profile/`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Bundle {
+    pub webapp: WebAppLauncher,
+    // `BrowserId` of the browser it used, looked for first on import
+    #[serde(default)]
+    pub browser: Option<String>,
+    // file name of the icon in `icon/`
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub profile: bool,
+}
+
+fn append_file(
+    archive: &mut tar::Builder<GzEncoder<fs::File>>,
+    name: &str,
+    data: &[u8],
+) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    archive.append_data(&mut header, name, data)?;
+
+    Ok(())
+}
+
+// Writes the bundle of `webapp`, the profile without its `PROFILE_SECRETS`.
+fn write_bundle(
+    webapp: &WebAppLauncher,
+    icon: Option<&Path>,
+    profile: Option<&Path>,
+    path: &Path,
+) -> Result<()> {
+    let bundle = Bundle {
+        webapp: webapp.clone(),
+        browser: webapp.browser.id().map(|id| id.as_ref().to_string()),
+        icon: icon
+            .and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().to_string()),
+        profile: profile.is_some(),
+    };
+
+    let mut archive = tar::Builder::new(GzEncoder::new(
+        fs::File::create(path)?,
+        Compression::default(),
+    ));
+
+    append_file(
+        &mut archive,
+        BUNDLE_CONFIG,
+        ron::ser::to_string_pretty(&bundle, ron::ser::PrettyConfig::default())?.as_bytes(),
+    )?;
+
+    if let (Some(icon), Some(name)) = (icon, &bundle.icon) {
+        append_file(
+            &mut archive,
+            &format!("{}/{}", BUNDLE_ICON_DIR, name),
+            &fs::read(icon)?,
+        )?;
+    }
+
+    if let Some(profile) = profile {
+        let entries = WalkDir::new(profile)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|entry| !profile_secret(&entry.file_name().to_string_lossy()))
+            .filter_map(|entry| entry.ok());
+
+        for entry in entries {
+            let Ok(relative) = entry.path().strip_prefix(profile) else {
+                continue;
+            };
+            let name = Path::new(BUNDLE_PROFILE_DIR).join(relative);
+
+            // sockets and links to the running browser are left out
+            if entry.file_type().is_dir() {
+                archive.append_dir(&name, entry.path())?;
+            } else if entry.file_type().is_file() {
+                archive.append_path_with_name(entry.path(), &name)?;
+            }
+        }
     }
+
+    archive.into_inner()?.finish()?;
+
+    Ok(())
 }
 
-//pub async fn launch_webapp(app_id: Arc<String>) -> anyhow::Result<()> {
-//    let proxy = DynamicLauncherProxy::new().await?;
-//
-//    proxy
-//        .launch(
-//            &format!("dev.heppen.webapps.{}.desktop", app_id),
-//            ashpd::desktop::dynamic_launcher::LaunchOptions::default(),
-//        )
-//        .await?;
-//
-//    Ok(())
-//}
+// Unpacks the bundle at `path` into `dir`.
+fn read_bundle(path: &Path, dir: &Path) -> Result<Bundle> {
+    tar::Archive::new(GzDecoder::new(fs::File::open(path)?))
+        .unpack(dir)
+        .map_err(|err| anyhow!("invalid bundle {:?}: {}", path, err))?;
+
+    ron::from_str(&fs::read_to_string(dir.join(BUNDLE_CONFIG))?)
+        .map_err(|err| anyhow!("invalid bundle {:?}: {}", path, err))
+}
+
+pub fn export_bundle(codename: &str, path: &Path, with_profile: bool) -> Result<()> {
+    let Some(webapp) = installed_webapps()
+        .into_iter()
+        .find(|webapp| webapp.codename == codename)
+    else {
+        bail!("no web app {}", codename);
+    };
+
+    export_webapp(&webapp, path, with_profile)
+}
+
+fn export_webapp(webapp: &WebAppLauncher, path: &Path, with_profile: bool) -> Result<()> {
+    let codename = &webapp.codename;
+    let icon_path = PathBuf::from(common::icon_path(&webapp.icon));
+    let icon = if icon_path.is_file() {
+        Some(icon_path.as_path())
+    } else {
+        tracing::warn!("icon {:?} of {} not bundled", icon_path, codename);
+        None
+    };
+
+    let profile_path = webapp.profile_path()?;
+    let profile = if !with_profile {
+        None
+    } else if webapp.isolate_profile
+        && webapp.profile_source != ProfileSource::ReuseDefault
+        && profile_path.is_dir()
+    {
+        Some(profile_path.as_path())
+    } else {
+        bail!("{} has no isolated profile to bundle", codename);
+    };
+
+    write_bundle(webapp, icon, profile, path)
+}
+
+// The same browser is preferred, then any installation of the same model.
+fn bundle_browser(bundle: &Bundle, browsers: impl FnOnce() -> Vec<Browser>) -> Option<Browser> {
+    if let Some(browser) = bundle
+        .browser
+        .as_deref()
+        .and_then(BrowserId::parse)
+        .and_then(|id| browser_by_id(&id))
+    {
+        return Some(browser);
+    }
+
+    let browser = Browser::from_appid(bundle.webapp.appid.clone());
+    if browser.model.is_some() {
+        return Some(browser);
+    }
+
+    browsers()
+        .into_iter()
+        .find(|browser| browser.model.is_some() && browser.model == bundle.webapp.browser.model)
+}
+
+pub async fn import_bundle(path: &Path) -> Result<WebAppLauncher, Error> {
+    // removed when dropped, whether the import succeeded or not
+    let dir = tempfile::Builder::new()
+        .prefix(&format!("{}-bundle-", crate::APP_ID))
+        .tempdir()?;
+
+    import_bundle_from(path, dir.path(), detected_browsers).await
+}
+
+// `browsers` are only looked up when the bundle's browser isn't found.
+async fn import_bundle_from(
+    path: &Path,
+    dir: &Path,
+    browsers: impl FnOnce() -> Vec<Browser>,
+) -> Result<WebAppLauncher, Error> {
+    let bundle = read_bundle(path, dir)?;
+
+    let browser = bundle_browser(&bundle, browsers)
+        .ok_or_else(|| Error::BrowserNotFound(bundle.webapp.browser.name.clone()))?;
+    let mut webapp = bundle.webapp.clone();
+
+    if let Some(entry) = &browser.entry {
+        webapp.appid = entry.appid.clone();
+    }
+    webapp.browser = browser;
+
+    // the bundle can't be trusted with paths, a codename with `..` or a
+    // custom profile location would write outside of the app's directories
+    webapp.codename = reserve_codename(&webapp.name);
+    webapp.profile_dir = None;
+
+    let result = install_bundle(&mut webapp, &bundle, dir).await;
+    release_codename(&webapp.codename);
+
+    result.map(|()| webapp)
+}
+
+async fn install_bundle(
+    webapp: &mut WebAppLauncher,
+    bundle: &Bundle,
+    dir: &Path,
+) -> Result<(), Error> {
+    if bundle.profile {
+        copy_profile(&dir.join(BUNDLE_PROFILE_DIR), &webapp.profile_path()?);
+    }
+
+    if let Some(name) = &bundle.icon {
+        let file_name = Path::new(&name)
+            .file_name()
+            .ok_or_else(|| anyhow!("invalid icon name {:?}", name))?;
+        let icon = dir.join(BUNDLE_ICON_DIR).join(file_name);
+
+        webapp.icon = common::move_icon(
+            &icon.to_string_lossy(),
+            &webapp.codename,
+            AppConfig::config().icon_format,
+        )
+        .await?;
+    }
+
+    webapp.create().await?;
+    webapp.save_record()?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Upsert {
+    Created,
+    Updated,
+}
+
+// Installed web app opening the same page in the same browser, if any.
+pub fn find_duplicate(url: &str, browser_appid: &str) -> Option<WebAppLauncher> {
+    let url = common::normalized_url(url)?;
+
+    installed_webapps().into_iter().find(|launcher| {
+        launcher.appid == browser_appid
+            && common::normalized_url(&launcher.url).as_ref() == Some(&url)
+    })
+}
+
+// Installed web app opening the same page in the same browser packaged
+// differently, native against flatpak. Their profiles are kept apart, so
+// logins of one aren't seen by the other.
+pub fn find_packaging_conflict(url: &str, browser: &Browser) -> Option<WebAppLauncher> {
+    packaging_conflict_in(url, browser, installed_webapps())
+}
+
+fn packaging_conflict_in(
+    url: &str,
+    browser: &Browser,
+    webapps: Vec<WebAppLauncher>,
+) -> Option<WebAppLauncher> {
+    let url = common::normalized_url(url)?;
+    let is_flatpak = |browser: &Browser| {
+        matches!(
+            browser.source,
+            BrowserSource::Flatpak | BrowserSource::SystemFlatpak
+        )
+    };
+
+    browser.model.as_ref()?;
+
+    webapps.into_iter().find(|launcher| {
+        launcher.browser.model == browser.model
+            && is_flatpak(&launcher.browser) != is_flatpak(browser)
+            && common::normalized_url(&launcher.url).as_ref() == Some(&url)
+    })
+}
+
+// Desktop file of another site specific browser tool, like webapp-manager or ICE,
+// opening the same page as a web app. Only reported, never removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignWebApp {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+// Page opened by an `Exec=` line, either the `--app` url of Chromium based
+// browsers or the first http(s) url argument.
+pub fn exec_url(exec: &str) -> Option<String> {
+    exec.split_whitespace()
+        .map(|arg| arg.trim_matches(|c| c == '"' || c == '\''))
+        .map(|arg| {
+            arg.strip_prefix("--app=")
+                .map_or(arg, |url| url.trim_matches(|c| c == '"' || c == '\''))
+        })
+        .find(|arg| {
+            ["http://", "https://", "file://"]
+                .iter()
+                .any(|scheme| arg.starts_with(scheme))
+        })
+        .map(unescape_exec_arg)
+}
+
+// Reverses the escaping of `common::escape_exec` and `common::quote_exec_arg`.
+fn unescape_exec_arg(arg: &str) -> String {
+    let arg = arg.replace("%%", "%");
+    let mut unescaped = String::new();
+    let mut chars = arg.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+
+    unescaped
+}
+
+// Desktop files not created by this app which open the same page.
+pub fn foreign_webapps(url: &str) -> Vec<ForeignWebApp> {
+    foreign_webapps_in(url, common::fd_entries())
+}
+
+fn foreign_webapps_in(url: &str, entries: Vec<DesktopEntry>) -> Vec<ForeignWebApp> {
+    let Some(url) = common::normalized_url(url) else {
+        return Vec::new();
+    };
+
+    entries
+        .into_iter()
+        .filter(|entry| !entry.appid.starts_with(crate::APP_ID))
+        .filter(|entry| {
+            entry
+                .exec()
+                .and_then(exec_url)
+                .and_then(|exec_url| common::normalized_url(&exec_url))
+                .is_some_and(|exec_url| exec_url == url)
+        })
+        .map(|entry| ForeignWebApp {
+            name: entry.name(&LOCALES).unwrap_or_default().to_string(),
+            path: entry.path.clone(),
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WebAppLauncher {
+    pub appid: String,
+    pub codename: String,
+    pub browser: Browser,
+    pub name: String,
+    #[serde(default)]
+    pub window_title: String,
+    pub icon: String,
+    // where the icon was originally taken from, empty when unknown
+    #[serde(default)]
+    pub icon_source: String,
+    pub category: Category,
+    pub url: String,
+    // opened as additional tabs, where the browser supports it
+    #[serde(default)]
+    pub extra_urls: Vec<String>,
+    pub custom_parameters: String,
+    pub isolate_profile: bool,
+    pub navbar: bool,
+    // links the site opens in a new window become tabs of the web app's
+    // window, where the browser supports it
+    #[serde(default)]
+    pub new_windows_as_tabs: bool,
+    pub is_incognito: bool,
+    #[serde(default)]
+    pub pwa: bool,
+    #[serde(default)]
+    pub profile_source: ProfileSource,
+    #[serde(default)]
+    pub single_window: bool,
+    #[serde(default)]
+    pub badges: bool,
+    #[serde(default)]
+    pub window_size: Option<(u32, u32)>,
+    #[serde(default)]
+    pub maximized: bool,
+    #[serde(default)]
+    pub window_position: Option<(i32, i32)>,
+    // `x-scheme-handler` the web app is registered for, like `slack`
+    #[serde(default)]
+    pub url_schemes: Vec<String>,
+    // custom location of the isolated profile, e.g. on another disk or a tmpfs
+    #[serde(default)]
+    pub profile_dir: Option<PathBuf>,
+    // version of this app which last wrote the desktop file, for migrations
+    #[serde(default)]
+    pub version: String,
+    // arbitrary keys written as `X-QWA-Meta-<key>`
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl From<DesktopEntry> for WebAppLauncher {
+    fn from(value: DesktopEntry) -> Self {
+        let group = value.groups.group("Desktop Entry");
+        let text = |key: &str| {
+            common::unescape_desktop_value(
+                group.and_then(|group| group.entry(key)).unwrap_or_default(),
+            )
+        };
+
+        match group {
+            Some(group) => Self {
+                appid: text("X-QWA-Browser-Id"),
+                codename: text("X-QWA-Codename"),
+                // launchers written before `X-QWA-Browser` only have the desktop entry id
+                browser: group
+                    .entry("X-QWA-Browser")
+                    .and_then(BrowserId::parse)
+                    .and_then(|id| browser_by_id(&id))
+                    .unwrap_or_else(|| Browser::from_appid(text("X-QWA-Browser-Id"))),
+                name: common::unescape_desktop_value(&value.name(&LOCALES).unwrap_or_default()),
+                window_title: text("X-QWA-Window-Title"),
+                icon: common::unescape_desktop_value(value.icon().unwrap_or_default()),
+                icon_source: text("X-QWA-Icon-Source"),
+                category: Category::from(
+                    value
+                        .categories()
+                        .and_then(|categories| categories.first().map(|c| c.to_string()))
+                        .unwrap_or_default(),
+                ),
+                url: text("X-QWA-Url"),
+                extra_urls: split_urls(&text("X-QWA-Extra-Urls")),
+                custom_parameters: text("X-QWA-Parameters"),
+                isolate_profile: group
+                    .entry("X-QWA-Isolated")
+                    .unwrap_or_default()
+                    .parse::<bool>()
+                    .unwrap_or_default(),
+                navbar: group
+                    .entry("X-QWA-Navbar")
+                    .unwrap_or_default()
+                    .parse::<bool>()
+                    .unwrap_or_default(),
+                new_windows_as_tabs: group
+                    .entry("X-QWA-New-Windows-As-Tabs")
+                    .unwrap_or_default()
+                    .parse::<bool>()
+                    .unwrap_or_default(),
+                is_incognito: group
+                    .entry("X-QWA-Private")
+                    .unwrap_or_default()
+                    .parse::<bool>()
+                    .unwrap_or_default(),
+                pwa: group
+                    .entry("X-QWA-Pwa")
+                    .unwrap_or_default()
+                    .parse::<bool>()
+                    .unwrap_or_default(),
+                profile_source: ProfileSource::from(&text("X-QWA-Profile-Source")),
+                single_window: group
+                    .entry("X-QWA-Single-Window")
+                    .unwrap_or_default()
+                    .parse::<bool>()
+                    .unwrap_or_default(),
+                badges: group
+                    .entry("X-QWA-Badges")
+                    .unwrap_or_default()
+                    .parse::<bool>()
+                    .unwrap_or_default(),
+                window_size: parse_window_size(&text("X-QWA-Window-Size")),
+                maximized: group
+                    .entry("X-QWA-Maximized")
+                    .unwrap_or_default()
+                    .parse::<bool>()
+                    .unwrap_or_default(),
+                window_position: parse_window_position(&text("X-QWA-Window-Position")),
+                url_schemes: split_schemes(&text("X-QWA-Schemes")),
+                profile_dir: parse_profile_dir(&text("X-QWA-Profile-Dir")),
+                version: text("X-QWA-Version"),
+                metadata: group
+                    .0
+                    .iter()
+                    .filter_map(|(key, (value, _))| {
+                        let key = key.strip_prefix(METADATA_PREFIX)?;
+                        Some((key.to_string(), common::unescape_desktop_value(value)))
+                    })
+                    .collect(),
+            },
+            None => Self {
+                appid: String::new(),
+                codename: String::new(),
+                browser: Browser::default(),
+                name: String::new(),
+                window_title: String::new(),
+                icon: String::new(),
+                icon_source: String::new(),
+                category: Category::default(),
+                url: String::new(),
+                extra_urls: Vec::new(),
+                custom_parameters: String::new(),
+                isolate_profile: false,
+                navbar: false,
+                new_windows_as_tabs: false,
+                is_incognito: false,
+                pwa: false,
+                profile_source: ProfileSource::default(),
+                single_window: false,
+                badges: false,
+                window_size: None,
+                maximized: false,
+                window_position: None,
+                url_schemes: Vec::new(),
+                profile_dir: None,
+                version: String::new(),
+                metadata: BTreeMap::new(),
+            },
+        }
+    }
+}
+
+impl WebAppLauncher {
+    // Relaunching a browser running on its own profile only raises
+    // or reuses that instance, so panels may treat it as one window.
+    fn single_main_window(&self) -> bool {
+        let single_instance = match &self.browser.model {
+            Some(model) => model.info().single_instance,
+            None => false,
+        };
+
+        self.single_window && self.isolate_profile && single_instance
+    }
+
+    fn warn_extra_urls(&self) {
+        if !self.extra_urls.is_empty() {
+            tracing::warn!(
+                "{} can only open a single url, ignoring {} more",
+                self.browser.name,
+                self.extra_urls.len()
+            );
+        }
+    }
+
+    fn warn_window_options(&self, size_supported: bool, maximized_supported: bool) {
+        if self.maximized && !maximized_supported {
+            tracing::warn!("{} can't start maximized, ignoring", self.browser.name);
+        }
+
+        // only Chromium takes a position, and it's applied on X11 alone
+        if self.window_position.is_some() {
+            tracing::warn!(
+                "{} can't set the window position, ignoring",
+                self.browser.name
+            );
+        }
+
+        if self.window_size.is_some() && !size_supported {
+            tracing::warn!("{} can't set the window size, ignoring", self.browser.name);
+        }
+    }
+
+    fn exec_firefox(&self, zen_browser: bool) -> Result<String, Error> {
+        self.warn_window_options(true, false);
+
+        let profile_path = self.profile_path()?;
+        let base_profile = match self.profile_source {
+            ProfileSource::New => None,
+            ProfileSource::CopyDefault | ProfileSource::ReuseDefault => {
+                default_firefox_profile().map(|profile| profile.path)
+            }
+        };
+
+        Ok(Firefox::builder(self.browser.exec.clone())
+            .url(self.url.clone())
+            .extra_urls(self.extra_urls.clone())
+            .codename(self.codename.clone())
+            .window_title(self.window_title.clone())
+            .window_size(self.window_size)
+            .navbar(self.navbar)
+            .new_windows_as_tabs(self.new_windows_as_tabs)
+            .isolated(self.isolate_profile && self.profile_source != ProfileSource::ReuseDefault)
+            .base_profile(base_profile)
+            .reuse_profile(self.profile_source == ProfileSource::ReuseDefault)
+            .profile_path(profile_path)
+            .zen_browser(zen_browser)
+            .private_mode(self.is_incognito)
+            .custom_args(self.custom_parameters.clone())
+            .build())
+    }
+
+    fn exec_chromium(&self, microsoft_edge: bool) -> Result<String, Error> {
+        let profile_dir = self.profile_path()?;
+        let version = if self.pwa {
+            self.browser.version()
+        } else {
+            None
+        };
+        let mut strategy =
+            LaunchStrategy::select(self.pwa, self.browser.model.as_ref(), version.as_deref());
+
+        if self.pwa && strategy == LaunchStrategy::App {
+            tracing::info!(
+                "{:?} doesn't support installing web apps, falling back to --app",
+                version
+            );
+        }
+
+        let isolated = self.isolate_profile && self.profile_source != ProfileSource::ReuseDefault;
+
+        // the app is installed from the browser's menu, until then it opens with `--app`
+        if strategy == LaunchStrategy::Pwa {
+            let profile = match (isolated, &self.browser.model) {
+                (true, _) => Some(profile_dir.join("Default")),
+                (false, Some(model)) => default_chromium_profile(model, &self.appid),
+                (false, None) => None,
+            };
+            let app_id = chromium_app_id(&self.url);
+
+            if !profile.is_some_and(|profile| chromium_app_installed(&profile, &app_id)) {
+                tracing::info!("{} isn't installed yet, starting it with --app", self.url);
+                strategy = LaunchStrategy::App;
+            }
+        }
+
+        // installed web apps are single window, there are no tabs to open
+        let extra_urls = if strategy == LaunchStrategy::Pwa {
+            self.warn_extra_urls();
+            Vec::new()
+        } else {
+            self.extra_urls.clone()
+        };
+
+        // the default profile itself is the shared profile, there's no
+        // other way to get all of its extensions
+        let base_profile = match (self.profile_source, &self.browser.model) {
+            (ProfileSource::CopyDefault, Some(model)) => {
+                default_chromium_profile(model, &self.appid)
+            }
+            _ => None,
+        };
+
+        Ok(Chromium::builder(self.browser.exec.clone())
+            .url(self.url.clone())
+            .extra_urls(extra_urls)
+            .strategy(strategy)
+            .codename(self.codename.clone())
+            .isolated(isolated)
+            .base_profile(base_profile)
+            .profile_path(profile_dir)
+            .ms_edge(microsoft_edge)
+            .private_mode(self.is_incognito)
+            .badges(self.badges)
+            .window_size(self.window_size)
+            .window_position(self.window_position)
+            .maximized(self.maximized)
+            .custom_args(self.custom_parameters.clone())
+            .build())
+    }
+
+    fn exec_falkon(&self) -> Result<String, Error> {
+        self.warn_extra_urls();
+        self.warn_window_options(false, false);
+        let profile_dir = self.profile_path()?;
+
+        Ok(Falkon::builder(self.browser.exec.clone())
+            .url(self.url.clone())
+            .codename(self.codename.clone())
+            .isolated(self.isolate_profile)
+            .profile_path(profile_dir)
+            .private_mode(self.is_incognito)
+            .custom_args(self.custom_parameters.clone())
+            .build())
+    }
+
+    fn exec_otter(&self) -> Result<String, Error> {
+        self.warn_extra_urls();
+        self.warn_window_options(false, false);
+        let profile_dir = self.profile_path()?;
+
+        Ok(Otter::builder(self.browser.exec.clone())
+            .url(self.url.clone())
+            .isolated(self.isolate_profile)
+            .profile_path(profile_dir)
+            .private_mode(self.is_incognito)
+            .custom_args(self.custom_parameters.clone())
+            .build())
+    }
+
+    fn exec_min(&self) -> Result<String, Error> {
+        self.warn_extra_urls();
+        self.warn_window_options(false, false);
+        Ok(Min::builder(self.browser.exec.clone())
+            .url(self.url.clone())
+            .custom_args(self.custom_parameters.clone())
+            .build())
+    }
+
+    // Command starting the installed web app. Without `gtk-launch` the
+    // `Exec=` line is run directly, inside flatpak both run on the host.
+    pub fn launch_command(&self, method: LaunchMethod) -> Result<Vec<String>, Error> {
+        self.launch_command_with(
+            method,
+            common::is_sandboxed(),
+            common::program_in_path("gtk-launch"),
+        )
+    }
+
+    fn launch_command_with(
+        &self,
+        method: LaunchMethod,
+        sandboxed: bool,
+        gtk_launch: bool,
+    ) -> Result<Vec<String>, Error> {
+        let mut command = Vec::new();
+
+        if sandboxed {
+            command.extend(["flatpak-spawn".to_string(), "--host".to_string()]);
+        }
+
+        if method == LaunchMethod::DesktopEntry && gtk_launch {
+            command.push("gtk-launch".to_string());
+            command.push(format!("{}.desktop", webapp_id(&self.codename)));
+        } else {
+            command.extend(["sh".to_string(), "-c".to_string(), self.exec_string()?]);
+        }
+
+        Ok(command)
+    }
+
+    pub fn launch(&self) -> Result<()> {
+        let command = self.launch_command(AppConfig::config().launch_method)?;
+        debug!("launching {}: {:?}", self.codename, command);
+
+        let mut child = std::process::Command::new(&command[0])
+            .args(&command[1..])
+            .spawn()?;
+
+        // reaped in the background, the web app may run longer than this app
+        std::thread::spawn(move || child.wait());
+
+        Ok(())
+    }
+
+    // The profile gets its own directory inside a custom location, so deleting
+    // the web app never removes anything else stored there.
+    fn profile_path(&self) -> Result<PathBuf, Error> {
+        match &self.profile_dir {
+            Some(dir) => Ok(dir.join(&self.codename)),
+            None => Ok(self.browser.profile_path()?.join(&self.codename)),
+        }
+    }
+
+    // The class given to the app windows by `--class` or `--wmclass`,
+    // `StartupWMClass` of the desktop file has to be the same.
+    pub fn wm_class(&self) -> Option<String> {
+        self.browser
+            .model
+            .as_ref()
+            .filter(|model| model.sets_wm_class())
+            .map(|_| webapp_id(&self.codename))
+    }
+
+    fn exec_string(&self) -> Result<String, Error> {
+        if let Some(model) = &self.browser.model {
+            return match model.info().engine {
+                Engine::Chromium => self.exec_chromium(*model == BrowserModel::MicrosoftEdge),
+                Engine::Gecko => self.exec_firefox(*model == BrowserModel::Zen),
+                Engine::Electron => self.exec_min(),
+                Engine::QtWebEngine if *model == BrowserModel::Otter => self.exec_otter(),
+                Engine::QtWebEngine => self.exec_falkon(),
+            };
+        };
+
+        Ok(String::new())
+    }
+
+    pub async fn create(&self) -> Result<(), Error> {
+        debug!("create {:?}", self);
+
+        if !common::url_valid(&self.url) {
+            return Err(Error::InvalidUrl(self.url.clone()));
+        }
+
+        if self.browser.model.is_none() {
+            return Err(Error::BrowserNotFound(self.appid.clone()));
+        }
+
+        DataPaths::current().check_writable()?;
+
+        if self.isolate_profile {
+            match &self.profile_dir {
+                Some(dir) => check_profile_dir(dir)?,
+                None => self.browser.check_profile_root()?,
+            }
+        }
+
+        let entry_location = desktop_file_for(&self.codename)?;
+        let desktop_entry = self.desktop_entry()?;
+
+        // launchers skip entries they can't parse, better to fail here
+        if let Err(err) = DesktopEntry::from_str(&entry_location, &desktop_entry, Some(&LOCALES)) {
+            return Err(anyhow!(
+                "generated desktop entry for {} is invalid: {}",
+                self.codename,
+                err
+            )
+            .into());
+        }
+
+        tracing::info!("writing desktop file to {:?}", entry_location);
+
+        // write next to the final location and rename it, so launchers
+        // and concurrent creations never see a partially written file
+        let temp_location =
+            entry_location.with_extension(format!("desktop.{}.tmp", rng().random::<u32>()));
+
+        let written = async {
+            let mut f = File::create(&temp_location).await?;
+            f.write_all(desktop_entry.as_bytes()).await?;
+            f.sync_all().await?;
+            rename(&temp_location, &entry_location).await
+        }
+        .await;
+
+        if let Err(err) = written {
+            tracing::error!("can't write {:?}: {}", entry_location, err);
+            // the temp file may be there when writing or renaming failed
+            let _ = remove_file(&temp_location).await;
+            return Err(err.into());
+        }
+
+        if !self.url_schemes.is_empty() {
+            self.register_schemes().await;
+        }
+
+        Ok(())
+    }
+
+    // Content of the desktop file. Every value is escaped, so a newline in
+    // an url or a path can't end the value and start another key.
+    pub fn desktop_entry(&self) -> Result<String, Error> {
+        let mut desktop_entry = String::from("[Desktop Entry]\n");
+        desktop_entry.push_str(&format!(
+            "Name={}\n",
+            common::escape_desktop_value(&self.name)
+        ));
+        desktop_entry.push_str("Comment=Quick Web App\n");
+        // launchers hide the web app once its browser is uninstalled
+        if let Some(binary) = self.browser.try_exec() {
+            desktop_entry.push_str(&format!(
+                "TryExec={}\n",
+                common::escape_desktop_value(binary)
+            ));
+        }
+        desktop_entry.push_str(&format!(
+            "Exec={}\n",
+            common::escape_exec(&self.exec_string()?)
+        ));
+        desktop_entry.push_str(&format!(
+            "Icon={}\n",
+            common::escape_desktop_value(&self.icon)
+        ));
+        desktop_entry.push_str("Terminal=false\n");
+        desktop_entry.push_str("Type=Application\n");
+        desktop_entry.push_str(&format!("Categories={};", self.category.as_ref()));
+        if AppConfig::config().menu_folder {
+            desktop_entry.push_str(&format!("{};", common::MENU_CATEGORY));
+        }
+        desktop_entry.push('\n');
+        desktop_entry.push_str("MimeType=text/html;text/xml;application/xhtml_xml;");
+        for scheme in &self.url_schemes {
+            desktop_entry.push_str(&format!("x-scheme-handler/{};", scheme));
+        }
+        desktop_entry.push('\n');
+        if let Some(wm_class) = self.wm_class() {
+            desktop_entry.push_str(&format!("StartupWMClass={}\n", wm_class));
+        }
+        desktop_entry.push_str("StartupNotify=true\n");
+        if self.single_main_window() {
+            desktop_entry.push_str("SingleMainWindow=true\n");
+        }
+        if common::dbus_service_installed(&self.codename) {
+            desktop_entry.push_str("DBusActivatable=true\n");
+        }
+        desktop_entry.push_str(&format!(
+            "X-QWA-Codename={}\n",
+            common::escape_desktop_value(&self.codename)
+        ));
+        desktop_entry.push_str(&format!(
+            "X-QWA-Browser-Id={}\n",
+            common::escape_desktop_value(&self.appid)
+        ));
+        if let Some(id) = self.browser.id() {
+            desktop_entry.push_str(&format!(
+                "X-QWA-Browser={}\n",
+                common::escape_desktop_value(id.as_ref())
+            ));
+        }
+        desktop_entry.push_str(&format!(
+            "X-QWA-Url={}\n",
+            common::escape_desktop_value(&self.url)
+        ));
+        desktop_entry.push_str(&format!(
+            "X-QWA-Extra-Urls={}\n",
+            common::escape_desktop_value(&self.extra_urls.join(" "))
+        ));
+        desktop_entry.push_str(&format!(
+            "X-QWA-Icon-Source={}\n",
+            common::escape_desktop_value(&self.icon_source)
+        ));
+        desktop_entry.push_str(&format!(
+            "X-QWA-Window-Title={}\n",
+            common::escape_desktop_value(&self.window_title)
+        ));
+        desktop_entry.push_str(&format!("X-QWA-Navbar={}\n", self.navbar));
+        desktop_entry.push_str(&format!(
+            "X-QWA-New-Windows-As-Tabs={}\n",
+            self.new_windows_as_tabs
+        ));
+        desktop_entry.push_str(&format!("X-QWA-Private={}\n", self.is_incognito));
+        desktop_entry.push_str(&format!("X-QWA-Pwa={}\n", self.pwa));
+        desktop_entry.push_str(&format!(
+            "X-QWA-Profile-Source={}\n",
+            self.profile_source.as_ref()
+        ));
+        desktop_entry.push_str(&format!("X-QWA-Isolated={}\n", self.isolate_profile));
+        desktop_entry.push_str(&format!("X-QWA-Single-Window={}\n", self.single_window));
+        desktop_entry.push_str(&format!("X-QWA-Badges={}\n", self.badges));
+        desktop_entry.push_str(&format!(
+            "X-QWA-Window-Size={}\n",
+            self.window_size
+                .map(|(width, height)| format!("{}x{}", width, height))
+                .unwrap_or_default()
+        ));
+        desktop_entry.push_str(&format!("X-QWA-Maximized={}\n", self.maximized));
+        desktop_entry.push_str(&format!(
+            "X-QWA-Window-Position={}\n",
+            self.window_position
+                .map(|(x, y)| format!("{},{}", x, y))
+                .unwrap_or_default()
+        ));
+        desktop_entry.push_str(&format!(
+            "X-QWA-Schemes={}\n",
+            common::escape_desktop_value(&self.url_schemes.join(" "))
+        ));
+        desktop_entry.push_str(&format!(
+            "X-QWA-Profile-Dir={}\n",
+            common::escape_desktop_value(
+                &self
+                    .profile_dir
+                    .as_ref()
+                    .map(|dir| dir.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            )
+        ));
+        desktop_entry.push_str(&format!(
+            "X-QWA-Parameters={}\n",
+            common::escape_desktop_value(&self.custom_parameters)
+        ));
+        desktop_entry.push_str(&format!("X-QWA-Version={}\n", VERSION));
+        for (key, value) in &self.metadata {
+            if !metadata_key_valid(key) {
+                tracing::warn!(
+                    "skipping invalid metadata key {:?} of {}",
+                    key,
+                    self.codename
+                );
+                continue;
+            }
+
+            desktop_entry.push_str(&format!(
+                "{}{}={}\n",
+                METADATA_PREFIX,
+                key,
+                common::escape_desktop_value(value)
+            ));
+        }
+
+        Ok(desktop_entry)
+    }
+
+    // Makes the web app the default handler of its schemes. The link itself
+    // isn't passed on, the web app is only opened.
+    async fn register_schemes(&self) {
+        let desktop_file = format!("{}.desktop", webapp_id(&self.codename));
+
+        let mut commands = Vec::new();
+        match applications_location() {
+            Ok(location) => commands.push(vec![
+                "update-desktop-database".to_string(),
+                location.to_string_lossy().to_string(),
+            ]),
+            Err(err) => tracing::warn!("can't update the desktop database: {}", err),
+        }
+        commands.extend(self.url_schemes.iter().map(|scheme| {
+            vec![
+                "xdg-mime".to_string(),
+                "default".to_string(),
+                desktop_file.clone(),
+                format!("x-scheme-handler/{}", scheme),
+            ]
+        }));
+
+        for command in commands {
+            let status = tokio::process::Command::new(&command[0])
+                .args(&command[1..])
+                .status()
+                .await;
+
+            match status {
+                Ok(status) if status.success() => (),
+                Ok(status) => tracing::warn!("{} exited with {}", command.join(" "), status),
+                Err(err) => tracing::warn!("can't run {}: {}", command[0], err),
+            }
+        }
+    }
+
+    // Creating the same codename again rewrites the existing desktop file in place,
+    // so it's safe to repeat for web apps managed from a script or a config.
+    pub async fn upsert(&self) -> Result<Upsert, Error> {
+        let existed = desktop_file_for(&self.codename)?.exists();
+
+        self.create().await?;
+
+        Ok(if existed {
+            Upsert::Updated
+        } else {
+            Upsert::Created
+        })
+    }
+
+    // Record of the web app in the database, read back by `installed_webapps`.
+    pub fn save_record(&self) -> Result<()> {
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(database_path(&format!("{}.ron", self.codename))?, content)?;
+
+        Ok(())
+    }
+
+    // Files already gone count as removed. Web apps on the browser's own
+    // profile never had a profile directory to remove.
+    pub async fn delete(&self) -> Result<()> {
+        let removed = |result: std::io::Result<()>| match result {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        };
+
+        removed(remove_file(desktop_file_for(&self.codename)?).await)?;
+
+        match self.profile_path() {
+            Ok(profile_path) => removed(remove_dir_all(&profile_path).await)?,
+            // the browser is gone, so is the way to its profiles root
+            Err(err) => tracing::warn!("not removing the profile of {}: {}", self.codename, err),
+        }
+
+        removed(remove_file(database_path(&format!("{}.ron", self.codename))?).await)?;
+        common::remove_original_icons(&self.codename);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn webapp() -> WebAppLauncher {
+        WebAppLauncher {
+            codename: "Example1234".to_string(),
+            browser: Browser {
+                model: Some(BrowserModel::Min),
+                exec: "min".to_string(),
+                name: "Min".to_string(),
+                ..Default::default()
+            },
+            name: "Example\nName=Injected".to_string(),
+            window_title: "Tab\tand \\ backslash".to_string(),
+            icon: "/tmp/icons/ex\\ample.png".to_string(),
+            icon_source: "https://example.com/icon.png\nX-QWA-Url=https://evil.com".to_string(),
+            url: "https://example.com/path?q=a%20b\nExec=rm".to_string(),
+            extra_urls: vec!["https://example.com/other".to_string()],
+            custom_parameters: "--flag=\"a b\"".to_string(),
+            profile_dir: Some(PathBuf::from("/tmp/profiles/with\nnewline")),
+            metadata: BTreeMap::from([("Owner".to_string(), "me\\you".to_string())]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn desktop_entry_round_trips() {
+        let webapp = webapp();
+        let content = webapp.desktop_entry().unwrap();
+
+        assert!(!content.contains("\nName=Injected"));
+        assert!(!content.contains("\nExec=rm"));
+        assert!(!content.contains("\nX-QWA-Url=https://evil.com"));
+
+        let path = PathBuf::from("/tmp/example.desktop");
+        let entry = DesktopEntry::from_str(&path, &content, Some(&LOCALES))
+            .expect("generated desktop entry parses");
+        let parsed = WebAppLauncher::from(entry);
+
+        assert_eq!(parsed.codename, webapp.codename);
+        assert_eq!(parsed.name, webapp.name);
+        assert_eq!(parsed.window_title, webapp.window_title);
+        assert_eq!(parsed.icon, webapp.icon);
+        assert_eq!(parsed.icon_source, webapp.icon_source);
+        assert_eq!(parsed.url, webapp.url);
+        assert_eq!(parsed.extra_urls, webapp.extra_urls);
+        assert_eq!(parsed.custom_parameters, webapp.custom_parameters);
+        assert_eq!(parsed.profile_dir, webapp.profile_dir);
+        assert_eq!(parsed.metadata, webapp.metadata);
+    }
+
+    #[tokio::test]
+    async fn version_and_metadata_round_trip_through_the_desktop_file() {
+        let root = crate::testing::data_root();
+        let browser = chromium(root.path());
+        let webapp = WebAppLauncher {
+            appid: "chromium".to_string(),
+            browser: browser.clone(),
+            version: "0.1.0".to_string(),
+            metadata: BTreeMap::from([
+                ("Created-By".to_string(), "backup script".to_string()),
+                ("Not valid".to_string(), "dropped".to_string()),
+            ]),
+            ..installable("Example1234", root.path())
+        };
+        webapp.create().await.unwrap();
+
+        let content = fs::read_to_string(desktop_file_for("Example1234").unwrap()).unwrap();
+        assert_eq!(
+            desktop_key(&content, "X-QWA-Version").as_deref(),
+            Some(VERSION)
+        );
+        assert_eq!(
+            desktop_key(&content, "X-QWA-Meta-Created-By").as_deref(),
+            Some("backup script")
+        );
+        assert!(!content.contains("Not valid"));
+
+        // the version is the one of the writer, not of the web app's record
+        let loaded = load_webapp_with("Example1234", || vec![browser.clone()]).unwrap();
+        assert_eq!(loaded.version, VERSION);
+        assert_eq!(
+            loaded.metadata,
+            BTreeMap::from([("Created-By".to_string(), "backup script".to_string())])
+        );
+    }
+
+    #[test]
+    fn browser_id_round_trips_through_the_desktop_file() {
+        let root = crate::testing::data_root();
+        let entries = root.path().join("applications");
+        fs::create_dir_all(&entries).unwrap();
+        for (appid, name) in [("firefox", "Firefox"), ("chromium", "Chromium")] {
+            fs::write(
+                entries.join(format!("{}.desktop", appid)),
+                format!("[Desktop Entry]\nType=Application\nName={name}\nExec={appid} %u\n"),
+            )
+            .unwrap();
+        }
+        let browsers: Vec<Browser> = ["firefox", "chromium"]
+            .iter()
+            .map(|appid| Browser::from_path(&entries.join(format!("{}.desktop", appid))))
+            .collect();
+
+        let webapp = WebAppLauncher {
+            browser: browsers[0].clone(),
+            ..installable("Example1234", root.path())
+        };
+        let content = webapp.desktop_entry().unwrap();
+        let written = desktop_key(&content, "X-QWA-Browser").unwrap();
+        assert_eq!(written, "firefox/native");
+
+        let id = BrowserId::parse(&written).unwrap();
+        assert_eq!(Some(&id), webapp.browser.id().as_ref());
+        let resolved = browsers
+            .iter()
+            .find(|browser| browser.id().as_ref() == Some(&id))
+            .unwrap();
+        assert_eq!(resolved.model, Some(BrowserModel::Firefox));
+
+        assert_eq!(BrowserId::parse("firefox"), None);
+        assert_eq!(BrowserId::parse("/native"), None);
+    }
+
+    #[test]
+    fn window_size_is_width_by_height() {
+        assert_eq!(parse_window_size("1280x720"), Some((1280, 720)));
+        assert_eq!(parse_window_size(" 800 X 600 "), Some((800, 600)));
+        assert_eq!(parse_window_size("0x600"), None);
+        assert_eq!(parse_window_size("800"), None);
+        assert_eq!(parse_window_size("-800x600"), None);
+    }
+
+    #[test]
+    fn window_size_is_passed_to_each_engine() {
+        let root = crate::testing::data_root();
+        let exec = |model: BrowserModel, maximized: bool| {
+            let mut webapp = installable("Example1234", root.path());
+            webapp.browser.model = Some(model);
+            webapp.window_size = Some((1280, 720));
+            webapp.maximized = maximized;
+            webapp.exec_string().unwrap()
+        };
+
+        let chromium = exec(BrowserModel::Chromium, false);
+        assert!(chromium.contains(" --window-size=1280,720"), "{}", chromium);
+        assert!(!chromium.contains("--start-maximized"), "{}", chromium);
+
+        // maximized wins over the size
+        let maximized = exec(BrowserModel::Chromium, true);
+        assert!(maximized.contains(" --start-maximized"), "{}", maximized);
+        assert!(!maximized.contains("--window-size"), "{}", maximized);
+
+        let firefox = exec(BrowserModel::Firefox, false);
+        assert!(
+            firefox.contains(" --width 1280 --height 720"),
+            "{}",
+            firefox
+        );
+
+        let logs = crate::testing::capture_logs();
+        let min = exec(BrowserModel::Min, true);
+        assert!(!min.contains("1280"), "{}", min);
+        assert!(logs.output().contains("can't set the window size"));
+        assert!(logs.output().contains("can't start maximized"));
+    }
+
+    #[test]
+    fn window_position_may_be_negative() {
+        assert_eq!(parse_window_position("1920,0"), Some((1920, 0)));
+        assert_eq!(parse_window_position(" -1280 , 20 "), Some((-1280, 20)));
+        assert_eq!(parse_window_position("1920x0"), None);
+        assert_eq!(parse_window_position("a,b"), None);
+    }
+
+    #[test]
+    fn window_position_is_passed_to_chromium() {
+        let root = crate::testing::data_root();
+        let webapp = |model: BrowserModel| {
+            let mut webapp = installable("Example1234", root.path());
+            webapp.browser.model = Some(model);
+            webapp.window_position = Some((-1280, 20));
+            webapp
+        };
+
+        let chromium = webapp(BrowserModel::Chromium);
+        let exec = chromium.exec_string().unwrap();
+        assert!(exec.contains(" --window-position=-1280,20"), "{}", exec);
+        assert_eq!(
+            desktop_key(&chromium.desktop_entry().unwrap(), "X-QWA-Window-Position").as_deref(),
+            Some("-1280,20")
+        );
+
+        let logs = crate::testing::capture_logs();
+        let firefox = webapp(BrowserModel::Firefox).exec_string().unwrap();
+        assert!(!firefox.contains("1280"), "{}", firefox);
+        assert!(logs.output().contains("can't set the window position"));
+    }
+
+    #[test]
+    fn native_and_flatpak_browsers_of_a_page_conflict() {
+        let browser = |model: BrowserModel, source: BrowserSource| Browser {
+            model: Some(model),
+            source,
+            ..Default::default()
+        };
+        let native = WebAppLauncher {
+            url: "https://mail.example.com/".to_string(),
+            browser: browser(BrowserModel::Chromium, BrowserSource::Native),
+            ..webapp()
+        };
+        let conflict = |url: &str, browser: Browser| {
+            packaging_conflict_in(url, &browser, vec![native.clone()])
+        };
+
+        let flatpak = browser(BrowserModel::Chromium, BrowserSource::Flatpak);
+        assert_eq!(
+            conflict("https://MAIL.example.com", flatpak.clone()),
+            Some(native.clone())
+        );
+        let system_flatpak = browser(BrowserModel::Chromium, BrowserSource::SystemFlatpak);
+        assert_eq!(
+            conflict("https://mail.example.com/", system_flatpak),
+            Some(native.clone())
+        );
+
+        // only flatpak against any other packaging counts, for the same browser and page
+        let snap = browser(BrowserModel::Chromium, BrowserSource::Snap);
+        assert_eq!(conflict("https://mail.example.com/", snap), None);
+        let brave = browser(BrowserModel::Brave, BrowserSource::Flatpak);
+        assert_eq!(conflict("https://mail.example.com/", brave), None);
+        assert_eq!(conflict("https://example.com/", flatpak), None);
+        assert_eq!(
+            conflict("https://mail.example.com/", Browser::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn new_windows_as_tabs_are_applied_per_browser() {
+        use strum::IntoEnumIterator;
+
+        let root = crate::testing::data_root();
+        let webapp = |model: BrowserModel, tabs: bool| {
+            let mut webapp = installable(&format!("Example{:?}{}", model, tabs), root.path());
+            webapp.browser.model = Some(model);
+            webapp.isolate_profile = true;
+            webapp.new_windows_as_tabs = tabs;
+            webapp
+        };
+
+        for model in BrowserModel::iter() {
+            let (tabs, windows) = (webapp(model.clone(), true), webapp(model.clone(), false));
+            let (with, without) = (tabs.exec_string().unwrap(), windows.exec_string().unwrap());
+            let user_prefs = |webapp: &WebAppLauncher| {
+                fs::read_to_string(webapp.profile_path().unwrap().join("user.js")).ok()
+            };
+
+            // only Firefox takes it, as a pref of the profile
+            assert_eq!(
+                with.replace(&tabs.codename, &windows.codename),
+                without,
+                "{:?}",
+                model
+            );
+            match model.info().engine {
+                Engine::Gecko => {
+                    assert!(model.info().supports_new_windows_as_tabs);
+                    let prefs = user_prefs(&tabs).unwrap();
+                    assert!(prefs.contains("user_pref(\"browser.link.open_newwindow\", 3);"));
+                    let prefs = user_prefs(&windows).unwrap();
+                    assert!(!prefs.contains("open_newwindow\", 3"), "{:?}", model);
+                }
+                _ => {
+                    assert!(!model.info().supports_new_windows_as_tabs);
+                    assert_eq!(user_prefs(&tabs), None, "{:?}", model);
+                }
+            }
+
+            assert_eq!(
+                desktop_key(&tabs.desktop_entry().unwrap(), "X-QWA-New-Windows-As-Tabs").as_deref(),
+                Some("true")
+            );
+        }
+    }
+
+    #[test]
+    fn schemes_are_split_and_filtered() {
+        assert_eq!(
+            split_schemes("slack: Zoommtg:// web+app https mailto 1bad"),
+            vec!["slack", "zoommtg", "web+app"]
+        );
+        assert!(split_schemes("").is_empty());
+    }
+
+    #[test]
+    fn requested_schemes_are_in_the_mime_types() {
+        let webapp = WebAppLauncher {
+            url_schemes: split_schemes("slack:// https"),
+            ..webapp()
+        };
+
+        assert_eq!(
+            desktop_key(&webapp.desktop_entry().unwrap(), "MimeType").as_deref(),
+            Some("text/html;text/xml;application/xhtml_xml;x-scheme-handler/slack;")
+        );
+    }
+
+    #[test]
+    fn icon_source_round_trips() {
+        let path = PathBuf::from("/tmp/example.desktop");
+        let parse = |content: &str| {
+            WebAppLauncher::from(DesktopEntry::from_str(&path, content, Some(&LOCALES)).unwrap())
+        };
+
+        for source in [
+            "https://example.com/favicon.svg",
+            "/home/user/Pictures/logo.png",
+        ] {
+            let webapp = WebAppLauncher {
+                icon_source: source.to_string(),
+                ..webapp()
+            };
+            let content = webapp.desktop_entry().unwrap();
+
+            assert_eq!(
+                desktop_key(&content, "X-QWA-Icon-Source").as_deref(),
+                Some(source)
+            );
+            assert_eq!(parse(&content).icon_source, source);
+        }
+
+        // written by versions which didn't record it
+        let content = webapp().desktop_entry().unwrap();
+        let old: String = content
+            .lines()
+            .filter(|line| !line.starts_with("X-QWA-Icon-Source="))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        assert_eq!(parse(&old).icon_source, "");
+    }
+
+    #[test]
+    fn exec_keeps_percent_signs_literal() {
+        let content = webapp().desktop_entry().unwrap();
+        let exec = content
+            .lines()
+            .find_map(|line| line.strip_prefix("Exec="))
+            .unwrap();
+
+        assert!(exec.contains("%%20"));
+        assert!(!exec.contains('\n'));
+    }
+
+    fn desktop_key(content: &str, key: &str) -> Option<String> {
+        content
+            .lines()
+            .find_map(|line| line.strip_prefix(&format!("{}=", key)))
+            .map(str::to_string)
+    }
+
+    #[test]
+    fn chromium_class_matches_startup_wm_class() {
+        let webapp = WebAppLauncher {
+            codename: "Example1234".to_string(),
+            browser: Browser {
+                model: Some(BrowserModel::Chromium),
+                exec: "chromium".to_string(),
+                name: "Chromium".to_string(),
+                ..Default::default()
+            },
+            url: "https://example.com".to_string(),
+            profile_dir: Some(PathBuf::from("/tmp/profiles")),
+            ..Default::default()
+        };
+        let content = webapp.desktop_entry().unwrap();
+        let wm_class = desktop_key(&content, "StartupWMClass").unwrap();
+        let exec = desktop_key(&content, "Exec").unwrap();
+
+        assert_eq!(wm_class, webapp_id("Example1234"));
+        assert!(exec.contains(&format!("--class={} ", wm_class)));
+    }
+
+    #[test]
+    fn bundle_round_trips_without_profile_secrets() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path();
+        let icon = dir.join("Example1234.png");
+        fs::write(&icon, b"icon data").unwrap();
+
+        let profile = dir.join("profile");
+        fs::create_dir_all(profile.join("Default")).unwrap();
+        fs::write(profile.join("prefs.js"), "prefs").unwrap();
+        fs::write(profile.join("logins.json"), "secret").unwrap();
+        fs::write(profile.join("cookies.sqlite-wal"), "secret").unwrap();
+        fs::write(profile.join("Default/Preferences"), "{}").unwrap();
+        fs::write(profile.join("Default/Login Data"), "secret").unwrap();
+
+        let webapp = webapp();
+        let path = dir.join("Example1234.qwa");
+        write_bundle(&webapp, Some(&icon), Some(&profile), &path).unwrap();
+
+        let unpacked = dir.join("unpacked");
+        let bundle = read_bundle(&path, &unpacked).unwrap();
+        let profile = unpacked.join(BUNDLE_PROFILE_DIR);
+
+        assert_eq!(bundle.webapp.codename, webapp.codename);
+        assert_eq!(bundle.webapp.name, webapp.name);
+        assert_eq!(bundle.webapp.url, webapp.url);
+        assert_eq!(bundle.webapp.metadata, webapp.metadata);
+        assert_eq!(bundle.icon.as_deref(), Some("Example1234.png"));
+        assert!(bundle.profile);
+        assert_eq!(
+            fs::read(unpacked.join(BUNDLE_ICON_DIR).join("Example1234.png")).unwrap(),
+            b"icon data"
+        );
+        assert!(profile.join("prefs.js").is_file());
+        assert!(profile.join("Default/Preferences").is_file());
+        assert!(!profile.join("logins.json").exists());
+        assert!(!profile.join("cookies.sqlite-wal").exists());
+        assert!(!profile.join("Default/Login Data").exists());
+    }
+
+    #[test]
+    fn bundle_leaves_out_profile_unless_asked() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path();
+        let path = dir.join("Example1234.qwa");
+        write_bundle(&webapp(), None, None, &path).unwrap();
+
+        let unpacked = dir.join("unpacked");
+        let bundle = read_bundle(&path, &unpacked).unwrap();
+
+        assert_eq!(bundle.icon, None);
+        assert!(!bundle.profile);
+        assert!(!unpacked.join(BUNDLE_PROFILE_DIR).exists());
+    }
+
+    #[tokio::test]
+    async fn web_app_round_trips_through_a_bundle() {
+        let root = crate::testing::data_root();
+        let browser = chromium(root.path());
+        let icon = root.path().join("example.png");
+        fs::write(&icon, png(128)).unwrap();
+
+        let webapp = WebAppLauncher {
+            appid: "chromium".to_string(),
+            browser: browser.clone(),
+            name: "Example".to_string(),
+            icon: icon.to_string_lossy().to_string(),
+            isolate_profile: true,
+            ..installable("Example1234", root.path())
+        };
+        webapp.create().await.unwrap();
+        webapp.save_record().unwrap();
+        let profile = webapp.profile_path().unwrap();
+        fs::create_dir_all(profile.join("Default")).unwrap();
+        fs::write(profile.join("Default/Preferences"), "{}").unwrap();
+        fs::write(profile.join("Default/Cookies"), "secret").unwrap();
+
+        let bundles = tempfile::tempdir().unwrap();
+        let path = bundles.path().join("Example1234.qwa");
+        export_webapp(&webapp, &path, true).unwrap();
+
+        let unpacked = tempfile::tempdir().unwrap();
+        let imported = import_bundle_from(&path, unpacked.path(), || vec![browser.clone()])
+            .await
+            .unwrap();
+
+        // installed next to the original web app
+        assert_ne!(imported.codename, webapp.codename);
+        assert!(imported.codename.starts_with("Example"));
+        assert_eq!(imported.name, webapp.name);
+        assert_eq!(imported.url, webapp.url);
+        assert_eq!(imported.browser.model, Some(BrowserModel::Chromium));
+        assert!(desktop_file_for(&imported.codename).unwrap().exists());
+        assert!(installed_webapps()
+            .iter()
+            .any(|webapp| webapp.codename == imported.codename));
+
+        let icon = PathBuf::from(common::icon_path(&imported.icon));
+        assert!(icon.is_file(), "{:?}", icon);
+
+        let profile = imported.profile_path().unwrap();
+        assert!(profile.join("Default/Preferences").is_file());
+        assert!(!profile.join("Default/Cookies").exists());
+    }
+
+    #[tokio::test]
+    async fn bundle_paths_are_not_trusted() {
+        let root = crate::testing::data_root();
+        let browser = chromium(root.path());
+        let outside = root.path().join("outside");
+
+        let webapp = WebAppLauncher {
+            appid: "chromium".to_string(),
+            browser: browser.clone(),
+            codename: "../../outside/Evil1234".to_string(),
+            name: "Evil".to_string(),
+            isolate_profile: true,
+            profile_dir: Some(outside.clone()),
+            ..installable("Evil1234", root.path())
+        };
+        let bundles = tempfile::tempdir().unwrap();
+        let path = bundles.path().join("Evil1234.qwa");
+        let profile = bundles.path().join("profile");
+        fs::create_dir_all(&profile).unwrap();
+        fs::write(profile.join("prefs.js"), "prefs").unwrap();
+        write_bundle(&webapp, None, Some(&profile), &path).unwrap();
+
+        let unpacked = tempfile::tempdir().unwrap();
+        let imported = import_bundle_from(&path, unpacked.path(), || vec![browser.clone()])
+            .await
+            .unwrap();
+
+        assert!(imported.codename.starts_with("Evil"));
+        assert!(!imported.codename.contains('/'));
+        assert_eq!(imported.profile_dir, None);
+        assert!(imported.profile_path().unwrap().join("prefs.js").is_file());
+        assert!(!outside.exists());
+    }
+
+    #[test]
+    fn no_startup_wm_class_without_class_switch() {
+        let content = webapp().desktop_entry().unwrap();
+
+        assert_eq!(desktop_key(&content, "StartupWMClass"), None);
+    }
+
+    // web app which can be created in the test data directory,
+    // with its profile there too
+    fn installable(codename: &str, root: &Path) -> WebAppLauncher {
+        WebAppLauncher {
+            codename: codename.to_string(),
+            browser: Browser {
+                model: Some(BrowserModel::Min),
+                exec: "min".to_string(),
+                name: "Min".to_string(),
+                profile_path: root.join("profiles"),
+                ..Default::default()
+            },
+            name: codename.to_string(),
+            url: "https://example.com".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn sample_entry(file: &str, name: &str, exec: &str) -> DesktopEntry {
+        let content = format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec={}\n",
+            name, exec
+        );
+        DesktopEntry::from_str(PathBuf::from(file), &content, Some(&LOCALES)).unwrap()
+    }
+
+    #[test]
+    fn urls_of_other_tools_are_parsed() {
+        // webapp-manager, Chromium and Firefox
+        assert_eq!(
+            exec_url(
+                "sh -c 'XAPP_FORCE_GTKWINDOW_ICON=\"webapp-manager\" chromium \
+                 --app=\"https://example.com/\" --class=WebApp-Example1234 \
+                 --user-data-dir=/home/user/.local/share/ice/profiles/Example1234'"
+            )
+            .as_deref(),
+            Some("https://example.com/")
+        );
+        assert_eq!(
+            exec_url(
+                "sh -c 'XAPP_FORCE_GTKWINDOW_ICON=\"webapp-manager\" firefox \
+                 --class WebApp-Example1234 --profile /home/user/.local/share/ice/firefox \
+                 --no-remote \"https://example.com/path\"'"
+            )
+            .as_deref(),
+            Some("https://example.com/path")
+        );
+        // ICE
+        assert_eq!(
+            exec_url("chromium --app=https://www.example.com --class=ICE-SSB-example").as_deref(),
+            Some("https://www.example.com")
+        );
+        assert_eq!(exec_url("firefox %u"), None);
+    }
+
+    #[test]
+    fn web_apps_of_other_tools_are_found_by_url() {
+        let entries =
+            || {
+                vec![
+                sample_entry(
+                    "/usr/share/applications/webapp-Example1234.desktop",
+                    "Example (webapp-manager)",
+                    "sh -c 'chromium --app=\"https://example.com/\" --class=WebApp-Example1234'",
+                ),
+                sample_entry(
+                    "/usr/share/applications/ice-example.desktop",
+                    "Example (ICE)",
+                    "firefox --class ICE-SSB-example --no-remote https://example.com",
+                ),
+                sample_entry(
+                    &format!("/usr/share/applications/{}.Example1234.desktop", crate::APP_ID),
+                    "Example",
+                    "chromium --app=https://example.com",
+                ),
+                sample_entry(
+                    "/usr/share/applications/webapp-Other.desktop",
+                    "Other",
+                    "chromium --app=https://example.com/other",
+                ),
+                sample_entry(
+                    "/usr/share/applications/firefox.desktop",
+                    "Firefox",
+                    "firefox %u",
+                ),
+            ]
+            };
+
+        let found = foreign_webapps_in("https://example.com/?ref=menu", entries());
+        assert_eq!(
+            found,
+            vec![
+                ForeignWebApp {
+                    name: "Example (webapp-manager)".to_string(),
+                    path: PathBuf::from("/usr/share/applications/webapp-Example1234.desktop"),
+                },
+                ForeignWebApp {
+                    name: "Example (ICE)".to_string(),
+                    path: PathBuf::from("/usr/share/applications/ice-example.desktop"),
+                },
+            ]
+        );
+
+        assert!(foreign_webapps_in("https://example.org", entries()).is_empty());
+        assert!(foreign_webapps_in("not a url", entries()).is_empty());
+    }
+
+    fn png(size: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        image::DynamicImage::new_rgba8(size, size)
+            .write_to(
+                &mut std::io::Cursor::new(&mut data),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        data
+    }
+
+    #[tokio::test]
+    async fn icons_of_poor_quality_are_flagged() {
+        let root = crate::testing::data_root();
+        let icons = root.path().join("icons");
+        fs::create_dir_all(&icons).unwrap();
+
+        fs::write(icons.join("sharp.png"), png(128)).unwrap();
+        fs::write(icons.join("small.png"), png(16)).unwrap();
+        fs::write(icons.join("broken.png"), b"not an image").unwrap();
+        fs::write(
+            icons.join("scalable.svg"),
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16"/>"#,
+        )
+        .unwrap();
+        common::ensure_icons_writable().unwrap();
+        let wrapped = common::convert_raster_to_svg_format(png(32).into(), "Wrapped").unwrap();
+
+        let icon = |name: &str| icons.join(name).to_string_lossy().to_string();
+        for (codename, icon) in [
+            ("Sharp1234", icon("sharp.png")),
+            ("Small1234", icon("small.png")),
+            ("Broken1234", icon("broken.png")),
+            ("Missing1234", icon("missing.png")),
+            ("Scalable1234", icon("scalable.svg")),
+            ("Wrapped1234", wrapped.clone()),
+        ] {
+            let mut webapp = installable(codename, root.path());
+            webapp.icon = icon;
+            webapp.create().await.unwrap();
+            webapp.save_record().unwrap();
+        }
+
+        let mut audits = audit_icons(MIN_ICON_SIZE).await;
+        audits.sort_by(|a, b| a.codename.cmp(&b.codename));
+        let issues: Vec<(&str, &IconIssue)> = audits
+            .iter()
+            .map(|audit| (audit.codename.as_str(), &audit.issue))
+            .collect();
+
+        assert_eq!(
+            issues,
+            vec![
+                ("Broken1234", &IconIssue::Broken),
+                ("Missing1234", &IconIssue::Missing),
+                ("Small1234", &IconIssue::LowResolution(16, 16)),
+                ("Wrapped1234", &IconIssue::LowResolution(32, 32)),
+            ]
+        );
+        assert_eq!(audits[3].icon, wrapped);
+
+        // the threshold is up to the caller
+        assert_eq!(audit_icons(16).await.len(), 2);
+    }
+
+    #[test]
+    fn launch_command_starts_the_desktop_entry() {
+        let webapp = webapp();
+        let desktop_file = format!("{}.desktop", webapp_id("Example1234"));
+
+        assert_eq!(
+            webapp
+                .launch_command_with(LaunchMethod::DesktopEntry, false, true)
+                .unwrap(),
+            vec!["gtk-launch".to_string(), desktop_file.clone()]
+        );
+        assert_eq!(
+            webapp
+                .launch_command_with(LaunchMethod::DesktopEntry, true, true)
+                .unwrap(),
+            vec![
+                "flatpak-spawn".to_string(),
+                "--host".to_string(),
+                "gtk-launch".to_string(),
+                desktop_file
+            ]
+        );
+    }
+
+    #[test]
+    fn launch_command_falls_back_to_exec() {
+        let webapp = webapp();
+        let exec = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            webapp.exec_string().unwrap(),
+        ];
+
+        // without gtk-launch, or when asked for
+        assert_eq!(
+            webapp
+                .launch_command_with(LaunchMethod::DesktopEntry, false, false)
+                .unwrap(),
+            exec
+        );
+        assert_eq!(
+            webapp
+                .launch_command_with(LaunchMethod::Exec, false, true)
+                .unwrap(),
+            exec
+        );
+        assert_eq!(
+            webapp
+                .launch_command_with(LaunchMethod::Exec, true, true)
+                .unwrap()[..3],
+            ["flatpak-spawn", "--host", "sh"]
+        );
+    }
+
+    fn chromium(root: &Path) -> Browser {
+        let path = root.join("chromium.desktop");
+        fs::write(
+            &path,
+            "[Desktop Entry]\nType=Application\nName=Chromium\nExec=chromium %U\n",
+        )
+        .unwrap();
+        Browser::from_path(&path)
+    }
+
+    #[tokio::test]
+    async fn web_app_round_trips_through_its_desktop_file() {
+        let root = crate::testing::data_root();
+        let browser = chromium(root.path());
+        let webapp = WebAppLauncher {
+            appid: "chromium".to_string(),
+            browser: browser.clone(),
+            window_title: "Example Mail".to_string(),
+            icon: root.path().join("icon.png").to_string_lossy().to_string(),
+            extra_urls: vec!["https://mail.example.com".to_string()],
+            custom_parameters: "--force-dark-mode".to_string(),
+            isolate_profile: true,
+            new_windows_as_tabs: true,
+            is_incognito: true,
+            profile_dir: Some(root.path().join("ssd")),
+            ..installable("Example1234", root.path())
+        };
+        webapp.create().await.unwrap();
+
+        let loaded = load_webapp_with("Example1234", || vec![browser.clone()]).unwrap();
+
+        // the desktop file records the version which wrote it
+        assert_eq!(
+            loaded,
+            WebAppLauncher {
+                version: VERSION.to_string(),
+                ..webapp
+            }
+        );
+    }
+
+    #[test]
+    fn desktop_files_without_custom_keys_are_reconstructed() {
+        let root = crate::testing::data_root();
+        let browser = chromium(root.path());
+        let path = desktop_file_for("Legacy1234").unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            &path,
+            "[Desktop Entry]\nType=Application\nName=Legacy\n\
+             Exec=chromium --app=https://example.com/inbox --user-data-dir=/tmp/Legacy1234 --incognito\n",
+        )
+        .unwrap();
+
+        let loaded = load_webapp_with("Legacy1234", || vec![browser.clone()]).unwrap();
+
+        assert_eq!(loaded.codename, "Legacy1234");
+        assert_eq!(loaded.name, "Legacy");
+        assert_eq!(loaded.url, "https://example.com/inbox");
+        assert_eq!(loaded.browser, browser);
+        assert_eq!(loaded.appid, "chromium");
+        assert!(loaded.isolate_profile);
+        assert!(loaded.is_incognito);
+
+        // a browser that's gone can't be reconstructed
+        assert!(matches!(
+            load_webapp_with("Legacy1234", Vec::new),
+            Err(Error::BrowserNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn hicolor_icons_are_upgraded_from_the_kept_original() {
+        let root = crate::testing::data_root();
+        let hicolor = common::hicolor_icons_location().unwrap();
+        let icons = common::ensure_icons_writable().unwrap();
+
+        // installed by an older version, without the largest size
+        let icon_name = common::hicolor_icon_name("Example1234");
+        let apps = hicolor.join("256x256/apps");
+        fs::create_dir_all(&apps).unwrap();
+        fs::write(apps.join(format!("{}.png", icon_name)), png(256)).unwrap();
+        fs::write(icons.join("Example1234.orig.png"), png(512)).unwrap();
+
+        let mut webapp = installable("Example1234", root.path());
+        webapp.icon = icon_name.clone();
+        webapp.create().await.unwrap();
+        webapp.save_record().unwrap();
+
+        // icons referenced by path, and icons without a source, are left alone
+        let mut by_path = installable("Path1234", root.path());
+        by_path.icon = apps
+            .join(format!("{}.png", icon_name))
+            .to_string_lossy()
+            .to_string();
+        by_path.create().await.unwrap();
+        by_path.save_record().unwrap();
+        let mut sourceless = installable("Sourceless1234", root.path());
+        sourceless.icon = common::hicolor_icon_name("Sourceless1234");
+        sourceless.create().await.unwrap();
+        sourceless.save_record().unwrap();
+
+        assert_eq!(upgrade_icons().await, vec!["Example1234".to_string()]);
+
+        let largest = hicolor.join(format!(
+            "{0}x{0}/apps/{1}.png",
+            common::HICOLOR_MAX_SIZE,
+            icon_name
+        ));
+        let (image, _) = common::decode_still_image(&fs::read(&largest).unwrap()).unwrap();
+        assert_eq!(image.width(), common::HICOLOR_MAX_SIZE);
+
+        // already upgraded
+        assert!(upgrade_icons().await.is_empty());
+    }
+
+    #[test]
+    fn menu_folder_category_is_applied() {
+        let webapp = webapp();
+        let categories = |webapp: &WebAppLauncher| {
+            desktop_key(&webapp.desktop_entry().unwrap(), "Categories").unwrap()
+        };
+
+        assert!(!categories(&webapp).contains(common::MENU_CATEGORY));
+
+        crate::testing::set_config(AppConfig {
+            menu_folder: true,
+            ..AppConfig::default()
+        });
+        assert_eq!(
+            categories(&webapp),
+            format!("{};{};", webapp.category.as_ref(), common::MENU_CATEGORY)
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_launchers_are_rejected_by_kind() {
+        let root = crate::testing::data_root();
+
+        let mut webapp = installable("Example1234", root.path());
+        webapp.url = "example".to_string();
+        assert!(matches!(
+            webapp.create().await,
+            Err(Error::InvalidUrl(url)) if url == "example"
+        ));
+
+        let mut webapp = installable("Example1234", root.path());
+        webapp.appid = "org.example.Gone".to_string();
+        webapp.browser.model = None;
+        assert!(matches!(
+            webapp.create().await,
+            Err(Error::BrowserNotFound(appid)) if appid == "org.example.Gone"
+        ));
+        assert!(!desktop_file_for("Example1234").unwrap().exists());
+    }
+
+    #[tokio::test]
+    async fn desktop_file_is_created_where_it_is_looked_up() {
+        let root = crate::testing::data_root();
+        let webapp = installable("Example1234", root.path());
+
+        webapp.create().await.unwrap();
+
+        let path = desktop_file_for("Example1234").unwrap();
+        assert_eq!(
+            path,
+            applications_location()
+                .unwrap()
+                .join(format!("{}.desktop", webapp_id("Example1234")))
+        );
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            webapp.desktop_entry().unwrap()
+        );
+    }
+
+    #[test]
+    fn duplicate_is_found_despite_a_trailing_slash() {
+        let root = crate::testing::data_root();
+        let webapp = WebAppLauncher {
+            appid: "org.mozilla.firefox".to_string(),
+            url: "https://example.com/app/".to_string(),
+            ..installable("Example1234", root.path())
+        };
+        webapp.save_record().unwrap();
+
+        let duplicate = find_duplicate("HTTPS://example.com:443/app", "org.mozilla.firefox");
+        assert_eq!(duplicate.unwrap().codename, "Example1234");
+
+        assert!(find_duplicate("https://example.com/app", "org.chromium.Chromium").is_none());
+        assert!(find_duplicate("https://example.com/other/", "org.mozilla.firefox").is_none());
+    }
+
+    #[tokio::test]
+    async fn only_orphaned_profiles_are_removed() {
+        let root = crate::testing::data_root();
+        let profiles = DataPaths::current()
+            .profiles()
+            .unwrap()
+            .join("org.mozilla.firefox");
+        let (live, orphan) = (profiles.join("Live1234"), profiles.join("Orphan1234"));
+        fs::create_dir_all(&live).unwrap();
+        fs::create_dir_all(&orphan).unwrap();
+
+        WebAppLauncher {
+            appid: "org.mozilla.firefox".to_string(),
+            ..installable("Live1234", root.path())
+        }
+        .save_record()
+        .unwrap();
+
+        assert_eq!(orphaned_profiles(), vec![orphan.clone()]);
+
+        // the broken record may be the one owning the profile
+        let broken = database_path("Broken1234.ron").unwrap();
+        fs::write(&broken, "(codename: ").unwrap();
+        assert!(orphaned_profiles().is_empty());
+        fs::remove_file(broken).unwrap();
+
+        assert_eq!(remove_orphaned_profiles().await, 1);
+        assert!(live.is_dir());
+        assert!(!orphan.exists());
+    }
+
+    #[tokio::test]
+    async fn unwritable_profile_root_is_reported_before_creation() {
+        let root = crate::testing::data_root();
+        // a file in the way fails like a read-only directory, also when run as root
+        let blocked = root.path().join("blocked");
+        fs::write(&blocked, b"").unwrap();
+
+        let mut webapp = installable("Example1234", root.path());
+        webapp.browser.model = Some(BrowserModel::Falkon);
+        webapp.browser.exec = "falkon".to_string();
+        webapp.browser.name = "Falkon".to_string();
+        webapp.browser.profile_path = blocked.join("profiles");
+        webapp.isolate_profile = true;
+
+        let err = webapp.create().await.unwrap_err().to_string();
+        assert!(
+            err.contains(&format!("{:?}", blocked.join("profiles"))),
+            "{}",
+            err
+        );
+        assert!(err.contains("Falkon"), "{}", err);
+        assert!(!desktop_file_for("Example1234").unwrap().exists());
+    }
+
+    #[tokio::test]
+    async fn profile_location_override_is_used() {
+        let root = crate::testing::data_root();
+        let location = root.path().join("ssd/webapps");
+
+        let mut webapp = installable("Example1234", root.path());
+        webapp.browser.model = Some(BrowserModel::Chromium);
+        webapp.browser.exec = "chromium".to_string();
+        webapp.isolate_profile = true;
+        webapp.profile_dir = Some(location.clone());
+
+        webapp.create().await.unwrap();
+
+        let content = fs::read_to_string(desktop_file_for("Example1234").unwrap()).unwrap();
+        let exec = desktop_key(&content, "Exec").unwrap();
+        let profile = location.join("Example1234");
+        assert!(
+            exec.contains(&format!("--user-data-dir={}", profile.to_str().unwrap())),
+            "{}",
+            exec
+        );
+        assert!(profile.is_dir());
+        assert!(!root.path().join("profiles/Example1234").exists());
+        assert_eq!(
+            desktop_key(&content, "X-QWA-Profile-Dir").as_deref(),
+            location.to_str()
+        );
+
+        // a location that can't be written to is reported before anything is created
+        let blocked = root.path().join("blocked");
+        fs::write(&blocked, b"").unwrap();
+        let mut webapp = installable("Other1234", root.path());
+        webapp.browser.model = Some(BrowserModel::Chromium);
+        webapp.isolate_profile = true;
+        webapp.profile_dir = Some(blocked.join("webapps"));
+
+        let err = webapp.create().await.unwrap_err().to_string();
+        assert!(err.contains("not writable"), "{}", err);
+        assert!(!desktop_file_for("Other1234").unwrap().exists());
+    }
+
+    #[test]
+    fn profile_locations_must_be_absolute() {
+        assert_eq!(
+            parse_profile_dir(" /mnt/ssd/webapps "),
+            Some(PathBuf::from("/mnt/ssd/webapps"))
+        );
+        assert_eq!(parse_profile_dir("webapps"), None);
+        assert_eq!(parse_profile_dir(""), None);
+        if let Some(home) = common::home_dir() {
+            assert_eq!(parse_profile_dir("~/webapps"), Some(home.join("webapps")));
+        }
+    }
+
+    #[test]
+    fn single_window_keys_need_the_capability() {
+        let root = crate::testing::data_root();
+        let mut webapp = installable("Example1234", root.path());
+        webapp.browser.model = Some(BrowserModel::Chromium);
+        webapp.browser.exec = "chromium".to_string();
+        webapp.isolate_profile = true;
+
+        let entry = webapp.desktop_entry().unwrap();
+        assert_eq!(desktop_key(&entry, "SingleMainWindow"), None);
+        assert_eq!(desktop_key(&entry, "DBusActivatable"), None);
+
+        webapp.single_window = true;
+        let entry = webapp.desktop_entry().unwrap();
+        assert_eq!(
+            desktop_key(&entry, "SingleMainWindow").as_deref(),
+            Some("true")
+        );
+
+        // Min runs every window in the same instance, whatever the profile
+        let min = WebAppLauncher {
+            single_window: true,
+            isolate_profile: true,
+            ..installable("Example1234", root.path())
+        };
+        assert_eq!(
+            desktop_key(&min.desktop_entry().unwrap(), "SingleMainWindow"),
+            None
+        );
+
+        let services = DataPaths::current().dbus_services().unwrap();
+        fs::create_dir_all(&services).unwrap();
+        fs::write(
+            services.join(format!("{}.service", webapp_id("Example1234"))),
+            "",
+        )
+        .unwrap();
+        let entry = webapp.desktop_entry().unwrap();
+        assert_eq!(
+            desktop_key(&entry, "DBusActivatable").as_deref(),
+            Some("true")
+        );
+    }
+
+    #[tokio::test]
+    async fn app_is_created_entirely_within_the_data_dir() {
+        let root = crate::testing::data_root();
+        let paths = DataPaths::with_root(root.path().to_path_buf());
+
+        let source = root.path().join("source.png");
+        image::RgbaImage::from_pixel(64, 64, image::Rgba([255, 0, 0, 255]))
+            .save(&source)
+            .unwrap();
+        let icon = common::move_icon(source.to_str().unwrap(), "Example1234", IconFormat::Png)
+            .await
+            .unwrap();
+
+        let mut webapp = installable("Example1234", root.path());
+        webapp.browser.model = Some(BrowserModel::Falkon);
+        webapp.browser.exec = "falkon".to_string();
+        webapp.browser.profile_path = paths.profiles().unwrap();
+        webapp.isolate_profile = true;
+        webapp.icon = icon;
+        webapp.create().await.unwrap();
+
+        let desktop_file = desktop_file_for("Example1234").unwrap();
+        assert!(desktop_file.starts_with(paths.applications().unwrap()));
+        assert!(desktop_file.exists());
+        assert!(Path::new(&webapp.icon).starts_with(paths.icons().unwrap()));
+        assert!(Path::new(&webapp.icon).exists());
+
+        let exec = desktop_key(&fs::read_to_string(desktop_file).unwrap(), "Exec").unwrap();
+        let profiles = paths.profiles().unwrap();
+        assert!(exec.contains(profiles.to_str().unwrap()));
+        assert!(profiles.join("Example1234").is_dir());
+    }
+
+    #[tokio::test]
+    async fn upsert_creates_then_updates_in_place() {
+        let root = crate::testing::data_root();
+        let webapp = installable("Example1234", root.path());
+
+        assert_eq!(webapp.upsert().await.unwrap(), Upsert::Created);
+
+        let renamed = WebAppLauncher {
+            name: "Renamed".to_string(),
+            url: "https://example.com/renamed".to_string(),
+            ..webapp
+        };
+        assert_eq!(renamed.upsert().await.unwrap(), Upsert::Updated);
+
+        let files = fs::read_dir(applications_location().unwrap()).unwrap();
+        assert_eq!(files.count(), 1);
+        assert_eq!(
+            fs::read_to_string(desktop_file_for("Example1234").unwrap()).unwrap(),
+            renamed.desktop_entry().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_succeeds_without_a_profile_directory() {
+        let root = crate::testing::data_root();
+        let webapp = installable("Example1234", root.path());
+        webapp.create().await.unwrap();
+        webapp.save_record().unwrap();
+
+        webapp.delete().await.unwrap();
+
+        assert!(!desktop_file_for("Example1234").unwrap().exists());
+        assert!(installed_webapps().is_empty());
+        // removing it again finds nothing left, which is fine
+        webapp.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reconcile_adds_updates_and_prunes() {
+        let root = crate::testing::data_root();
+        let falkon = installable("Example1234", root.path()).browser;
+        let falkon = Browser {
+            model: Some(BrowserModel::Falkon),
+            exec: "falkon".to_string(),
+            ..falkon
+        };
+        let browser_of = |appid: &str| match appid {
+            "org.kde.falkon" => falkon.clone(),
+            _ => Browser::default(),
+        };
+        let app = |codename: &str| WebAppLauncher {
+            appid: "org.kde.falkon".to_string(),
+            ..installable(codename, root.path())
+        };
+
+        let manifest = vec![app("First1234"), app("Second1234")];
+        let report = reconcile_with(&manifest, false, browser_of).await;
+        assert_eq!(report.created, vec!["First1234", "Second1234"]);
+        assert!(report.succeeded());
+
+        let report = reconcile_with(&manifest, false, browser_of).await;
+        assert_eq!(report.skipped, vec!["First1234", "Second1234"]);
+
+        let manifest = vec![WebAppLauncher {
+            name: "Renamed".to_string(),
+            ..app("First1234")
+        }];
+        let report = reconcile_with(&manifest, true, browser_of).await;
+        assert_eq!(report.updated, vec!["First1234"]);
+        assert_eq!(report.removed, vec!["Second1234"]);
+        assert!(report.succeeded());
+        assert!(!desktop_file_for("Second1234").unwrap().exists());
+
+        let missing = vec![WebAppLauncher {
+            appid: "org.example.Missing".to_string(),
+            ..app("Third1234")
+        }];
+        let report = reconcile_with(&missing, false, browser_of).await;
+        assert_eq!(report.failed, vec!["Third1234"]);
+        assert!(!report.succeeded());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_creations_with_the_same_name_get_their_own_files() {
+        let root = crate::testing::data_root();
+
+        let creations = (0..8).map(|_| {
+            let root = root.path().to_path_buf();
+
+            tokio::spawn(async move {
+                let codename = reserve_codename("Example App");
+                let webapp = WebAppLauncher {
+                    name: "Example App".to_string(),
+                    ..installable(&codename, &root)
+                };
+                let created = webapp.create().await;
+                release_codename(&codename);
+
+                created.map(|_| webapp)
+            })
+        });
+
+        let mut codenames = HashSet::new();
+        for creation in creations.collect::<Vec<_>>() {
+            let webapp = creation.await.unwrap().unwrap();
+
+            assert_eq!(
+                fs::read_to_string(desktop_file_for(&webapp.codename).unwrap()).unwrap(),
+                webapp.desktop_entry().unwrap()
+            );
+            assert!(codenames.insert(webapp.codename));
+        }
+
+        let files = fs::read_dir(applications_location().unwrap()).unwrap();
+        assert_eq!(files.count(), 8);
+    }
+
+    #[tokio::test]
+    async fn failed_creation_leaves_no_temp_file() {
+        let root = crate::testing::data_root();
+        let webapp = installable("Example1234", root.path());
+        // a directory in the way makes renaming the written file fail
+        fs::create_dir_all(desktop_file_for("Example1234").unwrap()).unwrap();
+
+        assert!(webapp.create().await.is_err());
+
+        let files = fs::read_dir(applications_location().unwrap()).unwrap();
+        assert_eq!(files.count(), 1);
+    }
+}
@@ -0,0 +1,239 @@
+use std::fs::write;
+
+use anyhow::Result;
+use url::Url;
+
+use crate::common::{ensure_icons_writable, normalized_host, sanitize_icon_name};
+use crate::favicon::ManifestColors;
+
+const SIZE: u32 = 256;
+
+const PALETTE: [&str; 12] = [
+    "#e53935", "#d81b60", "#8e24aa", "#5e35b1", "#3949ab", "#1e88e5", "#00897b", "#43a047",
+    "#7cb342", "#f4511e", "#6d4c41", "#546e7a",
+];
+
+// Up to two uppercase initials, one from each of the first words.
+pub fn initials(name: &str) -> String {
+    let initials: String = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter_map(|word| word.chars().next())
+        .take(2)
+        .flat_map(char::to_uppercase)
+        .collect();
+
+    if initials.is_empty() {
+        return String::from("?");
+    }
+
+    initials
+}
+
+// FNV-1a hash of the host, so the same site always gets the same color.
+pub fn background_color(host: &str) -> &'static str {
+    let hash = host
+        .to_lowercase()
+        .bytes()
+        .fold(0x811c9dc5_u32, |hash, byte| {
+            (hash ^ byte as u32).wrapping_mul(0x01000193)
+        });
+
+    PALETTE[hash as usize % PALETTE.len()]
+}
+
+// Channels of a `#rgb` or `#rrggbb` color.
+fn channels(color: &str) -> [u8; 3] {
+    let digits = color.trim_start_matches('#');
+    let channel = |i: usize| match digits.len() {
+        3 => u8::from_str_radix(&digits[i..i + 1].repeat(2), 16).unwrap_or_default(),
+        _ => u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).unwrap_or_default(),
+    };
+
+    [channel(0), channel(1), channel(2)]
+}
+
+fn luminance(color: &str) -> f32 {
+    let [red, green, blue] = channels(color);
+
+    0.299 * red as f32 + 0.587 * green as f32 + 0.114 * blue as f32
+}
+
+// Black or white, whichever is readable on the `#rgb` or `#rrggbb` background.
+fn text_color(background: &str) -> &'static str {
+    match luminance(background) > 160.0 {
+        true => "#000000",
+        false => "#ffffff",
+    }
+}
+
+// Color of the `-dark` variant: the darker of the manifest colors. The
+// background color is usually white, so when both are light the darker
+// one is dimmed until it suits a dark panel.
+fn dark_color(colors: &ManifestColors) -> Option<String> {
+    let darker = [colors.theme.as_deref(), colors.background.as_deref()]
+        .into_iter()
+        .flatten()
+        .min_by(|a, b| luminance(a).total_cmp(&luminance(b)))?;
+
+    if luminance(darker) <= 96.0 {
+        return Some(darker.to_string());
+    }
+
+    let scale = 96.0 / luminance(darker);
+    let [red, green, blue] = channels(darker).map(|channel| (channel as f32 * scale) as u8);
+
+    Some(format!("#{:02x}{:02x}{:02x}", red, green, blue))
+}
+
+pub fn letter_icon_svg(name: &str, color: &str) -> String {
+    let initials = initials(name);
+    let font_size = if initials.chars().count() > 1 {
+        112
+    } else {
+        144
+    };
+
+    format!(
+        concat!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{size}\" height=\"{size}\" ",
+            "viewBox=\"0 0 {size} {size}\">",
+            "<rect width=\"{size}\" height=\"{size}\" rx=\"48\" fill=\"{color}\"/>",
+            "<text x=\"50%\" y=\"50%\" dy=\"0.35em\" text-anchor=\"middle\" ",
+            "font-family=\"sans-serif\" font-weight=\"bold\" font-size=\"{font_size}\" ",
+            "fill=\"{text}\">{initials}</text>",
+            "</svg>"
+        ),
+        size = SIZE,
+        color = color,
+        text = text_color(color),
+        font_size = font_size,
+        initials = initials,
+    )
+}
+
+// Fallback for sites without any usable icon. With manifest colors a `-dark`
+// variant is saved too, on the darker of them, and the variant matching
+// the panel is returned. Returns path of the saved SVG.
+pub fn save_letter_icon(
+    name: &str,
+    url: &str,
+    icon_name: &str,
+    colors: Option<&ManifestColors>,
+    dark: bool,
+) -> Result<String> {
+    let host = Url::parse(url)
+        .ok()
+        .and_then(|url| normalized_host(&url))
+        .unwrap_or_else(|| name.to_string());
+
+    let location = ensure_icons_writable()?;
+    let icon_name = sanitize_icon_name(icon_name);
+
+    let light_color = colors
+        .and_then(|colors| colors.theme.as_deref())
+        .unwrap_or(background_color(&host));
+    let path = location.join(format!("{}.svg", icon_name));
+    write(&path, letter_icon_svg(name, light_color))?;
+
+    let Some(dark_color) = colors.and_then(dark_color) else {
+        return Ok(path.to_string_lossy().to_string());
+    };
+
+    let dark_path = location.join(format!("{}-dark.svg", icon_name));
+    write(&dark_path, letter_icon_svg(name, &dark_color))?;
+
+    Ok(match dark {
+        true => dark_path,
+        false => path,
+    }
+    .to_string_lossy()
+    .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::qwa_icons_location;
+
+    #[test]
+    fn initials_of_the_first_words() {
+        assert_eq!(initials("web apps"), "WA");
+        assert_eq!(initials("GitHub"), "G");
+        assert_eq!(initials("my-cool app"), "MC");
+        assert_eq!(initials("!!"), "?");
+    }
+
+    #[test]
+    fn background_color_depends_only_on_host() {
+        assert_eq!(
+            background_color("Example.com"),
+            background_color("example.com")
+        );
+        assert!(PALETTE.contains(&background_color("example.com")));
+    }
+
+    #[test]
+    fn text_is_readable_on_background() {
+        assert_eq!(text_color("#ffffff"), "#000000");
+        assert_eq!(text_color("#ff0"), "#000000");
+        assert_eq!(text_color("#000000"), "#ffffff");
+        assert_eq!(text_color("#1e88e5"), "#ffffff");
+    }
+
+    #[test]
+    fn dark_variant_uses_a_dark_color() {
+        let colors = |theme: Option<&str>, background: Option<&str>| ManifestColors {
+            theme: theme.map(String::from),
+            background: background.map(String::from),
+        };
+
+        assert_eq!(
+            dark_color(&colors(Some("#24292e"), Some("#ffffff"))).as_deref(),
+            Some("#24292e")
+        );
+        assert_eq!(dark_color(&colors(None, None)), None);
+
+        let dimmed = dark_color(&colors(Some("#ffffff"), Some("#eeeeee"))).unwrap();
+        assert!(luminance(&dimmed) <= 96.0);
+        assert_eq!(text_color(&dimmed), "#ffffff");
+    }
+
+    #[test]
+    fn letter_icon_is_valid_and_deterministic() {
+        let _root = crate::testing::data_root();
+
+        let svg = letter_icon_svg("Web Apps", "#1e88e5");
+        let tree = usvg::Tree::from_str(&svg, &usvg::Options::default()).unwrap();
+        assert_eq!(tree.size().width(), SIZE as f32);
+        assert!(svg.contains(">WA</text>"));
+        assert!(svg.contains("fill=\"#ffffff\""));
+
+        let path = save_letter_icon(
+            "Web Apps",
+            "https://example.com",
+            "Example1234",
+            None,
+            false,
+        )
+        .unwrap();
+        let first = std::fs::read_to_string(&path).unwrap();
+        save_letter_icon(
+            "Web Apps",
+            "https://Example.com/other",
+            "Example1234",
+            None,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            std::path::Path::new(&path),
+            qwa_icons_location().unwrap().join("Example1234.svg")
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), first);
+        assert_eq!(
+            first,
+            letter_icon_svg("Web Apps", background_color("example.com"))
+        );
+    }
+}
@@ -1,10 +1,15 @@
 mod browser;
+mod cli;
 mod common;
 mod config;
+mod error;
 mod favicon;
 mod launcher;
+mod lettericon;
 mod localize;
 mod pages;
+#[cfg(test)]
+mod testing;
 mod themes;
 
 use common::icons_location;
@@ -18,6 +23,7 @@ use tokio::{fs::File, io::AsyncWriteExt, process::Child};
 
 lazy_static! {
     pub static ref LOCALES: Vec<String> = get_languages_from_env();
+    pub static ref ARGS: cli::Args = cli::Args::parse();
 }
 
 pub const REPOSITORY: &str = env!("CARGO_PKG_REPOSITORY");
@@ -43,11 +49,180 @@ fn init_logging() {
         .init();
 }
 
+// Whether the installed web apps now match the manifest.
+fn reconcile_manifest(path: &std::path::Path) -> bool {
+    let manifest = match std::fs::read_to_string(path) {
+        Ok(content) => ron::from_str::<Vec<launcher::WebAppLauncher>>(&content),
+        Err(err) => {
+            eprintln!("can't read {:?}: {}", path, err);
+            return false;
+        }
+    };
+
+    let manifest = match manifest {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            eprintln!("invalid manifest {:?}: {}", path, err);
+            return false;
+        }
+    };
+
+    let report = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime.block_on(launcher::reconcile(&manifest, ARGS.prune)),
+        Err(err) => {
+            eprintln!("{}", err);
+            return false;
+        }
+    };
+
+    println!("created: {}", report.created.join(", "));
+    println!("updated: {}", report.updated.join(", "));
+    println!("removed: {}", report.removed.join(", "));
+    println!("skipped: {}", report.skipped.join(", "));
+    println!("failed: {}", report.failed.join(", "));
+
+    report.succeeded()
+}
+
+fn audit_icons() {
+    let audits = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime.block_on(launcher::audit_icons(launcher::MIN_ICON_SIZE)),
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    for audit in &audits {
+        let issue = match audit.issue {
+            launcher::IconIssue::Missing => String::from("missing"),
+            launcher::IconIssue::Broken => String::from("can't be decoded"),
+            launcher::IconIssue::LowResolution(width, height) => {
+                format!("low resolution, {}x{}", width, height)
+            }
+        };
+
+        println!(
+            "{} ({}): {} - {}",
+            audit.name, audit.codename, audit.icon, issue
+        );
+    }
+
+    println!("{} icons to refresh", audits.len());
+}
+
+fn upgrade_icons() {
+    let upgraded = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime.block_on(launcher::upgrade_icons()),
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    println!("upgraded: {}", upgraded.join(", "));
+}
+
+// Whether the bundle was written.
+fn export_bundle(codename: &str) -> bool {
+    let path = std::path::PathBuf::from(format!("{}.{}", codename, launcher::BUNDLE_EXTENSION));
+
+    match launcher::export_bundle(codename, &path, ARGS.with_profile) {
+        Ok(()) => {
+            println!("exported {} to {}", codename, path.display());
+            true
+        }
+        Err(err) => {
+            eprintln!("can't export {}: {}", codename, err);
+            false
+        }
+    }
+}
+
+// Whether the web app of the bundle was installed.
+fn import_bundle(path: &std::path::Path) -> bool {
+    let result = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime.block_on(launcher::import_bundle(path)),
+        Err(err) => {
+            eprintln!("{}", err);
+            return false;
+        }
+    };
+
+    match result {
+        Ok(webapp) => {
+            println!("imported {} ({})", webapp.name, webapp.codename);
+            true
+        }
+        Err(err) => {
+            eprintln!("can't import {:?}: {}", path, err);
+            false
+        }
+    }
+}
+
 fn main() -> cosmic::iced::Result {
     init_logging();
 
     init_localizer();
 
+    if ARGS.list_browsers {
+        for (model, info) in browser::browser_models() {
+            println!(
+                "{} ({}): engine {:?}, private mode: {} ({}), profile: {}, pwa install: {}, navbar: {}, new windows as tabs: {}, single instance: {}, badges: {}",
+                info.name,
+                model.as_ref(),
+                info.engine,
+                info.supports_private,
+                info.private_flag,
+                info.profile_flag,
+                info.supports_pwa,
+                info.supports_navbar,
+                info.supports_new_windows_as_tabs,
+                info.single_instance,
+                info.supports_badges
+            );
+        }
+
+        return Ok(());
+    }
+
+    if ARGS.audit_icons {
+        audit_icons();
+
+        return Ok(());
+    }
+
+    if ARGS.upgrade_icons {
+        upgrade_icons();
+
+        return Ok(());
+    }
+
+    if let Some(codename) = &ARGS.export {
+        if !export_bundle(codename) {
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
+    if let Some(path) = &ARGS.import {
+        if !import_bundle(path) {
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
+    if let Some(manifest) = &ARGS.reconcile {
+        if !reconcile_manifest(manifest) {
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
     let mut settings = Settings::default();
     settings = settings.size(Size {
         width: 920.,
@@ -70,8 +245,12 @@ pub fn icon_pack_installed() -> bool {
     let packs: Vec<&str> = vec!["Papirus", "Papirus-Dark", "Papirus-Light"];
     let mut directories = 0;
 
+    let Ok(location) = icons_location() else {
+        return false;
+    };
+
     for theme in packs.iter() {
-        let mut icons_dir = icons_location();
+        let mut icons_dir = location.clone();
         icons_dir.push(theme);
 
         if icons_dir.exists() {
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
 use cosmic::{
     iced::{alignment::Vertical, futures::executor::block_on, Length},
@@ -6,16 +6,24 @@ use cosmic::{
     widget::{self},
     Element, Task,
 };
-use rand::{rng, Rng};
 use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
 use crate::{
-    browser::{installed_browsers, Browser, BrowserModel},
-    common::{self, image_handle, move_icon, url_valid, Icon, IconType},
-    fl,
-    launcher::{webapplauncher_is_valid, WebAppLauncher},
+    browser::{default_browser, installed_browsers, Browser, BrowserModel, Engine, ProfileSource},
+    common::{
+        self, image_handle, move_icon, strip_tracking_params, url_valid, Icon, IconType,
+        ReachStatus,
+    },
+    config::AppConfig,
+    favicon, fl,
+    launcher::{
+        find_duplicate, find_packaging_conflict, foreign_webapps, installed_webapps,
+        parse_profile_dir, parse_window_position, parse_window_size, release_codename,
+        reserve_codename, split_schemes, split_urls, webapplauncher_is_valid, ForeignWebApp,
+        WebAppLauncher, VERSION,
+    },
     pages,
 };
 
@@ -113,76 +121,171 @@ impl Category {
 pub struct AppEditor {
     pub app_codename: String,
     pub app_title: String,
+    pub app_window_title: String,
     pub app_url: String,
+    pub app_extra_urls: String,
     pub app_icon: String,
+    pub app_icon_source: String,
     pub app_parameters: String,
     pub app_category: Category,
     pub app_browser: Option<Browser>,
     pub app_navbar: bool,
+    pub app_new_windows_as_tabs: bool,
     pub app_incognito: bool,
     pub app_isolated: bool,
+    pub app_pwa: bool,
+    pub app_profile_source: ProfileSource,
+    pub app_profile_dir: String,
+    // kept as it is, there's no editing of it
+    pub app_metadata: BTreeMap<String, String>,
+    pub app_single_window: bool,
+    pub app_badges: bool,
+    // `WIDTHxHEIGHT`, empty for the browser's default
+    pub app_window_size: String,
+    pub app_maximized: bool,
+    // `X,Y` of the desktop, empty to let the window manager decide
+    pub app_window_position: String,
+    pub app_url_schemes: String,
+    pub url_status: Option<ReachStatus>,
+    // installed web app with the same url and browser, found when creating
+    pub duplicate: Option<WebAppLauncher>,
+    // same page opened by desktop files of other tools
+    pub foreign_webapps: Vec<ForeignWebApp>,
+    // same page in the same browser of another packaging, see `find_packaging_conflict`
+    pub packaging_conflict: Option<WebAppLauncher>,
+    // why the last Done failed, the icon install or writing the launcher
+    pub save_error: Option<String>,
+    pub allow_duplicate: bool,
     pub selected_icon: Option<Icon>,
     pub browsers: Vec<Browser>,
     pub browser_idx: Option<usize>,
     pub categories: Vec<String>,
     pub category_idx: Option<usize>,
-    //pub is_installed: bool,
+    pub profile_sources: Vec<String>,
+    pub is_installed: bool,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     Arguments(String),
+    Badges(bool),
     Browser(usize),
     Category(usize),
+    CreateAnyway,
     Done,
+    EditExisting,
+    ExtraUrls(String),
+    ForeignWebApps(Vec<ForeignWebApp>),
     Incognito(bool),
     IsolatedProfile(bool),
-    //LaunchApp,
+    LaunchApp,
+    Maximized(bool),
     Navbar(bool),
+    NewWindowsAsTabs(bool),
     OpenIconPicker(String),
+    PackagingConflict(Option<WebAppLauncher>),
+    PageTitle(String),
+    ProfileDir(String),
+    ProfileSource(usize),
+    Pwa(bool),
+    SaveFailed(String),
     SearchFavicon,
+    SingleWindow(bool),
     Title(String),
     Url(String),
+    UrlChecked(ReachStatus),
+    UrlSchemes(String),
+    UseRedirect(String),
+    VerifyUrl,
+    WindowPosition(String),
+    WindowSize(String),
+    WindowTitle(String),
 }
 
 impl AppEditor {
     pub fn new() -> Self {
         let browsers = installed_browsers();
-        let browser = if !browsers.is_empty() {
-            Some(browsers[0].clone())
-        } else {
-            None
-        };
+        let browser_idx = default_browser(&browsers).unwrap_or_default();
+        let browser = browsers.get(browser_idx).cloned();
 
         let categories = Category::iter().map(|c| c.name()).collect::<Vec<String>>();
 
         AppEditor {
             app_codename: String::new(),
             app_title: String::new(),
+            app_window_title: String::new(),
             app_url: String::new(),
+            app_extra_urls: String::new(),
             app_icon: String::new(),
+            app_icon_source: String::new(),
             app_parameters: String::new(),
             app_category: Category::default(),
             app_browser: browser,
             app_navbar: false,
+            app_new_windows_as_tabs: false,
             app_incognito: false,
-            app_isolated: true,
+            app_isolated: AppConfig::config().isolate_profiles,
+            app_pwa: false,
+            app_profile_source: ProfileSource::default(),
+            app_profile_dir: String::new(),
+            app_metadata: BTreeMap::new(),
+            app_single_window: false,
+            app_badges: false,
+            app_window_size: String::new(),
+            app_maximized: false,
+            app_window_position: String::new(),
+            app_url_schemes: String::new(),
+            url_status: None,
+            duplicate: None,
+            foreign_webapps: Vec::new(),
+            packaging_conflict: None,
+            save_error: None,
+            allow_duplicate: false,
             selected_icon: None,
             browsers,
-            browser_idx: Some(0),
+            browser_idx: Some(browser_idx),
             categories,
             category_idx: Some(0),
-            //is_installed: false,
+            profile_sources: ProfileSource::iter().map(|s| s.name()).collect(),
+            is_installed: false,
         }
     }
 
+    // Form filled in from an url handed over by a browser or the command line,
+    // `Message::VerifyUrl` afterwards follows redirects and looks up the icon.
+    pub fn prefill_from_url(url: &str, title: Option<&str>) -> Self {
+        let mut editor = Self::new();
+
+        editor.app_url = url.to_string();
+        editor.app_title = match title {
+            Some(title) if !title.trim().is_empty() => title.trim().to_string(),
+            _ => common::default_name_from_url(url),
+        };
+
+        editor
+    }
+
     pub fn from(webapp_launcher: WebAppLauncher) -> Self {
         let category_idx = Category::iter().position(|c| c == webapp_launcher.category);
         let category = Category::from_index(category_idx.unwrap_or_default() as u8);
         let categories = Category::to_vec();
 
-        let selected_icon = block_on(image_handle(webapp_launcher.icon.clone()));
-        let browsers = installed_browsers();
+        let selected_icon = block_on(image_handle(common::icon_path(&webapp_launcher.icon)));
+        let is_installed =
+            common::desktop_file_for(&webapp_launcher.codename).is_ok_and(|path| path.exists());
+        let mut browsers = installed_browsers();
+
+        // a browser hidden after the web app was made stays selectable for it
+        if webapp_launcher
+            .browser
+            .is_hidden(&AppConfig::config().hidden_browsers)
+            && !browsers
+                .iter()
+                .any(|b| b.model == webapp_launcher.browser.model)
+        {
+            browsers.push(webapp_launcher.browser.clone());
+        }
+
         let browser_idx = browsers
             .iter()
             .position(|b| b.model == webapp_launcher.browser.model);
@@ -190,20 +293,51 @@ impl AppEditor {
         Self {
             app_codename: webapp_launcher.codename,
             app_title: webapp_launcher.name,
+            app_window_title: webapp_launcher.window_title,
             app_url: webapp_launcher.url,
+            app_extra_urls: webapp_launcher.extra_urls.join(" "),
             app_icon: webapp_launcher.icon,
+            app_icon_source: webapp_launcher.icon_source,
             app_parameters: webapp_launcher.custom_parameters,
             app_category: category,
             app_browser: Some(webapp_launcher.browser),
             app_navbar: webapp_launcher.navbar,
+            app_new_windows_as_tabs: webapp_launcher.new_windows_as_tabs,
             app_incognito: webapp_launcher.is_incognito,
             app_isolated: webapp_launcher.isolate_profile,
+            app_pwa: webapp_launcher.pwa,
+            app_profile_source: webapp_launcher.profile_source,
+            app_profile_dir: webapp_launcher
+                .profile_dir
+                .as_ref()
+                .map(|dir| dir.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            app_metadata: webapp_launcher.metadata.clone(),
+            app_single_window: webapp_launcher.single_window,
+            app_badges: webapp_launcher.badges,
+            app_window_size: webapp_launcher
+                .window_size
+                .map(|(width, height)| format!("{}x{}", width, height))
+                .unwrap_or_default(),
+            app_maximized: webapp_launcher.maximized,
+            app_window_position: webapp_launcher
+                .window_position
+                .map(|(x, y)| format!("{},{}", x, y))
+                .unwrap_or_default(),
+            app_url_schemes: webapp_launcher.url_schemes.join(" "),
+            url_status: None,
+            duplicate: None,
+            foreign_webapps: Vec::new(),
+            packaging_conflict: None,
+            save_error: None,
+            allow_duplicate: false,
             selected_icon,
             browsers,
             browser_idx,
             categories,
             category_idx,
-            //is_installed: installed,
+            profile_sources: ProfileSource::iter().map(|s| s.name()).collect(),
+            is_installed,
         }
     }
 
@@ -212,23 +346,82 @@ impl AppEditor {
             Message::Arguments(args) => {
                 self.app_parameters = args;
             }
+            Message::Badges(flag) => {
+                self.app_badges = flag;
+            }
             Message::Browser(idx) => {
                 self.browser_idx = Some(idx);
                 self.app_browser = Some(self.browsers[idx].clone());
+
+                return self.check_packaging_conflict();
             }
             Message::Category(idx) => {
                 self.app_category = Category::from_index(idx as u8);
                 self.category_idx = Some(idx);
             }
+            Message::CreateAnyway => {
+                self.allow_duplicate = true;
+                self.duplicate = None;
+
+                return task::message(pages::Message::Editor(Message::Done));
+            }
             Message::Done => {
-                if self.app_codename.is_empty() {
-                    self.app_codename = format!(
-                        "{}{}",
-                        &self.app_title.replace(' ', ""),
-                        rng().random_range(1000..10000)
-                    );
+                self.save_error = None;
+
+                // the browser's own password manager keeps them instead
+                if let Some(url) = common::without_credentials(&self.app_url) {
+                    tracing::warn!("removed credentials from the url of {}", self.app_title);
+                    self.app_url = url;
+                }
+
+                let reserved = self.app_codename.is_empty();
+
+                if reserved {
+                    if !self.allow_duplicate {
+                        let browser_appid = self
+                            .app_browser
+                            .as_ref()
+                            .and_then(|browser| browser.entry.as_ref())
+                            .map(|entry| entry.appid.clone())
+                            .unwrap_or_default();
+
+                        if let Some(existing) = find_duplicate(&self.app_url, &browser_appid) {
+                            self.duplicate = Some(existing);
+                            return Task::none();
+                        }
+                    }
+
+                    self.app_codename = reserve_codename(&self.app_title);
+                }
+                let config = AppConfig::config();
+                let icon_format = config.icon_format;
+
+                if config.strip_tracking_params {
+                    self.app_url = strip_tracking_params(&self.app_url, &config.tracking_params);
                 }
-                let icon_final_path = block_on(move_icon(&self.app_icon, &self.app_codename));
+
+                let installed_icon = common::is_installed_icon(&self.app_icon, &self.app_codename);
+
+                // editing an app without changing its icon keeps the installed one,
+                // icons referenced by their theme name are never copied
+                let named_icon = !self.app_icon.contains('/') && !url_valid(&self.app_icon);
+                let icon_final_path = if named_icon || installed_icon {
+                    self.app_icon.clone()
+                } else {
+                    match block_on(move_icon(&self.app_icon, &self.app_codename, icon_format)) {
+                        Ok(path) => path,
+                        Err(err) => {
+                            tracing::error!("failed to save icon: {}", err);
+                            self.save_error =
+                                Some(fl!("icon-save-failed", error = err.to_string()));
+                            if reserved {
+                                release_codename(&self.app_codename);
+                                self.app_codename.clear();
+                            }
+                            return Task::none();
+                        }
+                    }
+                };
 
                 if webapplauncher_is_valid(&icon_final_path, &self.app_title, &self.app_url) {
                     if let Some(browser) = &self.app_browser {
@@ -238,27 +431,70 @@ impl AppEditor {
                                 codename: self.app_codename.clone(),
                                 browser: browser.clone(),
                                 name: self.app_title.clone(),
+                                window_title: self.app_window_title.clone(),
                                 icon: icon_final_path,
+                                icon_source: self.app_icon_source.clone(),
                                 category: self.app_category.clone(),
                                 url: self.app_url.clone(),
+                                extra_urls: split_urls(&self.app_extra_urls),
                                 custom_parameters: self.app_parameters.clone(),
                                 isolate_profile: self.app_isolated,
                                 navbar: self.app_navbar,
+                                new_windows_as_tabs: self.app_new_windows_as_tabs,
                                 is_incognito: self.app_incognito,
+                                pwa: self.app_pwa,
+                                profile_source: self.app_profile_source,
+                                single_window: self.app_single_window,
+                                badges: self.app_badges,
+                                window_size: parse_window_size(&self.app_window_size),
+                                maximized: self.app_maximized,
+                                window_position: parse_window_position(&self.app_window_position),
+                                url_schemes: split_schemes(&self.app_url_schemes),
+                                profile_dir: parse_profile_dir(&self.app_profile_dir),
+                                version: VERSION.to_string(),
+                                metadata: self.app_metadata.clone(),
                             });
 
                             let arc_launcher = Arc::clone(&launcher);
 
                             return task::future(async move {
-                                if arc_launcher.create().await.is_ok() {
-                                    pages::Message::SaveLauncher(arc_launcher)
-                                } else {
-                                    pages::Message::None
+                                let result = arc_launcher.upsert().await;
+                                release_codename(&arc_launcher.codename);
+
+                                match result {
+                                    Ok(outcome) => {
+                                        tracing::info!("{:?} {}", outcome, arc_launcher.codename);
+                                        pages::Message::SaveLauncher(arc_launcher)
+                                    }
+                                    Err(err) => {
+                                        tracing::error!("failed to create web app: {}", err);
+                                        pages::Message::Editor(Message::SaveFailed(fl!(
+                                            "create-failed",
+                                            error = err.to_string()
+                                        )))
+                                    }
                                 }
                             });
                         };
                     }
                 }
+
+                // nothing was created, the codename is free again
+                if reserved {
+                    release_codename(&self.app_codename);
+                    self.app_codename.clear();
+                }
+            }
+            Message::EditExisting => {
+                if let Some(existing) = self.duplicate.take() {
+                    *self = AppEditor::from(existing);
+                }
+            }
+            Message::ExtraUrls(urls) => {
+                self.app_extra_urls = urls;
+            }
+            Message::ForeignWebApps(found) => {
+                self.foreign_webapps = found;
             }
             Message::Incognito(flag) => {
                 self.app_incognito = flag;
@@ -266,27 +502,73 @@ impl AppEditor {
             Message::IsolatedProfile(flag) => {
                 self.app_isolated = flag;
             }
-            //Message::LaunchApp => {
-            //let app_id = Arc::new(self.app_codename.clone());
-            //let cloned_id = Arc::clone(&app_id);
-            //return task::future(async move {
-            //    launch_webapp(cloned_id).await.unwrap();
-            //
-            //    pages::Message::None
-            //});
-            //}
+            Message::LaunchApp => {
+                let codename = self.app_codename.clone();
+
+                return task::future(async move {
+                    // the installed web app, without unsaved changes of the editor
+                    match installed_webapps()
+                        .into_iter()
+                        .find(|webapp| webapp.codename == codename)
+                    {
+                        Some(webapp) => {
+                            if let Err(err) = webapp.launch() {
+                                tracing::error!("failed to launch {}: {}", codename, err);
+                            }
+                        }
+                        None => tracing::warn!("{} isn't installed", codename),
+                    }
+
+                    pages::Message::None
+                });
+            }
+            Message::Maximized(flag) => {
+                self.app_maximized = flag;
+            }
             Message::Navbar(flag) => {
                 self.app_navbar = flag;
             }
+            Message::NewWindowsAsTabs(flag) => {
+                self.app_new_windows_as_tabs = flag;
+            }
             Message::OpenIconPicker(app_url) => {
                 return task::future(async { pages::Message::OpenIconPicker(app_url) })
             }
+            Message::PackagingConflict(found) => {
+                self.packaging_conflict = found;
+            }
+            Message::PageTitle(title) => {
+                // the user may have typed a name meanwhile
+                if self.title_is_derived() {
+                    self.app_title = title;
+                }
+            }
+            Message::ProfileDir(dir) => {
+                self.app_profile_dir = dir;
+            }
+            Message::ProfileSource(idx) => {
+                if let Some(source) = ProfileSource::iter().nth(idx) {
+                    self.app_profile_source = source;
+                }
+            }
+            Message::Pwa(flag) => {
+                self.app_pwa = flag;
+            }
+            Message::SaveFailed(error) => {
+                self.save_error = Some(error);
+
+                // the released codename may be taken meanwhile, a retry reserves another
+                if !self.is_installed {
+                    self.app_codename.clear();
+                }
+            }
             Message::SearchFavicon => {
-                let name = common::get_icon_name_from_url(&self.app_url);
-                let url = self.app_url.clone();
+                let url = self.final_url();
+                let name = common::get_icon_name_from_url(&url);
+                let options = common::IconSearchOptions::from_config();
 
-                return task::future(async {
-                    let paths = common::find_icons(name, url).await;
+                return task::future(async move {
+                    let paths = common::find_icons(name, url, options).await;
 
                     for path in paths {
                         if let Some(icon) = image_handle(path).await {
@@ -296,11 +578,99 @@ impl AppEditor {
                     pages::Message::None
                 });
             }
+            Message::SingleWindow(flag) => {
+                self.app_single_window = flag;
+            }
             Message::Title(title) => {
                 self.app_title = title;
             }
             Message::Url(url) => {
                 self.app_url = url;
+                self.url_status = None;
+                self.duplicate = None;
+                self.foreign_webapps.clear();
+                self.packaging_conflict = None;
+                self.allow_duplicate = false;
+            }
+            Message::UrlChecked(status) => {
+                if let ReachStatus::RedirectedTo(target) = &status {
+                    if self.title_is_derived() {
+                        self.app_title = common::default_name_from_url(target);
+                    }
+                }
+
+                let reachable =
+                    !matches!(status, ReachStatus::NotFound | ReachStatus::NetworkError(_));
+                self.url_status = Some(status);
+
+                let mut tasks = Vec::new();
+
+                if reachable && self.title_is_derived() {
+                    let url = self.final_url();
+                    let offline = common::IconSearchOptions::from_config().offline;
+
+                    // the manifest's name beats the page title, which often
+                    // carries a slogan or the current page
+                    tasks.push(task::future(async move {
+                        let name = match offline {
+                            true => None,
+                            false => favicon::manifest_name(&url).await,
+                        };
+                        let name = match name {
+                            Some(name) => Some(name),
+                            None => common::fetch_page_title(&url).await,
+                        };
+
+                        match name {
+                            Some(title) => pages::Message::Editor(Message::PageTitle(title)),
+                            None => pages::Message::None,
+                        }
+                    }));
+                }
+
+                if reachable && self.app_icon.is_empty() {
+                    tasks.push(task::message(pages::Message::Editor(
+                        Message::SearchFavicon,
+                    )));
+                }
+
+                return Task::batch(tasks);
+            }
+            Message::UrlSchemes(schemes) => {
+                self.app_url_schemes = schemes;
+            }
+            Message::UseRedirect(url) => {
+                if self.title_is_derived() {
+                    self.app_title = common::default_name_from_url(&url);
+                }
+                self.app_url = url;
+                self.url_status = Some(ReachStatus::Reachable);
+            }
+            Message::VerifyUrl => {
+                let url = self.app_url.clone();
+
+                let foreign_url = url.clone();
+
+                return Task::batch([
+                    task::future(async move {
+                        let status = common::check_url_reachable(&url).await;
+                        pages::Message::Editor(Message::UrlChecked(status))
+                    }),
+                    task::future(async move {
+                        let found = foreign_webapps(&foreign_url);
+                        pages::Message::Editor(Message::ForeignWebApps(found))
+                    }),
+                    self.check_packaging_conflict(),
+                ]);
+            }
+            Message::WindowPosition(position) => {
+                self.app_window_position = position;
+            }
+            Message::WindowSize(size) => {
+                self.app_window_size = size;
+            }
+            Message::WindowTitle(title) => {
+                self.app_window_title = title;
             }
         }
         Task::none()
@@ -309,10 +679,80 @@ impl AppEditor {
     pub fn update_icon(&mut self, icon: Option<Icon>) {
         if let Some(icon) = icon {
             self.app_icon = icon.path.clone();
+            self.app_icon_source = icon.path.clone();
             self.selected_icon = Some(icon);
         }
     }
 
+    // Where the typed url really leads to, once verified. Icons are looked up there,
+    // so e.g. twitter.com gets the icon of x.com it redirects to.
+    fn final_url(&self) -> String {
+        match &self.url_status {
+            Some(ReachStatus::RedirectedTo(target)) => target.clone(),
+            _ => self.app_url.clone(),
+        }
+    }
+
+    // Looks for a web app of the same page in the same browser model
+    // installed from another source, which keeps a separate profile.
+    fn check_packaging_conflict(&self) -> Task<pages::Message> {
+        let Some(browser) = self.app_browser.clone() else {
+            return Task::none();
+        };
+        let url = self.app_url.clone();
+        let codename = self.app_codename.clone();
+
+        task::future(async move {
+            // the edited web app itself isn't a conflict
+            let found = find_packaging_conflict(&url, &browser)
+                .filter(|existing| existing.codename != codename);
+
+            if let Some(existing) = &found {
+                tracing::warn!(
+                    "{} opens {} in {} with a separate profile",
+                    existing.codename,
+                    url,
+                    existing.browser.name
+                );
+            }
+
+            pages::Message::Editor(Message::PackagingConflict(found))
+        })
+    }
+
+    // True while the title wasn't typed by the user, so it can follow the url.
+    fn title_is_derived(&self) -> bool {
+        let title = self.app_title.trim();
+
+        title.is_empty()
+            || title == common::default_name_from_url(&self.app_url)
+            || title == common::default_name_from_url(&self.final_url())
+    }
+
+    fn url_status_element(&self) -> Option<Element<Message>> {
+        let status = self.url_status.as_ref()?;
+
+        let element = match status {
+            ReachStatus::Reachable => widget::text(fl!("url-reachable")).into(),
+            ReachStatus::RedirectedTo(target) => widget::settings::item_row(vec![
+                widget::text(fl!("url-redirected", url = target.as_str())).into(),
+                widget::horizontal_space().into(),
+                widget::button::standard(fl!("use-redirect"))
+                    .on_press(Message::UseRedirect(target.clone()))
+                    .into(),
+            ])
+            .into(),
+            ReachStatus::AuthRequired => widget::text(fl!("url-auth-required")).into(),
+            ReachStatus::NotFound => widget::text(fl!("url-not-found")).into(),
+            ReachStatus::NetworkError(err) => {
+                widget::text(fl!("url-network-error", error = err.as_str())).into()
+            }
+            ReachStatus::Skipped => widget::text(fl!("url-check-skipped")).into(),
+        };
+
+        Some(element)
+    }
+
     fn icon_element(&self, icon: Option<Icon>) -> Element<Message> {
         let ico = if let Some(ico) = icon {
             match ico.icon {
@@ -377,6 +817,13 @@ impl AppEditor {
                             widget::text_input::inline_input(fl!("url"), &self.app_url)
                                 .on_input(Message::Url)
                                 .into(),
+                            widget::button::standard(fl!("verify-url"))
+                                .on_press_maybe(if url_valid(&self.app_url) {
+                                    Some(Message::VerifyUrl)
+                                } else {
+                                    None
+                                })
+                                .into(),
                             widget::button::standard(fl!("download-favicon"))
                                 .on_press_maybe(if url_valid(&self.app_url) {
                                     Some(Message::SearchFavicon)
@@ -385,6 +832,54 @@ impl AppEditor {
                                 })
                                 .into(),
                         ]))
+                        .add_maybe(self.url_status_element())
+                        .add_maybe(if self.foreign_webapps.is_empty() {
+                            None
+                        } else {
+                            let apps = self
+                                .foreign_webapps
+                                .iter()
+                                .map(|app| format!("{} ({})", app.name, app.path.display()))
+                                .collect::<Vec<_>>()
+                                .join(", ");
+
+                            Some(widget::text(fl!("foreign-webapps", apps = apps)))
+                        })
+                        .add_maybe(self.packaging_conflict.as_ref().map(|existing| {
+                            widget::text(fl!(
+                                "packaging-conflict",
+                                name = existing.name.as_str(),
+                                browser = existing.browser.name.as_str()
+                            ))
+                        }))
+                        .add_maybe(if common::without_credentials(&self.app_url).is_some() {
+                            Some(widget::text(fl!("url-credentials")))
+                        } else {
+                            None
+                        })
+                        .add(
+                            widget::text_input::inline_input(
+                                fl!("extra-urls"),
+                                &self.app_extra_urls,
+                            )
+                            .on_input(Message::ExtraUrls),
+                        )
+                        .add(
+                            widget::text_input::inline_input(
+                                fl!("url-schemes"),
+                                &self.app_url_schemes,
+                            )
+                            .on_input(Message::UrlSchemes),
+                        )
+                        .add_maybe(
+                            if self.app_url_schemes.split_whitespace().count()
+                                != split_schemes(&self.app_url_schemes).len()
+                            {
+                                Some(widget::text(fl!("invalid-url-schemes")))
+                            } else {
+                                None
+                            },
+                        )
                         .add(widget::settings::item(
                             fl!("select-category"),
                             widget::dropdown(
@@ -405,15 +900,80 @@ impl AppEditor {
                             .on_input(Message::Arguments),
                         )
                         .add_maybe(if let Some(browser) = &self.app_browser {
-                            match browser.model {
-                                Some(BrowserModel::Firefox)
-                                | Some(BrowserModel::Zen)
-                                | Some(BrowserModel::Librewolf)
-                                | Some(BrowserModel::Waterfox) => widget::settings::item(
-                                    fl!("navbar"),
-                                    widget::toggler(self.app_navbar).on_toggle(Message::Navbar),
+                            match &browser.model {
+                                Some(model) if model.info().supports_navbar => {
+                                    widget::settings::item(
+                                        fl!("navbar"),
+                                        widget::toggler(self.app_navbar).on_toggle(Message::Navbar),
+                                    )
+                                    .into()
+                                }
+                                _ => None,
+                            }
+                        } else {
+                            None
+                        })
+                        .add_maybe(if let Some(browser) = &self.app_browser {
+                            match &browser.model {
+                                Some(model) if model.info().supports_new_windows_as_tabs => {
+                                    widget::settings::item(
+                                        fl!("new-windows-as-tabs"),
+                                        widget::toggler(self.app_new_windows_as_tabs)
+                                            .on_toggle(Message::NewWindowsAsTabs),
+                                    )
+                                    .into()
+                                }
+                                _ => None,
+                            }
+                        } else {
+                            None
+                        })
+                        .add_maybe(if let Some(browser) = &self.app_browser {
+                            match &browser.model {
+                                Some(BrowserModel::Firefox) => widget::settings::item(
+                                    fl!("profile-source"),
+                                    widget::dropdown(
+                                        &self.profile_sources,
+                                        ProfileSource::iter()
+                                            .position(|s| s == self.app_profile_source),
+                                        Message::ProfileSource,
+                                    ),
                                 )
                                 .into(),
+                                Some(model)
+                                    if model.chromium_config_dir().is_some()
+                                        && self.app_isolated =>
+                                {
+                                    widget::settings::item(
+                                        fl!("profile-source"),
+                                        widget::dropdown(
+                                            &self.profile_sources,
+                                            ProfileSource::iter()
+                                                .position(|s| s == self.app_profile_source),
+                                            Message::ProfileSource,
+                                        ),
+                                    )
+                                    .into()
+                                }
+                                _ => None,
+                            }
+                        } else {
+                            None
+                        })
+                        .add_maybe(if let Some(browser) = &self.app_browser {
+                            match (&browser.model, self.app_profile_source) {
+                                (Some(model), ProfileSource::CopyDefault)
+                                    if model.chromium_config_dir().is_some()
+                                        && self.app_isolated =>
+                                {
+                                    Some(widget::text(fl!("profile-extensions-copied")))
+                                }
+                                (Some(model), ProfileSource::ReuseDefault)
+                                    if model.chromium_config_dir().is_some()
+                                        && self.app_isolated =>
+                                {
+                                    Some(widget::text(fl!("profile-default-shared")))
+                                }
                                 _ => None,
                             }
                         } else {
@@ -423,11 +983,8 @@ impl AppEditor {
                             fl!("isolated-profile"),
                             widget::toggler(self.app_isolated).on_toggle_maybe(
                                 if let Some(browser) = &self.app_browser {
-                                    match browser.model {
-                                        Some(BrowserModel::Firefox)
-                                        | Some(BrowserModel::Zen)
-                                        | Some(BrowserModel::Librewolf)
-                                        | Some(BrowserModel::Waterfox) => {
+                                    match &browser.model {
+                                        Some(model) if model.info().supports_navbar => {
                                             if self.app_navbar {
                                                 Message::IsolatedProfile.into()
                                             } else {
@@ -441,23 +998,187 @@ impl AppEditor {
                                 },
                             ),
                         ))
+                        .add_maybe(if self.app_isolated {
+                            widget::text_input::inline_input(
+                                fl!("profile-dir"),
+                                &self.app_profile_dir,
+                            )
+                            .on_input(Message::ProfileDir)
+                            .into()
+                        } else {
+                            None
+                        })
+                        .add_maybe(
+                            if !self.app_profile_dir.trim().is_empty()
+                                && parse_profile_dir(&self.app_profile_dir).is_none()
+                            {
+                                Some(widget::text(fl!("invalid-profile-dir")))
+                            } else {
+                                None
+                            },
+                        )
+                        .add_maybe(if let Some(browser) = &self.app_browser {
+                            match &browser.model {
+                                Some(model) if model.info().engine == Engine::Gecko => {
+                                    widget::text_input::inline_input(
+                                        fl!("window-title"),
+                                        &self.app_window_title,
+                                    )
+                                    .on_input(Message::WindowTitle)
+                                    .into()
+                                }
+                                _ => None,
+                            }
+                        } else {
+                            None
+                        })
+                        .add_maybe(if let Some(browser) = &self.app_browser {
+                            match &browser.model {
+                                Some(model)
+                                    if matches!(
+                                        model.info().engine,
+                                        Engine::Chromium | Engine::Gecko
+                                    ) =>
+                                {
+                                    widget::text_input::inline_input(
+                                        fl!("window-size"),
+                                        &self.app_window_size,
+                                    )
+                                    .on_input(Message::WindowSize)
+                                    .into()
+                                }
+                                _ => None,
+                            }
+                        } else {
+                            None
+                        })
+                        .add_maybe(
+                            if !self.app_window_size.trim().is_empty()
+                                && parse_window_size(&self.app_window_size).is_none()
+                            {
+                                Some(widget::text(fl!("invalid-window-size")))
+                            } else {
+                                None
+                            },
+                        )
+                        .add_maybe(if let Some(browser) = &self.app_browser {
+                            match &browser.model {
+                                Some(model) if model.info().engine == Engine::Chromium => {
+                                    widget::settings::item(
+                                        fl!("start-maximized"),
+                                        widget::toggler(self.app_maximized)
+                                            .on_toggle(Message::Maximized),
+                                    )
+                                    .into()
+                                }
+                                _ => None,
+                            }
+                        } else {
+                            None
+                        })
+                        .add_maybe(if let Some(browser) = &self.app_browser {
+                            match &browser.model {
+                                Some(model) if model.info().engine == Engine::Chromium => {
+                                    widget::text_input::inline_input(
+                                        fl!("window-position"),
+                                        &self.app_window_position,
+                                    )
+                                    .on_input(Message::WindowPosition)
+                                    .into()
+                                }
+                                _ => None,
+                            }
+                        } else {
+                            None
+                        })
+                        .add_maybe(
+                            if !self.app_window_position.trim().is_empty()
+                                && parse_window_position(&self.app_window_position).is_none()
+                            {
+                                Some(widget::text(fl!("invalid-window-position")))
+                            } else {
+                                None
+                            },
+                        )
+                        .add_maybe(if let Some(browser) = &self.app_browser {
+                            match &browser.model {
+                                Some(model) if model.info().supports_pwa => widget::settings::item(
+                                    fl!("install-pwa"),
+                                    widget::toggler(self.app_pwa).on_toggle(Message::Pwa),
+                                )
+                                .into(),
+                                _ => None,
+                            }
+                        } else {
+                            None
+                        })
+                        .add_maybe(if let Some(browser) = &self.app_browser {
+                            match &browser.model {
+                                Some(model)
+                                    if model.info().single_instance && self.app_isolated =>
+                                {
+                                    widget::settings::item(
+                                        fl!("single-window"),
+                                        widget::toggler(self.app_single_window)
+                                            .on_toggle(Message::SingleWindow),
+                                    )
+                                    .into()
+                                }
+                                _ => None,
+                            }
+                        } else {
+                            None
+                        })
+                        .add_maybe(if let Some(browser) = &self.app_browser {
+                            match &browser.model {
+                                Some(model) if model.info().supports_badges => {
+                                    widget::settings::item(
+                                        fl!("badges"),
+                                        widget::toggler(self.app_badges).on_toggle(Message::Badges),
+                                    )
+                                    .into()
+                                }
+                                _ => None,
+                            }
+                        } else {
+                            None
+                        })
                         .add(widget::settings::item(
                             fl!("private-mode"),
                             widget::toggler(self.app_incognito).on_toggle(Message::Incognito),
                         )),
                 )
+                .push_maybe(self.duplicate.as_ref().map(|existing| {
+                    widget::row()
+                        .spacing(8)
+                        .align_y(Vertical::Center)
+                        .push(widget::text(fl!(
+                            "duplicate-webapp",
+                            name = existing.name.as_str()
+                        )))
+                        .push(widget::horizontal_space())
+                        .push(
+                            widget::button::standard(fl!("edit-existing"))
+                                .on_press(Message::EditExisting),
+                        )
+                        .push(
+                            widget::button::standard(fl!("create-anyway"))
+                                .on_press(Message::CreateAnyway),
+                        )
+                }))
+                .push_maybe(self.save_error.as_deref().map(widget::text))
                 .push(
                     widget::row()
                         .spacing(8)
                         .push(widget::horizontal_space())
-                        //.push_maybe(if self.is_installed {
-                        //    Some(
-                        //        widget::button::standard(fl!("run-app"))
-                        //            .on_press(Message::LaunchApp),
-                        //    )
-                        //} else {
-                        //    None
-                        //})
+                        .push_maybe(if self.is_installed {
+                            Some(
+                                widget::button::standard(fl!("run-app"))
+                                    .on_press(Message::LaunchApp),
+                            )
+                        } else {
+                            None
+                        })
                         .push(widget::button::suggested(fl!("create")).on_press_maybe(
                             if webapplauncher_is_valid(
                                 &self.app_icon,
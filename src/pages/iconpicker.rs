@@ -8,24 +8,37 @@ use cosmic::{
 };
 
 use crate::{
-    common::{self, find_icons, get_icon_name_from_url, Icon},
-    fl, icon_pack_installed, pages,
+    common::{
+        self, find_icons_reporting, get_icon_name_from_url, image_handle, Icon, IconOrigin,
+        IconSearchOptions, SearchCancel,
+    },
+    favicon, fl, icon_pack_installed,
+    lettericon::save_letter_icon,
+    pages,
 };
 
 #[derive(Debug, Clone)]
 pub enum Message {
     CustomIconsSearch(String),
     DownloadIconsPack,
+    GenerateIcon,
     OpenIconPickerDialog,
     IconSearch,
+    NamedIconNotFound,
+    RefreshIcons,
     SetIcon(Option<Icon>),
+    UseNamedIcon,
 }
 
 #[derive(Debug, Clone)]
 pub struct IconPicker {
     pub app_url: String,
     pub icon_searching: String,
-    pub icons: Vec<Icon>,
+    pub icons: Vec<(IconOrigin, Icon)>,
+    pub named_icon_missing: bool,
+    // problems met by the last search, shown when it found nothing
+    pub search_warnings: Vec<String>,
+    search_cancel: SearchCancel,
 }
 
 impl IconPicker {
@@ -34,17 +47,49 @@ impl IconPicker {
             app_url,
             icon_searching: String::new(),
             icons: Vec::new(),
+            named_icon_missing: false,
+            search_warnings: Vec::new(),
+            search_cancel: SearchCancel::default(),
         }
     }
 
-    pub fn push_icon(&mut self, icon: Icon) {
-        self.icons.push(icon);
+    pub fn cancel_search(&self) {
+        self.search_cancel.cancel();
+    }
+
+    pub fn push_icon(&mut self, origin: IconOrigin, icon: Icon) {
+        self.icons.push((origin, icon));
     }
 
     pub fn update(&mut self, message: Message) -> Task<pages::Message> {
         match message {
-            Message::CustomIconsSearch(input) => self.icon_searching = input,
+            Message::CustomIconsSearch(input) => {
+                self.icon_searching = input;
+                self.named_icon_missing = false;
+            }
             Message::DownloadIconsPack => return task::message(pages::Message::DownloaderStarted),
+            Message::GenerateIcon => {
+                let name = get_icon_name_from_url(&self.app_url);
+                let app_url = self.app_url.clone();
+
+                let dark = theme::active().cosmic().is_dark;
+
+                return task::future(async move {
+                    let colors = match IconSearchOptions::from_config().offline {
+                        true => None,
+                        false => favicon::manifest_colors(&app_url).await,
+                    };
+                    let icon_name = format!("{}-letter", name);
+
+                    match save_letter_icon(&name, &app_url, &icon_name, colors.as_ref(), dark) {
+                        Ok(path) => pages::Message::SetIcon(image_handle(path).await),
+                        Err(err) => {
+                            tracing::error!("failed to generate icon: {}", err);
+                            pages::Message::None
+                        }
+                    }
+                });
+            }
             Message::OpenIconPickerDialog => {
                 return task::future(async move {
                     let result = SelectedFiles::open_file()
@@ -72,44 +117,97 @@ impl IconPicker {
                     }
                 })
             }
-            Message::IconSearch => {
-                self.icons.clear();
+            Message::IconSearch => return self.search(IconSearchOptions::from_config()),
+            Message::NamedIconNotFound => self.named_icon_missing = true,
+            Message::RefreshIcons => {
+                return self.search(IconSearchOptions::from_config().refresh());
+            }
+            Message::SetIcon(icon) => return task::future(async { pages::Message::SetIcon(icon) }),
+            // the theme is asked for the icon by its name, nothing gets copied
+            Message::UseNamedIcon => {
+                let name = self.icon_searching.trim().to_string();
 
-                let name = match self.icon_searching.is_empty() {
-                    true => get_icon_name_from_url(&self.app_url),
-                    false => self.icon_searching.clone(),
-                };
-                let app_url = self.app_url.clone();
+                return task::future(async move {
+                    let found = match common::named_icon_path(&name) {
+                        Some(path) => image_handle(path.to_string_lossy().to_string()).await,
+                        None => None,
+                    };
 
-                return task::future(async {
-                    pages::Message::IconsResult(find_icons(name, app_url).await)
+                    match found {
+                        Some(mut icon) => {
+                            icon.path = name;
+                            pages::Message::SetIcon(Some(icon))
+                        }
+                        None => {
+                            tracing::warn!("no icon named {:?} in the icon themes", name);
+                            pages::Message::IconPicker(Message::NamedIconNotFound)
+                        }
+                    }
                 });
             }
-            Message::SetIcon(icon) => return task::future(async { pages::Message::SetIcon(icon) }),
         }
 
         Task::none()
     }
 
+    fn search(&mut self, options: IconSearchOptions) -> Task<pages::Message> {
+        self.icons.clear();
+        self.search_warnings.clear();
+
+        let name = match self.icon_searching.is_empty() {
+            true => get_icon_name_from_url(&self.app_url),
+            false => self.icon_searching.clone(),
+        };
+        let app_url = self.app_url.clone();
+
+        // results of the previous search would be mixed with these ones
+        self.cancel_search();
+        self.search_cancel = SearchCancel::default();
+        let cancel = self.search_cancel.clone();
+
+        task::future(async move {
+            pages::Message::IconsSearched(
+                find_icons_reporting(name, app_url, options, cancel).await,
+            )
+        })
+    }
+
     pub fn view(&self) -> Element<Message> {
         let cosmic_theme::Spacing { space_xxs, .. } = theme::active().cosmic().spacing;
 
-        let mut icons: Vec<Element<Message>> = Vec::new();
-
-        for ico in self.icons.iter() {
-            let btn = match ico.clone().icon {
-                common::IconType::Raster(icon) => widget::button::custom(widget::image(icon))
-                    .width(Length::Fixed(48.))
-                    .height(Length::Fixed(48.))
-                    .on_press(Message::SetIcon(Some(ico.clone())))
-                    .class(theme::Button::Icon),
-                common::IconType::Svg(icon) => widget::button::custom(widget::svg(icon))
-                    .width(Length::Fixed(48.))
-                    .height(Length::Fixed(48.))
-                    .on_press(Message::SetIcon(Some(ico.clone())))
-                    .class(theme::Button::Icon),
-            };
-            icons.push(btn.into());
+        // icons are shown in groups by where they were found
+        let mut sections = widget::column().spacing(space_xxs);
+        let origins = [
+            IconOrigin::Favicon,
+            IconOrigin::UserIconsDir,
+            IconOrigin::SystemIconsDir,
+            IconOrigin::Symbolic,
+        ];
+
+        for origin in origins {
+            let mut icons: Vec<Element<Message>> = Vec::new();
+
+            for (_, ico) in self.icons.iter().filter(|(o, _)| *o == origin) {
+                let btn = match ico.clone().icon {
+                    common::IconType::Raster(icon) => widget::button::custom(widget::image(icon))
+                        .width(Length::Fixed(48.))
+                        .height(Length::Fixed(48.))
+                        .on_press(Message::SetIcon(Some(ico.clone())))
+                        .class(theme::Button::Icon),
+                    common::IconType::Svg(icon) => widget::button::custom(widget::svg(icon))
+                        .width(Length::Fixed(48.))
+                        .height(Length::Fixed(48.))
+                        .on_press(Message::SetIcon(Some(ico.clone())))
+                        .class(theme::Button::Icon),
+                };
+                icons.push(btn.into());
+            }
+
+            if !icons.is_empty() {
+                sections = sections
+                    .push(widget::text::heading(origin.name()))
+                    .push(widget::settings::flex_item_row(icons));
+            }
         }
 
         let mut elements: Vec<Element<Message>> = vec![
@@ -117,9 +215,22 @@ impl IconPicker {
                 .on_input(Message::CustomIconsSearch)
                 .on_submit(Message::IconSearch)
                 .into(),
+            widget::button::standard(fl!("use-icon-name"))
+                .on_press_maybe(if self.icon_searching.trim().is_empty() {
+                    None
+                } else {
+                    Some(Message::UseNamedIcon)
+                })
+                .into(),
+            widget::button::standard(fl!("refresh"))
+                .on_press(Message::RefreshIcons)
+                .into(),
             widget::button::standard(fl!("open"))
                 .on_press(Message::OpenIconPickerDialog)
                 .into(),
+            widget::button::standard(fl!("generate-icon"))
+                .on_press(Message::GenerateIcon)
+                .into(),
         ];
         if !icon_pack_installed() {
             elements.push(
@@ -130,13 +241,25 @@ impl IconPicker {
         }
         widget::settings::section()
             .add(widget::settings::item_row(elements))
-            .add_maybe(if !icons.is_empty() {
+            .add_maybe(if self.named_icon_missing {
+                Some(widget::text(fl!(
+                    "named-icon-missing",
+                    name = self.icon_searching.trim()
+                )))
+            } else {
+                None
+            })
+            .add_maybe(
+                if self.icons.is_empty() && !self.search_warnings.is_empty() {
+                    Some(widget::text(self.search_warnings.join("\n")))
+                } else {
+                    None
+                },
+            )
+            .add_maybe(if !self.icons.is_empty() {
                 Some(
-                    widget::container(
-                        widget::scrollable(widget::settings::flex_item_row(icons))
-                            .spacing(space_xxs),
-                    )
-                    .max_height(600.0),
+                    widget::container(widget::scrollable(sections).spacing(space_xxs))
+                        .max_height(600.0),
                 )
             } else {
                 None
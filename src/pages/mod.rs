@@ -1,13 +1,22 @@
 pub mod editor;
 mod iconpicker;
 
+use crate::browser::{detected_browsers, Browser};
 use crate::common::{
-    database_path, find_icon, image_handle, move_icon, qwa_icons_location, themes_path, Icon,
+    find_icon, image_handle, migrate_wrapped_icons, move_icon, qwa_icons_location,
+    remove_menu_folder, themes_path, url_valid, write_menu_folder, Icon, IconFormat, IconOrigin,
+    IconSearchResult, LaunchMethod, SearchCancel,
+};
+use crate::config::{
+    default_tracking_params, AppConfig, DEFAULT_ICON_SEARCH_DEPTH, DEFAULT_MAX_DOWNLOAD_SIZE,
+    DEFAULT_REQUEST_TIMEOUT,
+};
+use crate::launcher::{
+    installed_webapps, parse_profile_dir, parse_window_position, parse_window_size,
+    remove_orphaned_profiles, split_schemes, split_urls, WebAppLauncher, VERSION,
 };
-use crate::config::AppConfig;
-use crate::launcher::{installed_webapps, WebAppLauncher};
 use crate::themes::Theme;
-use crate::{add_icon_packs_install_script, execute_script, APP_ICON, APP_ID, REPOSITORY};
+use crate::{add_icon_packs_install_script, execute_script, APP_ICON, APP_ID, ARGS, REPOSITORY};
 use crate::{fl, pages::iconpicker::IconPicker};
 use ashpd::desktop::file_chooser::{FileFilter, SelectedFiles};
 use cosmic::app::command::set_theme;
@@ -26,22 +35,32 @@ use cosmic::{
 use cosmic::{task, theme};
 use editor::AppEditor;
 use futures_util::SinkExt;
-use ron::ser::to_string_pretty;
 use std::collections::HashMap;
 use std::fs::read_dir;
-use std::io::{Read, Write};
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::process::ExitStatus;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
+use strum::IntoEnumIterator;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::sync::oneshot;
 
+const DOWNLOAD_SIZES_MB: [u64; 5] = [1, 5, 10, 25, 50];
+const ICON_SEARCH_DEPTHS: [usize; 5] = [3, 4, 5, 6, 8];
+
 #[derive(Debug, Clone)]
 pub enum Message {
+    AcceptInvalidIconCerts(bool),
+    ChangeIconFormat(usize),
+    ChangeIconSearchDepth(usize),
+    ChangeLaunchMethod(usize),
+    ChangeMaxDownloadSize(usize),
     ChangeUserTheme(usize),
+    CleanProfiles,
     CloseDialog,
+    CompactIcons(bool),
     Editor(editor::Message),
     Delete(widget::segmented_button::Entity),
     DeletionDone(widget::segmented_button::Entity),
@@ -49,21 +68,34 @@ pub enum Message {
     DownloaderStarted,
     DownloaderStream(String),
     DownloaderStreamFinished,
+    FaviconServiceFallback(bool),
+    HashedIconNames(bool),
     IconPicker(iconpicker::Message),
+    IconsMigrated(Vec<Arc<WebAppLauncher>>, usize),
     IconsResult(Vec<String>),
+    IconsSearched(IconSearchResult),
     ImportThemeFilePicker,
+    IsolateProfiles(bool),
+    KeepOriginalIcons(bool),
     LaunchUrl(String),
     LoadThemes,
+    MenuFolder(bool),
+    MenuFolderName(String),
+    MigrateIcons,
     NavBar(widget::segmented_button::Entity),
+    OfflineMode(bool),
     OpenFileResult(Vec<String>),
     OpenIconPicker(String),
     OpenRepositoryUrl,
     OpenThemeResult(String),
+    ProfilesCleaned(usize),
     ConfirmDeletion(widget::segmented_button::Entity),
     ReloadNavbarItems,
     ResetSettings,
     SaveLauncher(Arc<WebAppLauncher>),
     SetIcon(Option<Icon>),
+    ShowBrowser(String, bool),
+    StripTrackingParams(bool),
     DownloaderStop,
     ToggleContextPage(ContextPage),
     UpdateConfig(AppConfig),
@@ -98,6 +130,13 @@ pub struct QuickWebApps {
     downloader_output: String,
     themes_list: Vec<Theme>,
     theme_idx: Option<usize>,
+    icon_formats: Vec<String>,
+    launch_methods: Vec<String>,
+    download_sizes: Vec<String>,
+    search_depths: Vec<String>,
+    migrated_icons: Option<usize>,
+    cleaned_profiles: Option<usize>,
+    browsers: Vec<Browser>,
 }
 
 impl Application for QuickWebApps {
@@ -122,7 +161,10 @@ impl Application for QuickWebApps {
             Id::unique()
         };
         let config = AppConfig::config();
-        let add_page = Page::Editor(AppEditor::new());
+        let add_page = match &ARGS.url {
+            Some(url) => Page::Editor(AppEditor::prefill_from_url(url, ARGS.title.as_deref())),
+            None => Page::Editor(AppEditor::new()),
+        };
         let nav = nav_bar::Model::default();
 
         let themes_list = Vec::new();
@@ -141,15 +183,32 @@ impl Application for QuickWebApps {
             downloader_output: String::new(),
             themes_list,
             theme_idx: Some(0),
+            icon_formats: IconFormat::iter().map(|f| f.name()).collect(),
+            launch_methods: LaunchMethod::iter().map(|m| m.name()).collect(),
+            download_sizes: DOWNLOAD_SIZES_MB
+                .iter()
+                .map(|size| format!("{} MB", size))
+                .collect(),
+            search_depths: ICON_SEARCH_DEPTHS
+                .iter()
+                .map(|depth| depth.to_string())
+                .collect(),
+            migrated_icons: None,
+            cleaned_profiles: None,
+            browsers: detected_browsers(),
         };
 
-        let tasks = vec![
+        let mut tasks = vec![
             windows.update_title(),
             task::message(Message::ReloadNavbarItems),
             task::message(Message::LoadThemes),
             task::message(Message::UpdateTheme(Box::new(Theme::Light))),
         ];
 
+        if ARGS.url.as_deref().is_some_and(url_valid) {
+            tasks.push(task::message(Message::Editor(editor::Message::VerifyUrl)));
+        }
+
         (windows, task::batch(tasks))
     }
 
@@ -210,13 +269,43 @@ impl Application for QuickWebApps {
         let mut tasks: Vec<Task<Message>> = Vec::new();
 
         match message {
+            Message::ChangeIconFormat(idx) => {
+                if let Some(format) = IconFormat::iter().nth(idx) {
+                    if let Some(handler) = AppConfig::config_handler() {
+                        let _ = self.config.set_icon_format(&handler, format);
+                    };
+                }
+            }
+            Message::ChangeLaunchMethod(idx) => {
+                if let Some(method) = LaunchMethod::iter().nth(idx) {
+                    if let Some(handler) = AppConfig::config_handler() {
+                        let _ = self.config.set_launch_method(&handler, method);
+                    };
+                }
+            }
+            Message::ChangeIconSearchDepth(idx) => {
+                if let Some(depth) = ICON_SEARCH_DEPTHS.get(idx) {
+                    if let Some(handler) = AppConfig::config_handler() {
+                        let _ = self.config.set_icon_search_depth(&handler, *depth);
+                    };
+                }
+            }
+            Message::ChangeMaxDownloadSize(idx) => {
+                if let Some(size) = DOWNLOAD_SIZES_MB.get(idx) {
+                    if let Some(handler) = AppConfig::config_handler() {
+                        let _ = self
+                            .config
+                            .set_max_download_size(&handler, size * 1024 * 1024);
+                    };
+                }
+            }
             Message::ChangeUserTheme(idx) => {
                 self.theme_idx = Some(idx);
                 let selected = self.themes_list[idx].clone();
 
                 tasks.push(task::message(Message::UpdateTheme(Box::new(selected))));
             }
-            Message::CloseDialog => self.dialogs = None,
+            Message::CloseDialog => self.close_dialogs(),
             Message::ConfirmDeletion(id) => {
                 let data = self.nav.data::<Page>(id);
 
@@ -243,13 +332,30 @@ impl Application for QuickWebApps {
                                 codename: app_editor.app_codename.clone(),
                                 browser: browser.clone(),
                                 name: app_editor.app_title.clone(),
+                                window_title: app_editor.app_window_title.clone(),
                                 icon: app_editor.app_icon.clone(),
+                                icon_source: app_editor.app_icon_source.clone(),
                                 category: app_editor.app_category.clone(),
                                 url: app_editor.app_url.clone(),
+                                extra_urls: split_urls(&app_editor.app_extra_urls),
                                 custom_parameters: app_editor.app_parameters.clone(),
                                 isolate_profile: app_editor.app_isolated,
                                 navbar: app_editor.app_navbar,
+                                new_windows_as_tabs: app_editor.app_new_windows_as_tabs,
                                 is_incognito: app_editor.app_incognito,
+                                pwa: app_editor.app_pwa,
+                                profile_source: app_editor.app_profile_source,
+                                single_window: app_editor.app_single_window,
+                                badges: app_editor.app_badges,
+                                window_size: parse_window_size(&app_editor.app_window_size),
+                                maximized: app_editor.app_maximized,
+                                window_position: parse_window_position(
+                                    &app_editor.app_window_position,
+                                ),
+                                url_schemes: split_schemes(&app_editor.app_url_schemes),
+                                profile_dir: parse_profile_dir(&app_editor.app_profile_dir),
+                                version: VERSION.to_string(),
+                                metadata: app_editor.app_metadata.clone(),
                             };
 
                             return task::future(async move {
@@ -262,7 +368,7 @@ impl Application for QuickWebApps {
             }
             Message::DeletionDone(id) => {
                 self.nav.remove(id);
-                self.dialogs = None;
+                self.close_dialogs();
                 self.page = Page::Editor(AppEditor::new())
             }
             Message::DownloaderDone => {
@@ -270,7 +376,7 @@ impl Application for QuickWebApps {
                 return task::message(Message::CloseDialog);
             }
             Message::DownloaderStarted => {
-                self.dialogs = None;
+                self.close_dialogs();
                 self.downloader_started = true;
                 self.dialogs = Some(Dialogs::IconsDownloader)
             }
@@ -302,7 +408,28 @@ impl Application for QuickWebApps {
                 if let Some(Dialogs::IconPicker(icon_picker)) = &mut self.dialogs {
                     for path in result {
                         if let Some(icon) = block_on(image_handle(path)) {
-                            icon_picker.push_icon(icon);
+                            icon_picker.push_icon(IconOrigin::UserIconsDir, icon);
+                        }
+                    }
+                };
+            }
+            Message::IconsSearched(result) => {
+                // a newer search replaced this one, or the picker was closed
+                if result.cancelled {
+                    return Task::none();
+                }
+
+                if let Some(Dialogs::IconPicker(icon_picker)) = &mut self.dialogs {
+                    icon_picker.search_warnings = result.warnings;
+
+                    for found in result.icons {
+                        let name = found.theme_name();
+
+                        if let Some(mut icon) = block_on(image_handle(found.path)) {
+                            if let Some(name) = name {
+                                icon.path = name;
+                            }
+                            icon_picker.push_icon(found.origin, icon);
                         }
                     }
                 };
@@ -354,10 +481,9 @@ impl Application for QuickWebApps {
                     self.themes_list.push(Theme::Dark);
                 }
 
-                let folder = themes_path("");
-                let dir = read_dir(folder);
+                let dir = themes_path("").map(read_dir);
 
-                if let Ok(files) = dir {
+                if let Ok(Ok(files)) = dir {
                     for path in files {
                         let dir_entry = path.unwrap();
                         let file_name = dir_entry.file_name();
@@ -406,14 +532,31 @@ impl Application for QuickWebApps {
                         let icon_name = buf.file_stem();
 
                         if let Some(file_stem) = icon_name {
-                            move_icon(&path, file_stem.to_str().unwrap()).await;
+                            if let Err(err) =
+                                move_icon(&path, file_stem.to_str().unwrap(), IconFormat::Svg).await
+                            {
+                                tracing::error!("failed to import icon {}: {}", path, err);
+                            }
                         };
                     }
 
-                    Message::IconsResult(find_icon(qwa_icons_location(), String::new()).await)
+                    match qwa_icons_location() {
+                        Ok(location) => {
+                            let depth = AppConfig::config().icon_search_depth;
+                            let icons =
+                                find_icon(location, String::new(), depth, &SearchCancel::default())
+                                    .await;
+                            Message::IconsResult(icons)
+                        }
+                        Err(err) => {
+                            tracing::error!("{}", err);
+                            Message::None
+                        }
+                    }
                 })
             }
             Message::OpenIconPicker(app_url) => {
+                self.close_dialogs();
                 self.dialogs = Some(Dialogs::IconPicker(IconPicker::new(app_url)));
             }
             Message::OpenRepositoryUrl => {
@@ -424,9 +567,12 @@ impl Application for QuickWebApps {
                     let from_path = Path::new(&theme);
                     if let Some(file_name) = from_path.file_name() {
                         let file_name = file_name.to_string_lossy();
-                        let destination = themes_path(&file_name);
-                        if !destination.exists() {
-                            let _ = std::fs::copy(from_path, destination);
+                        match themes_path(&file_name) {
+                            Ok(destination) if !destination.exists() => {
+                                let _ = std::fs::copy(from_path, destination);
+                            }
+                            Ok(_) => (),
+                            Err(err) => tracing::warn!("can't import theme: {}", err),
                         }
                     }
                 }
@@ -454,31 +600,199 @@ impl Application for QuickWebApps {
 
                 self.page = Page::Editor(AppEditor::new());
             }
+            Message::CleanProfiles => {
+                return task::future(async {
+                    Message::ProfilesCleaned(remove_orphaned_profiles().await)
+                });
+            }
+            Message::ProfilesCleaned(count) => {
+                self.cleaned_profiles = Some(count);
+            }
+            Message::MigrateIcons => {
+                let format = self.config.icon_format;
+
+                return task::future(async move {
+                    let migrated = match migrate_wrapped_icons(format) {
+                        Ok(migrated) => migrated,
+                        Err(err) => {
+                            tracing::error!("icons migration failed: {}", err);
+                            Vec::new()
+                        }
+                    };
+
+                    let mut launchers = Vec::new();
+
+                    for mut launcher in installed_webapps() {
+                        let Some((_, icon)) =
+                            migrated.iter().find(|(old, _)| *old == launcher.icon)
+                        else {
+                            continue;
+                        };
+
+                        launcher.icon = icon.clone();
+
+                        match launcher.create().await {
+                            Ok(()) => launchers.push(Arc::new(launcher)),
+                            Err(err) => {
+                                tracing::error!("failed to update {}: {}", launcher.name, err)
+                            }
+                        }
+                    }
+
+                    Message::IconsMigrated(launchers, migrated.len())
+                });
+            }
+            Message::IconsMigrated(launchers, count) => {
+                self.migrated_icons = Some(count);
+
+                return task::batch(
+                    launchers
+                        .into_iter()
+                        .map(|launcher| task::message(Message::SaveLauncher(launcher))),
+                );
+            }
             Message::ResetSettings => {
                 if let Some(handler) = AppConfig::config_handler() {
                     let _ = self.config.set_app_theme(&handler, String::new());
+                    let _ = self.config.set_icon_format(&handler, IconFormat::default());
+                    let _ = self.config.set_hashed_icon_names(&handler, false);
+                    let _ = self.config.set_keep_original_icons(&handler, false);
+                    let _ = self.config.set_compact_icons(&handler, false);
+                    let _ = self.config.set_offline_mode(&handler, false);
+                    let _ = self.config.set_favicon_service_fallback(&handler, false);
+                    let _ = self.config.set_accept_invalid_icon_certs(&handler, false);
+                    let _ = self.config.set_isolate_profiles(&handler, true);
+                    let _ = self
+                        .config
+                        .set_launch_method(&handler, LaunchMethod::default());
+                    let _ = self.config.set_menu_folder(&handler, false);
+                    let _ = self.config.set_menu_folder_name(&handler, String::new());
+                    let _ = self
+                        .config
+                        .set_max_download_size(&handler, DEFAULT_MAX_DOWNLOAD_SIZE);
+                    let _ = self
+                        .config
+                        .set_request_timeout(&handler, DEFAULT_REQUEST_TIMEOUT);
+                    let _ = self
+                        .config
+                        .set_icon_search_depth(&handler, DEFAULT_ICON_SEARCH_DEPTH);
+                    let _ = self.config.set_strip_tracking_params(&handler, false);
+                    let _ = self
+                        .config
+                        .set_tracking_params(&handler, default_tracking_params());
+                    let _ = self.config.set_hidden_browsers(&handler, Vec::new());
                 };
 
                 return set_theme(cosmic::Theme::light());
             }
-            Message::SaveLauncher(launcher) => {
-                let location = database_path(&format!("{}.ron", launcher.codename));
-                let content = to_string_pretty(&*launcher, ron::ser::PrettyConfig::default());
+            Message::AcceptInvalidIconCerts(flag) => {
+                if let Some(handler) = AppConfig::config_handler() {
+                    let _ = self.config.set_accept_invalid_icon_certs(&handler, flag);
+                };
+            }
+            Message::FaviconServiceFallback(flag) => {
+                if let Some(handler) = AppConfig::config_handler() {
+                    let _ = self.config.set_favicon_service_fallback(&handler, flag);
+                };
+            }
+            Message::HashedIconNames(flag) => {
+                if let Some(handler) = AppConfig::config_handler() {
+                    let _ = self.config.set_hashed_icon_names(&handler, flag);
+                };
+            }
+            Message::IsolateProfiles(flag) => {
+                if let Some(handler) = AppConfig::config_handler() {
+                    let _ = self.config.set_isolate_profiles(&handler, flag);
+                };
+            }
+            Message::CompactIcons(flag) => {
+                if let Some(handler) = AppConfig::config_handler() {
+                    let _ = self.config.set_compact_icons(&handler, flag);
+                };
+            }
+            Message::MenuFolder(flag) => {
+                if let Some(handler) = AppConfig::config_handler() {
+                    let _ = self.config.set_menu_folder(&handler, flag);
+                };
+
+                if flag {
+                    if let Err(err) = write_menu_folder(&self.menu_folder_name()) {
+                        tracing::error!("failed to create the menu folder: {}", err);
+                    }
+                } else {
+                    remove_menu_folder();
+                }
+
+                // web apps join or leave the folder through their categories
+                return task::future(async {
+                    for launcher in installed_webapps() {
+                        if let Err(err) = launcher.create().await {
+                            tracing::error!("failed to update {}: {}", launcher.name, err);
+                        }
+                    }
 
-                if let Ok(content) = content {
-                    let file = std::fs::File::create(location);
+                    Message::None
+                });
+            }
+            Message::MenuFolderName(name) => {
+                if let Some(handler) = AppConfig::config_handler() {
+                    let _ = self.config.set_menu_folder_name(&handler, name);
+                };
 
-                    if let Ok(mut f) = file {
-                        let _ = f.write_all(content.as_bytes());
+                if self.config.menu_folder {
+                    if let Err(err) = write_menu_folder(&self.menu_folder_name()) {
+                        tracing::error!("failed to rename the menu folder: {}", err);
                     }
                 }
+            }
+            Message::KeepOriginalIcons(flag) => {
+                if let Some(handler) = AppConfig::config_handler() {
+                    let _ = self.config.set_keep_original_icons(&handler, flag);
+                };
+            }
+            Message::ShowBrowser(id, show) => {
+                let mut hidden = self.config.hidden_browsers.clone();
+                hidden.retain(|hidden| *hidden != id);
+
+                if !show {
+                    let users = installed_webapps()
+                        .iter()
+                        .filter(|app| app.browser.id().is_some_and(|app_id| app_id.as_ref() == id))
+                        .count();
+
+                    // those web apps keep launching, only new ones can't pick it
+                    if users > 0 {
+                        tracing::info!("hiding browser {} still used by {} web apps", id, users);
+                    }
+
+                    hidden.push(id);
+                }
+
+                if let Some(handler) = AppConfig::config_handler() {
+                    let _ = self.config.set_hidden_browsers(&handler, hidden);
+                };
+            }
+            Message::StripTrackingParams(flag) => {
+                if let Some(handler) = AppConfig::config_handler() {
+                    let _ = self.config.set_strip_tracking_params(&handler, flag);
+                };
+            }
+            Message::OfflineMode(flag) => {
+                if let Some(handler) = AppConfig::config_handler() {
+                    let _ = self.config.set_offline_mode(&handler, flag);
+                };
+            }
+            Message::SaveLauncher(launcher) => {
+                if let Err(err) = launcher.save_record() {
+                    tracing::error!("failed to save {}: {}", launcher.codename, err);
+                }
 
                 return task::message(Message::ReloadNavbarItems);
             }
             Message::SetIcon(icon) => {
                 let Page::Editor(app_editor) = &mut self.page;
                 app_editor.update_icon(icon);
-                self.dialogs = None;
+                self.close_dialogs();
             }
             Message::ToggleContextPage(context_page) => {
                 if self.context_page == context_page {
@@ -595,7 +909,7 @@ impl Application for QuickWebApps {
     }
 
     fn on_escape(&mut self) -> Task<Message> {
-        self.dialogs = None;
+        self.close_dialogs();
         self.core.window.show_context = false;
 
         Task::none()
@@ -718,16 +1032,171 @@ impl QuickWebApps {
                             Message::ChangeUserTheme,
                         ),
                     ))
+                    .add(widget::settings::item(
+                        fl!("icon-format"),
+                        widget::dropdown(
+                            &self.icon_formats,
+                            IconFormat::iter().position(|f| f == self.config.icon_format),
+                            Message::ChangeIconFormat,
+                        ),
+                    ))
+                    .add(widget::settings::item(
+                        fl!("launch-method"),
+                        widget::dropdown(
+                            &self.launch_methods,
+                            LaunchMethod::iter().position(|m| m == self.config.launch_method),
+                            Message::ChangeLaunchMethod,
+                        ),
+                    ))
+                    .add(widget::settings::item(
+                        fl!("hashed-icon-names"),
+                        widget::toggler(self.config.hashed_icon_names)
+                            .on_toggle(Message::HashedIconNames),
+                    ))
+                    .add(widget::settings::item(
+                        fl!("keep-original-icons"),
+                        widget::toggler(self.config.keep_original_icons)
+                            .on_toggle(Message::KeepOriginalIcons),
+                    ))
+                    .add_maybe(if self.config.icon_format == IconFormat::Png {
+                        widget::settings::item(
+                            fl!("compact-icons"),
+                            widget::toggler(self.config.compact_icons)
+                                .on_toggle(Message::CompactIcons),
+                        )
+                        .into()
+                    } else {
+                        None
+                    })
+                    .add(widget::settings::item(
+                        fl!("isolate-profiles"),
+                        widget::toggler(self.config.isolate_profiles)
+                            .on_toggle(Message::IsolateProfiles),
+                    ))
+                    .add(widget::settings::item(
+                        fl!("menu-folder"),
+                        widget::toggler(self.config.menu_folder).on_toggle(Message::MenuFolder),
+                    ))
+                    .add_maybe(if self.config.menu_folder {
+                        widget::text_input::inline_input(
+                            fl!("web-apps"),
+                            &self.config.menu_folder_name,
+                        )
+                        .on_input(Message::MenuFolderName)
+                        .into()
+                    } else {
+                        None
+                    })
+                    .add(widget::settings::item(
+                        fl!("strip-tracking-params"),
+                        widget::toggler(self.config.strip_tracking_params)
+                            .on_toggle(Message::StripTrackingParams),
+                    ))
+                    .add(widget::settings::item(
+                        fl!("offline-mode"),
+                        widget::toggler(self.config.offline_mode).on_toggle(Message::OfflineMode),
+                    ))
+                    .add(widget::settings::item(
+                        fl!("favicon-service-fallback"),
+                        widget::toggler(self.config.favicon_service_fallback)
+                            .on_toggle(Message::FaviconServiceFallback),
+                    ))
+                    .add(widget::settings::item(
+                        fl!("accept-invalid-icon-certs"),
+                        widget::toggler(self.config.accept_invalid_icon_certs)
+                            .on_toggle(Message::AcceptInvalidIconCerts),
+                    ))
+                    .add(widget::settings::item(
+                        fl!("max-download-size"),
+                        widget::dropdown(
+                            &self.download_sizes,
+                            DOWNLOAD_SIZES_MB.iter().position(|size| {
+                                size * 1024 * 1024 == self.config.max_download_size
+                            }),
+                            Message::ChangeMaxDownloadSize,
+                        ),
+                    ))
+                    .add(widget::settings::item(
+                        fl!("icon-search-depth"),
+                        widget::dropdown(
+                            &self.search_depths,
+                            ICON_SEARCH_DEPTHS
+                                .iter()
+                                .position(|depth| *depth == self.config.icon_search_depth),
+                            Message::ChangeIconSearchDepth,
+                        ),
+                    ))
+                    .add(widget::settings::item(
+                        fl!("migrate-icons"),
+                        widget::row()
+                            .spacing(8)
+                            .align_y(Alignment::Center)
+                            .push_maybe(
+                                self.migrated_icons.map(|count| {
+                                    widget::text(fl!("migrated-icons", count = count))
+                                }),
+                            )
+                            .push(
+                                widget::button::standard(fl!("migrate"))
+                                    .on_press(Message::MigrateIcons),
+                            ),
+                    ))
+                    .add(widget::settings::item(
+                        fl!("clean-profiles"),
+                        widget::row()
+                            .spacing(8)
+                            .align_y(Alignment::Center)
+                            .push_maybe(
+                                self.cleaned_profiles.map(|count| {
+                                    widget::text(fl!("cleaned-profiles", count = count))
+                                }),
+                            )
+                            .push(
+                                widget::button::standard(fl!("clean"))
+                                    .on_press(Message::CleanProfiles),
+                            ),
+                    ))
                     .add(widget::settings::item(
                         fl!("reset-settings"),
                         widget::button::standard(fl!("reset")).on_press(Message::ResetSettings),
                     )),
             )
+            .push(self.browsers.iter().fold(
+                widget::settings::section().title(fl!("shown-browsers")),
+                |section, browser| {
+                    match browser.id() {
+                        Some(id) => section.add(widget::settings::item(
+                            &browser.name,
+                            widget::toggler(!browser.is_hidden(&self.config.hidden_browsers))
+                                .on_toggle(move |show| {
+                                    Message::ShowBrowser(id.as_ref().to_string(), show)
+                                }),
+                        )),
+                        None => section,
+                    }
+                },
+            ))
             .align_x(Alignment::Center)
             .spacing(space_xxs)
             .into()
     }
 
+    // an icon search still running for the closed picker is stopped
+    fn close_dialogs(&mut self) {
+        if let Some(Dialogs::IconPicker(icon_picker)) = &self.dialogs {
+            icon_picker.cancel_search();
+        }
+
+        self.dialogs = None;
+    }
+
+    fn menu_folder_name(&self) -> String {
+        match self.config.menu_folder_name.trim() {
+            "" => fl!("web-apps"),
+            name => name.to_string(),
+        }
+    }
+
     fn update_title(&mut self) -> Task<Message> {
         self.set_header_title(fl!("app"));
         self.set_window_title(fl!("app"), self.window_id)
@@ -0,0 +1,337 @@
+// Helpers shared by the unit tests, which must never read or write the
+// user's web apps, icons or settings.
+
+use once_cell::sync::Lazy;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+use tempfile::TempDir;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use crate::config::AppConfig;
+
+thread_local! {
+    static CONFIG: RefCell<Option<AppConfig>> = const { RefCell::new(None) };
+    static FAVICON_SERVICE: RefCell<Option<String>> = const { RefCell::new(None) };
+    static DECODES: RefCell<usize> = const { RefCell::new(0) };
+    static BREW_PREFIX: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+// Settings returned by `AppConfig::config()` on the current thread, which
+// runs only one test. Other threads, and tests not setting any, get the defaults.
+pub fn set_config(config: AppConfig) {
+    CONFIG.with(|current| *current.borrow_mut() = Some(config));
+}
+
+pub fn config() -> AppConfig {
+    CONFIG.with(|current| current.borrow().clone().unwrap_or_default())
+}
+
+// Favicon service asked on the current thread instead of the public one.
+pub fn set_favicon_service(url: String) {
+    FAVICON_SERVICE.with(|current| *current.borrow_mut() = Some(url));
+}
+
+pub fn favicon_service() -> Option<String> {
+    FAVICON_SERVICE.with(|current| current.borrow().clone())
+}
+
+// `$HOMEBREW_PREFIX` seen by `common::brew_prefixes` on the current thread.
+pub fn set_brew_prefix(prefix: PathBuf) {
+    BREW_PREFIX.with(|current| *current.borrow_mut() = Some(prefix));
+}
+
+pub fn brew_prefix() -> Option<PathBuf> {
+    BREW_PREFIX.with(|current| current.borrow().clone())
+}
+
+// Icons decoded on the current thread to find their dimensions.
+pub fn count_decode() {
+    DECODES.with(|decodes| *decodes.borrow_mut() += 1);
+}
+
+pub fn decodes() -> usize {
+    DECODES.with(|decodes| *decodes.borrow())
+}
+
+// Data directory of tests which don't set up their own.
+static SHARED_ROOT: Lazy<TempDir> =
+    Lazy::new(|| TempDir::new().expect("can't create the test data directory"));
+static DATA_ROOT: Mutex<Option<PathBuf>> = Mutex::new(None);
+static DATA_LOCK: Mutex<()> = Mutex::new(());
+
+// Empty data directory used by `DataPaths::current()` while it's alive. Tests
+// holding one run one at a time, so they see only the web apps they made.
+pub struct DataRoot {
+    dir: TempDir,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl DataRoot {
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+impl Drop for DataRoot {
+    fn drop(&mut self) {
+        *DATA_ROOT.lock().unwrap_or_else(|err| err.into_inner()) = None;
+    }
+}
+
+pub fn data_root() -> DataRoot {
+    // a failed test poisons the lock, the next one can still run
+    let lock = DATA_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let dir = TempDir::new().expect("can't create the test data directory");
+    *DATA_ROOT.lock().unwrap_or_else(|err| err.into_inner()) = Some(dir.path().to_path_buf());
+
+    DataRoot { dir, _lock: lock }
+}
+
+pub fn data_dir() -> PathBuf {
+    DATA_ROOT
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone()
+        .unwrap_or_else(|| SHARED_ROOT.path().to_path_buf())
+}
+
+// Answer of the mock server to one path.
+#[derive(Debug, Clone)]
+pub struct Route {
+    status: u16,
+    content_type: String,
+    body: Vec<u8>,
+    // waited after the headers, before the body is sent
+    delay: Option<Duration>,
+    // the body then ends only when the connection is closed
+    without_length: bool,
+    location: Option<String>,
+}
+
+impl Route {
+    pub fn ok(content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: 200,
+            content_type: content_type.to_string(),
+            body: body.into(),
+            delay: None,
+            without_length: false,
+            location: None,
+        }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            content_type: String::from("text/plain"),
+            body: Vec::new(),
+            delay: None,
+            without_length: false,
+            location: None,
+        }
+    }
+
+    pub fn redirect(location: &str) -> Self {
+        Self {
+            location: Some(location.to_string()),
+            ..Self::status(302)
+        }
+    }
+
+    pub fn slow(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    pub fn without_length(mut self) -> Self {
+        self.without_length = true;
+        self
+    }
+}
+
+// HTTP server on localhost answering from fixed routes, 404 for anything
+// else. Routes are keyed by path, or by "HEAD /path" to answer one method
+// differently. Requests are recorded as "GET /path" to check what was asked.
+pub struct MockServer {
+    addr: SocketAddr,
+    scheme: &'static str,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    pub async fn start(routes: Vec<(&str, Route)>) -> Self {
+        Self::listen(routes, None).await
+    }
+
+    // Same over HTTPS, with a self-signed certificate for 127.0.0.1.
+    pub async fn start_tls(routes: Vec<(&str, Route)>) -> Self {
+        let identity = native_tls::Identity::from_pkcs8(
+            include_bytes!("../data/testing/self-signed.crt"),
+            include_bytes!("../data/testing/self-signed.key"),
+        )
+        .unwrap();
+        let acceptor = native_tls::TlsAcceptor::new(identity).unwrap();
+
+        Self::listen(routes, Some(acceptor.into())).await
+    }
+
+    async fn listen(
+        routes: Vec<(&str, Route)>,
+        tls: Option<tokio_native_tls::TlsAcceptor>,
+    ) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let routes: Arc<HashMap<String, Route>> = Arc::new(
+            routes
+                .into_iter()
+                .map(|(path, route)| (path.to_string(), route))
+                .collect(),
+        );
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let scheme = match tls {
+            Some(_) => "https",
+            None => "http",
+        };
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (routes, recorded) = (routes.clone(), recorded.clone());
+
+                match tls.clone() {
+                    Some(tls) => {
+                        tokio::spawn(async move {
+                            // clients refusing the certificate end the handshake
+                            if let Ok(stream) = tls.accept(stream).await {
+                                serve(stream, routes, recorded).await;
+                            }
+                        });
+                    }
+                    None => {
+                        tokio::spawn(serve(stream, routes, recorded));
+                    }
+                }
+            }
+        });
+
+        Self {
+            addr,
+            scheme,
+            requests,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}://{}{}", self.scheme, self.addr, path)
+    }
+
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn serve(
+    mut stream: impl AsyncRead + AsyncWrite + Unpin,
+    routes: Arc<HashMap<String, Route>>,
+    requests: Arc<Mutex<Vec<String>>>,
+) {
+    let mut head = Vec::new();
+    let mut buffer = [0; 1024];
+
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        match stream.read(&mut buffer).await {
+            Ok(0) | Err(_) => return,
+            Ok(read) => head.extend_from_slice(&buffer[..read]),
+        }
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    requests
+        .lock()
+        .unwrap()
+        .push(format!("{} {}", method, path));
+
+    let route = routes
+        .get(&format!("{} {}", method, path))
+        .or_else(|| routes.get(&path))
+        .cloned()
+        .unwrap_or_else(|| Route::status(404));
+    let mut headers = String::new();
+    if !route.without_length {
+        headers.push_str(&format!("Content-Length: {}\r\n", route.body.len()));
+    }
+    if let Some(location) = &route.location {
+        headers.push_str(&format!("Location: {}\r\n", location));
+    }
+    let header = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: {}\r\n{}Connection: close\r\n\r\n",
+        route.status, route.content_type, headers
+    );
+
+    if stream.write_all(header.as_bytes()).await.is_err() {
+        return;
+    }
+
+    if let Some(delay) = route.delay {
+        let _ = stream.flush().await;
+        tokio::time::sleep(delay).await;
+    }
+
+    if method != "HEAD" {
+        let _ = stream.write_all(&route.body).await;
+    }
+
+    let _ = stream.shutdown().await;
+}
+
+// Log output of the current thread, at every level, while it's alive.
+pub struct LogCapture {
+    output: Arc<Mutex<Vec<u8>>>,
+    _guard: tracing::subscriber::DefaultGuard,
+}
+
+impl LogCapture {
+    pub fn output(&self) -> String {
+        String::from_utf8_lossy(&self.output.lock().unwrap()).to_string()
+    }
+}
+
+struct CaptureWriter(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for CaptureWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn capture_logs() -> LogCapture {
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let writer = output.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::TRACE)
+        .with_writer(move || CaptureWriter(writer.clone()))
+        .finish();
+
+    LogCapture {
+        output,
+        _guard: tracing::subscriber::set_default(subscriber),
+    }
+}